@@ -1,6 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use geo::{Coordinate, Line};
-use rand;
 use rand::Rng;
 use wkt::ToWkt;
 
@@ -0,0 +1,128 @@
+use std::convert::TryInto;
+
+#[cfg(feature = "parallel")]
+use std::collections::hash_map::{Entry, HashMap};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{CoordFloat, Error, Interaction, SJoinRow, SpatialIndex, SplitGeoSeq};
+
+#[cfg(feature = "parallel")]
+use crate::{Par, ParSpatialIndex};
+
+// The results are indexed by `small_index`, so they run up to the
+// next index `insert` would hand out; removed geometries keep the
+// initial value.
+impl<C: CoordFloat> SpatialIndex<'_, C> {
+    /// Counts, for each small geometry, the elements of `big` it
+    /// joins with. Element `i` of the result is the number of rows
+    /// `spatial_join` would return with `small_index == i`.
+    pub fn count_by_small<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<Vec<usize>, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        self.fold_by_small(big, interaction, 0, |count, _| *count += 1)
+    }
+
+    /// Folds the rows of `spatial_join` into one accumulator per small
+    /// geometry, each starting as a clone of `init`. Element `i` of
+    /// the result has seen every row with `small_index == i`, so
+    /// summing an attribute of `big` is
+    /// `fold_by_small(big, interaction, 0., |sum, row| *sum += weights[row.big_index])`.
+    pub fn fold_by_small<'a, T, U, A, F>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+        init: A,
+        mut fold: F,
+    ) -> Result<Vec<A>, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+        A: Clone,
+        F: FnMut(&mut A, SJoinRow),
+    {
+        let mut acc = vec![init; self.next_index];
+        for row in self.spatial_join(big, interaction)? {
+            fold(&mut acc[row.small_index], row);
+        }
+        Ok(acc)
+    }
+}
+
+// Each rayon job folds into its own map holding just the small
+// geometries its rows touched, so a job costs nothing for the ones it
+// never sees. The maps get merged and then spread into the result
+// once, and no rows are ever collected.
+#[cfg(feature = "parallel")]
+impl<C: CoordFloat> ParSpatialIndex<'_, C> {
+    /// Counts, for each small geometry, the elements of `big` it
+    /// joins with. Element `i` of the result is the number of rows
+    /// `spatial_join` would return with `small_index == i`.
+    pub fn count_by_small<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<Vec<usize>, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        self.fold_by_small(
+            big,
+            interaction,
+            0,
+            |count, _| *count += 1,
+            |count, other| *count += other,
+        )
+    }
+
+    /// Like `SpatialIndex::fold_by_small`, but rows are folded on
+    /// several threads at once, so `merge` has to combine two
+    /// accumulators for the same small geometry.
+    pub fn fold_by_small<'a, T, U, A, F, M>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+        init: A,
+        fold: F,
+        merge: M,
+    ) -> Result<Vec<A>, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+        A: Clone + Send + Sync,
+        F: Fn(&mut A, SJoinRow) + Send + Sync,
+        M: Fn(&mut A, A) + Send + Sync,
+    {
+        let sparse = self
+            .spatial_join(big, interaction)?
+            .fold(HashMap::new, |mut acc: HashMap<usize, A>, row| {
+                let a = acc.entry(row.small_index).or_insert_with(|| init.clone());
+                fold(a, row);
+                acc
+            })
+            .reduce(HashMap::new, |mut acc, other| {
+                for (index, b) in other {
+                    match acc.entry(index) {
+                        Entry::Occupied(mut a) => merge(a.get_mut(), b),
+                        Entry::Vacant(a) => {
+                            a.insert(b);
+                        }
+                    }
+                }
+                acc
+            });
+        let mut acc = vec![init; self.0.next_index];
+        for (index, a) in sparse {
+            acc[index] = a;
+        }
+        Ok(acc)
+    }
+}
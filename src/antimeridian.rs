@@ -0,0 +1,228 @@
+use geo::algorithm::map_coords::MapCoordsInplace;
+use geo::algorithm::translate::Translate;
+use smallvec::SmallVec;
+
+use crate::de9im::{Parts, Topology};
+use crate::rtrees::{Envelope, RTreeEnvelope};
+use crate::structs::Stored;
+use crate::{Config, CoordFloat, SplitGeoSeq};
+
+// With `Config::geographic` set, any geometry with an edge spanning
+// more than 180 degrees of longitude is taken to cross the
+// antimeridian rather than go the long way round. We shift its
+// western half east by 360 degrees so every geometry is continuous
+// and lies somewhere in [-180, 360), and then compare copies of the
+// small geometries shifted by whole turns: a small geometry matches a
+// big one if it matches once the two are lined up across the
+// antimeridian.
+impl<'a, C: CoordFloat> SplitGeoSeq<'a, C> {
+    pub(crate) fn prepare(mut self, config: &Config) -> SplitGeoSeq<'a, C> {
+        if config.geographic {
+            unwrap_all(&mut self.geos.points);
+            unwrap_all(&mut self.geos.lines);
+            unwrap_all(&mut self.geos.polys);
+            unwrap_all(&mut self.geos.line_strings);
+            unwrap_all(&mut self.geos.rects);
+            unwrap_all(&mut self.geos.tris);
+            unwrap_all(&mut self.geos.multi_points);
+            unwrap_all(&mut self.geos.multi_line_strings);
+            unwrap_all(&mut self.geos.multi_polys);
+        }
+        self
+    }
+}
+
+// A borrowed geometry only gets cloned if it needs unwrapping.
+pub(crate) fn unwrap_all<'a, S>(geos: &mut [S])
+where
+    S: Stored<'a>,
+    S::Geo: Envelope<S::Coord> + Topology + MapCoordsInplace<S::Coord>,
+{
+    for geo in geos.iter_mut() {
+        // Only a geometry more than 180 degrees wide needs unwrapping,
+        // and looking any closer is expensive.
+        let env = geo.geo().to_env();
+        if (env.upper()[0] - env.lower()[0]).as_f64() > 180. && needs_unwrapping(&geo.geo().parts())
+        {
+            geo.geo_mut().map_coords_inplace(|&(x, y)| (unwrap(x), y));
+        }
+    }
+}
+
+fn unwrap<C: CoordFloat>(x: C) -> C {
+    if x < C::zero() {
+        x + C::from_f64(360.)
+    } else {
+        x
+    }
+}
+
+// We unwrap when that gets rid of every edge longer than 180 degrees
+// without adding any. Failing that, a geometry whose edges are all
+// short (or that has none, like a MultiPoint) may still be made of
+// parts on either side of the antimeridian, so we also unwrap when
+// it makes the geometry narrower.
+fn needs_unwrapping(parts: &Parts) -> bool {
+    let long = |a: f64, b: f64| (a - b).abs() > 180.;
+    let segments = parts.segments();
+    if segments
+        .iter()
+        .any(|s| long(unwrap(s.start.x), unwrap(s.end.x)))
+    {
+        return false;
+    }
+    if segments.iter().any(|s| long(s.start.x, s.end.x)) {
+        return true;
+    }
+    let width = |xs: &mut dyn Iterator<Item = f64>| {
+        let (min, max) = xs.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+            (min.min(x), max.max(x))
+        });
+        max - min
+    };
+    width(&mut parts.vertices().map(|c| unwrap(c.x))) < width(&mut parts.vertices().map(|c| c.x))
+}
+
+/// The copies of `env` (each with the number of turns it was shifted
+/// by) that between them cover everything `env` covers once
+/// longitudes wrap around.
+pub(crate) fn copies<C: CoordFloat>(
+    env: RTreeEnvelope<C>,
+) -> SmallVec<[(RTreeEnvelope<C>, i32); 3]> {
+    let (lower, upper) = (env.lower(), env.upper());
+    let half_turn = C::from_f64(180.);
+    let mut result = SmallVec::new();
+    if upper[0] - lower[0] >= half_turn + half_turn {
+        result.push((
+            RTreeEnvelope::from_corners([-half_turn, lower[1]], [half_turn, upper[1]]),
+            0,
+        ));
+        return result;
+    }
+    result.push((env, 0));
+    if upper[0] > half_turn {
+        result.push((shift_env(&env, -1), -1));
+    }
+    if lower[0] < -half_turn {
+        result.push((shift_env(&env, 1), 1));
+    }
+    result
+}
+
+fn shift_env<C: CoordFloat>(env: &RTreeEnvelope<C>, turns: i32) -> RTreeEnvelope<C> {
+    let dx = C::from_f64(360. * turns as f64);
+    RTreeEnvelope::from_corners(
+        [env.lower()[0] + dx, env.lower()[1]],
+        [env.upper()[0] + dx, env.upper()[1]],
+    )
+}
+
+/// `geo` moved east by `turns` whole turns; `slot` holds the copy
+/// when we need one.
+pub(crate) fn shifted<'a, C, G>(geo: &'a G, turns: i32, slot: &'a mut Option<G>) -> &'a G
+where
+    C: CoordFloat,
+    G: Translate<C>,
+{
+    if turns == 0 {
+        geo
+    } else {
+        slot.get_or_insert_with(|| geo.translate(C::from_f64(360. * turns as f64), C::zero()))
+    }
+}
+
+// Lets us hand back either rstar's lazy search or a buffer of
+// deduplicated hits from a single function. The buffer lives on the
+// heap: the joins nest a lot of these, and an inline one would bloat
+// every search, wrapped or not.
+pub(crate) enum Candidates<I> {
+    Direct(I),
+    Collected(std::vec::IntoIter<(usize, i32)>),
+}
+
+impl<I: Iterator<Item = (usize, i32)>> Iterator for Candidates<I> {
+    type Item = (usize, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Candidates::Direct(iter) => iter.next(),
+            Candidates::Collected(iter) => iter.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Line, Point, Rect};
+    use std::borrow::Cow;
+    use std::convert::TryInto;
+
+    #[test]
+    fn unwrap_crossing_geometries() {
+        let config = Config::new().geographic(true);
+        let geos = vec![
+            geo::Geometry::Line(Line::new((179., 0.), (-179., 1.))),
+            geo::Geometry::Line(Line::new((-100., 0.), (100., 1.))),
+            geo::Geometry::LineString(vec![(-100., 0.), (0., 0.), (100., 1.)].into()),
+            geo::Geometry::Rect(Rect::new((-179., 0.), (179., 1.))),
+            geo::Geometry::Point(Point::new(-179., 0.)),
+            geo::Geometry::MultiPoint(vec![Point::new(-179., 0.), Point::new(179., 0.)].into()),
+            geo::Geometry::MultiPoint(
+                vec![
+                    Point::new(-120., 0.),
+                    Point::new(0., 0.),
+                    Point::new(120., 0.),
+                ]
+                .into(),
+            ),
+        ];
+        let sgs: SplitGeoSeq = (&geos).try_into().unwrap();
+        let sgs = sgs.prepare(&config);
+        assert_eq!(
+            sgs.geos.lines,
+            vec![
+                Line::new((179., 0.), (181., 1.)),
+                Line::new((260., 0.), (100., 1.))
+            ]
+        );
+        // Wide, but no edge goes the long way round.
+        assert_eq!(
+            sgs.geos.line_strings,
+            vec![Cow::Owned(vec![(-100., 0.), (0., 0.), (100., 1.)].into())]
+        );
+        assert_eq!(sgs.geos.rects, vec![Rect::new((179., 0.), (181., 1.))]);
+        assert_eq!(sgs.geos.points, vec![Point::new(-179., 0.)]);
+        assert_eq!(
+            sgs.geos.multi_points,
+            vec![
+                Cow::Owned(vec![Point::new(181., 0.), Point::new(179., 0.)].into()),
+                Cow::Owned(
+                    vec![
+                        Point::new(-120., 0.),
+                        Point::new(0., 0.),
+                        Point::new(120., 0.)
+                    ]
+                    .into()
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn envelope_copies() {
+        let env = RTreeEnvelope::from_corners([170., 0.], [190., 1.]);
+        let turns: Vec<_> = copies(env).iter().map(|(_, turns)| *turns).collect();
+        assert_eq!(turns, vec![0, -1]);
+        assert_eq!(copies(env)[1].0.lower(), [-190., 0.]);
+
+        let env = RTreeEnvelope::from_corners([-10., 0.], [10., 1.]);
+        assert_eq!(copies(env).len(), 1);
+
+        let env = RTreeEnvelope::from_corners([-200., 0.], [200., 1.]);
+        assert_eq!(
+            copies(env)[..],
+            [(RTreeEnvelope::from_corners([-180., 0.], [180., 1.]), 0)]
+        );
+    }
+}
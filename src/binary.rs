@@ -0,0 +1,611 @@
+// The format saved indexes are written in. It's a plain binary
+// serde format along the lines of bincode: everything is written in
+// the order serde hands it to us, with nothing to say what it is, so
+// the reader has to know the layout already (which it does, being
+// the same code). Integers and floats are fixed-width little-endian,
+// lengths and collection sizes are u64, enum variants are their
+// index as a u32, and options are a 0 or 1 byte followed by the
+// value. Struct field names aren't written at all.
+//
+// We keep this ourselves rather than depend on a crate for it: it's
+// small, and the formats we used to lean on stopped being maintained.
+
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io::{Read, Write};
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+#[derive(Debug)]
+pub(crate) enum BinaryError {
+    Io(std::io::Error),
+    Invalid(String),
+}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryError::Io(e) => write!(f, "{}", e),
+            BinaryError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+impl ser::Error for BinaryError {
+    fn custom<T: Display>(message: T) -> Self {
+        BinaryError::Invalid(message.to_string())
+    }
+}
+
+impl de::Error for BinaryError {
+    fn custom<T: Display>(message: T) -> Self {
+        BinaryError::Invalid(message.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, BinaryError>;
+
+pub(crate) fn to_writer<W: Write, T: Serialize>(writer: W, value: &T) -> Result<()> {
+    value.serialize(&mut Encoder { writer })
+}
+
+pub(crate) fn from_reader<R: Read, T: de::DeserializeOwned>(reader: R) -> Result<T> {
+    let mut decoder = Decoder { reader };
+    let value = T::deserialize(&mut decoder)?;
+    let mut rest = [0u8; 1];
+    match decoder.reader.read(&mut rest).map_err(BinaryError::Io)? {
+        0 => Ok(value),
+        _ => Err(BinaryError::Invalid("trailing bytes".to_string())),
+    }
+}
+
+struct Encoder<W> {
+    writer: W,
+}
+
+impl<W: Write> Encoder<W> {
+    fn bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes).map_err(BinaryError::Io)
+    }
+
+    fn len(&mut self, len: usize) -> Result<()> {
+        self.bytes(&(len as u64).to_le_bytes())
+    }
+}
+
+macro_rules! serialize_number {
+    ($method:ident, $Type:ty) => {
+        fn $method(self, value: $Type) -> Result<()> {
+            self.bytes(&value.to_le_bytes())
+        }
+    };
+}
+
+impl<W: Write> ser::Serializer for &mut Encoder<W> {
+    type Ok = ();
+    type Error = BinaryError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    serialize_number!(serialize_i8, i8);
+    serialize_number!(serialize_i16, i16);
+    serialize_number!(serialize_i32, i32);
+    serialize_number!(serialize_i64, i64);
+    serialize_number!(serialize_i128, i128);
+    serialize_number!(serialize_u8, u8);
+    serialize_number!(serialize_u16, u16);
+    serialize_number!(serialize_u32, u32);
+    serialize_number!(serialize_u64, u64);
+    serialize_number!(serialize_u128, u128);
+    serialize_number!(serialize_f32, f32);
+    serialize_number!(serialize_f64, f64);
+
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.serialize_u8(value as u8)
+    }
+
+    fn serialize_char(self, value: char) -> Result<()> {
+        self.serialize_u32(value as u32)
+    }
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.serialize_bytes(value.as_bytes())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        self.len(value.len())?;
+        self.bytes(value)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_u8(0)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        self.serialize_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        let len = len.ok_or_else(|| BinaryError::Invalid("sequence without a length".into()))?;
+        self.len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        self.serialize_seq(len)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! serialize_elements {
+    ($Trait:ident, $method:ident) => {
+        impl<W: Write> ser::$Trait for &mut Encoder<W> {
+            type Ok = ();
+            type Error = BinaryError;
+
+            fn $method<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+serialize_elements!(SerializeSeq, serialize_element);
+serialize_elements!(SerializeTuple, serialize_element);
+serialize_elements!(SerializeTupleStruct, serialize_field);
+serialize_elements!(SerializeTupleVariant, serialize_field);
+
+impl<W: Write> ser::SerializeMap for &mut Encoder<W> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+macro_rules! serialize_fields {
+    ($Trait:ident) => {
+        impl<W: Write> ser::$Trait for &mut Encoder<W> {
+            type Ok = ();
+            type Error = BinaryError;
+
+            fn serialize_field<T: ?Sized + Serialize>(
+                &mut self,
+                _key: &'static str,
+                value: &T,
+            ) -> Result<()> {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+serialize_fields!(SerializeStruct);
+serialize_fields!(SerializeStructVariant);
+
+struct Decoder<R> {
+    reader: R,
+}
+
+impl<R: Read> Decoder<R> {
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.reader
+            .read_exact(&mut bytes)
+            .map_err(BinaryError::Io)?;
+        Ok(bytes)
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        let len = u64::from_le_bytes(self.array()?);
+        usize::try_from(len).map_err(|_| BinaryError::Invalid(format!("length {} too large", len)))
+    }
+
+    // Grows the buffer as the bytes turn up rather than trusting the
+    // length up front, so a bad length runs out of input instead of
+    // memory.
+    fn byte_buf(&mut self) -> Result<Vec<u8>> {
+        let len = self.len()?;
+        let mut bytes = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)
+            .map_err(BinaryError::Io)?;
+        if bytes.len() != len {
+            return Err(BinaryError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        Ok(bytes)
+    }
+}
+
+macro_rules! deserialize_number {
+    ($method:ident, $visit:ident, $Type:ty) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            visitor.$visit(<$Type>::from_le_bytes(self.array()?))
+        }
+    };
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut Decoder<R> {
+    type Error = BinaryError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(BinaryError::Invalid(
+            "the index format doesn't say what it holds".to_string(),
+        ))
+    }
+
+    deserialize_number!(deserialize_i8, visit_i8, i8);
+    deserialize_number!(deserialize_i16, visit_i16, i16);
+    deserialize_number!(deserialize_i32, visit_i32, i32);
+    deserialize_number!(deserialize_i64, visit_i64, i64);
+    deserialize_number!(deserialize_i128, visit_i128, i128);
+    deserialize_number!(deserialize_u8, visit_u8, u8);
+    deserialize_number!(deserialize_u16, visit_u16, u16);
+    deserialize_number!(deserialize_u32, visit_u32, u32);
+    deserialize_number!(deserialize_u64, visit_u64, u64);
+    deserialize_number!(deserialize_u128, visit_u128, u128);
+    deserialize_number!(deserialize_f32, visit_f32, f32);
+    deserialize_number!(deserialize_f64, visit_f64, f64);
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.array::<1>()?[0] {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            b => Err(BinaryError::Invalid(format!("bad bool {}", b))),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let c = u32::from_le_bytes(self.array()?);
+        let c = std::char::from_u32(c)
+            .ok_or_else(|| BinaryError::Invalid(format!("bad char {}", c)))?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let string = String::from_utf8(self.byte_buf()?)
+            .map_err(|_| BinaryError::Invalid("bad UTF-8".to_string()))?;
+        visitor.visit_string(string)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.byte_buf()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.array::<1>()?[0] {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            b => Err(BinaryError::Invalid(format!("bad option tag {}", b))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.len()?;
+        visitor.visit_seq(Elements {
+            decoder: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Elements {
+            decoder: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.len()?;
+        visitor.visit_map(Elements {
+            decoder: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct Elements<'a, R> {
+    decoder: &'a mut Decoder<R>,
+    left: usize,
+}
+
+impl<'de, 'a, R: Read> de::SeqAccess<'de> for Elements<'a, R> {
+    type Error = BinaryError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    // Capped so that a bad length can't make the caller reserve more
+    // than the input could possibly hold.
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left.min(4096))
+    }
+}
+
+impl<'de, 'a, R: Read> de::MapAccess<'de> for Elements<'a, R> {
+    type Error = BinaryError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.decoder)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left.min(4096))
+    }
+}
+
+impl<'de, R: Read> de::EnumAccess<'de> for &mut Decoder<R> {
+    type Error = BinaryError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index = u32::from_le_bytes(self.array()?);
+        let variant = seed.deserialize(index.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for &mut Decoder<R> {
+    type Error = BinaryError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Named { name: String, sides: Option<u8> },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Layer {
+        id: u32,
+        visible: bool,
+        shapes: Vec<Shape>,
+        corners: [(i64, f32); 2],
+    }
+
+    #[test]
+    fn round_trip() {
+        let layer = Layer {
+            id: 7,
+            visible: true,
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(-2.5),
+                Shape::Named {
+                    name: "tri".to_string(),
+                    sides: Some(3),
+                },
+            ],
+            corners: [(-1, 0.5), (i64::MAX, f32::MIN)],
+        };
+        let mut bytes = vec![];
+        to_writer(&mut bytes, &layer).unwrap();
+        assert_eq!(&bytes[..5], &[7, 0, 0, 0, 1]);
+        assert_eq!(from_reader::<_, Layer>(&bytes[..]).unwrap(), layer);
+
+        assert!(matches!(
+            from_reader::<_, Layer>(&bytes[..bytes.len() - 1]),
+            Err(BinaryError::Io(_))
+        ));
+        bytes.push(0);
+        assert!(matches!(
+            from_reader::<_, Layer>(&bytes[..]),
+            Err(BinaryError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn huge_lengths() {
+        // A length of 2^64 - 1 with nothing behind it.
+        let bytes = [0xff; 8];
+        assert!(from_reader::<_, Vec<u64>>(&bytes[..]).is_err());
+        assert!(from_reader::<_, String>(&bytes[..]).is_err());
+    }
+}
@@ -1,54 +1,35 @@
+use std::borrow::Cow;
 use std::convert::TryFrom;
 
-use geo::{Geometry, Line, LineString, Point, Polygon, Rect, Triangle};
+use geo::{
+    Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Rect,
+    Triangle,
+};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use super::validation::IsSafe;
-use crate::{Error, Indexes, SplitGeo, SplitGeoIndexes, SplitGeoSeq};
+use crate::{CoordFloat, Error, Indexes, SplitGeo, SplitGeoIndexes, SplitGeoSeq};
 
 #[cfg(feature = "parallel")]
 use crate::structs::Par;
 
-impl TryFrom<&[Geometry<f64>]> for SplitGeoSeq {
-    type Error = Error;
-
-    fn try_from(seq: &[Geometry<f64>]) -> Result<Self, Self::Error> {
+impl<'a, C: CoordFloat> SplitGeoSeq<'a, C> {
+    // Validates each geometry and files it under the bucket for its
+    // type. `offset` is the position of the first geometry in the
+    // caller's sequence so that chunks converted in parallel still
+    // record (and report) their real indexes. Invalid geometries get
+    // handed to `on_error` which decides whether we give up or skip
+    // them.
+    fn convert<I, F>(seq: I, offset: usize, mut on_error: F) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Cow<'a, Geometry<C>>>,
+        F: FnMut(Error) -> Result<(), Error>,
+    {
         let mut result = SplitGeoSeq::default();
-        for (i, geo) in seq.iter().enumerate() {
-            match geo {
-                Geometry::Line(ln) => {
-                    ln.is_safe(i)?;
-                    result.geos.lines.push(*ln);
-                    result.indexes.lines.push(i);
-                }
-                Geometry::Point(pt) => {
-                    pt.is_safe(i)?;
-                    result.geos.points.push(*pt);
-                    result.indexes.points.push(i)
-                }
-                Geometry::Polygon(poly) => {
-                    poly.is_safe(i)?;
-                    result.geos.polys.push(poly.clone());
-                    result.indexes.polys.push(i)
-                }
-                Geometry::LineString(ls) => {
-                    ls.is_safe(i)?;
-                    result.geos.line_strings.push(ls.clone());
-                    result.indexes.line_strings.push(i)
-                }
-                Geometry::Rect(r) => {
-                    r.is_safe(i)?;
-                    result.geos.rects.push(*r);
-                    result.indexes.rects.push(i)
-                }
-                Geometry::Triangle(tri) => {
-                    tri.is_safe(i)?;
-                    result.geos.tris.push(*tri);
-                    result.indexes.tris.push(i)
-                }
-
-                _ => unimplemented!("ugh"),
+        for (i, geo) in seq.into_iter().enumerate() {
+            if let Err(e) = result.push(offset + i, geo) {
+                on_error(e)?;
             }
         }
 
@@ -58,102 +39,234 @@ impl TryFrom<&[Geometry<f64>]> for SplitGeoSeq {
         result.indexes.line_strings.canonicalize();
         result.indexes.rects.canonicalize();
         result.indexes.tris.canonicalize();
+        result.indexes.multi_points.canonicalize();
+        result.indexes.multi_line_strings.canonicalize();
+        result.indexes.multi_polys.canonicalize();
 
         Ok(result)
     }
+
+    /// A sequence holding just `geo`, which is element `i` of some
+    /// stream, so we can join big geometries as they arrive.
+    pub(crate) fn single(i: usize, geo: Geometry<C>) -> Result<Self, Error> {
+        let mut result = SplitGeoSeq::default();
+        result.push(i, Cow::Owned(geo))?;
+        Ok(result)
+    }
+
+    // Borrowed geometries stay borrowed, so converting a slice
+    // doesn't clone anything on the heap.
+    fn push(&mut self, i: usize, geo: Cow<'a, Geometry<C>>) -> Result<(), Error> {
+        macro_rules! take {
+            ($Var:ident) => {
+                match geo {
+                    Cow::Borrowed(Geometry::$Var(g)) => Cow::Borrowed(g),
+                    Cow::Owned(Geometry::$Var(g)) => Cow::Owned(g),
+                    _ => unreachable!(),
+                }
+            };
+        }
+        match &*geo {
+            Geometry::Line(ln) => {
+                ln.is_safe(i)?;
+                self.geos.lines.push(*ln);
+                self.indexes.lines.push(i);
+            }
+            Geometry::Point(pt) => {
+                pt.is_safe(i)?;
+                self.geos.points.push(*pt);
+                self.indexes.points.push(i)
+            }
+            Geometry::Polygon(poly) => {
+                poly.is_safe(i)?;
+                self.geos.polys.push(take!(Polygon));
+                self.indexes.polys.push(i)
+            }
+            Geometry::LineString(ls) => {
+                ls.is_safe(i)?;
+                self.geos.line_strings.push(take!(LineString));
+                self.indexes.line_strings.push(i)
+            }
+            Geometry::Rect(r) => {
+                r.is_safe(i)?;
+                self.geos.rects.push(*r);
+                self.indexes.rects.push(i)
+            }
+            Geometry::Triangle(tri) => {
+                tri.is_safe(i)?;
+                self.geos.tris.push(*tri);
+                self.indexes.tris.push(i)
+            }
+            Geometry::MultiPoint(mp) => {
+                mp.is_safe(i)?;
+                self.geos.multi_points.push(take!(MultiPoint));
+                self.indexes.multi_points.push(i)
+            }
+            Geometry::MultiLineString(mls) => {
+                mls.is_safe(i)?;
+                self.geos.multi_line_strings.push(take!(MultiLineString));
+                self.indexes.multi_line_strings.push(i)
+            }
+            Geometry::MultiPolygon(mpoly) => {
+                mpoly.is_safe(i)?;
+                self.geos.multi_polys.push(take!(MultiPolygon));
+                self.indexes.multi_polys.push(i)
+            }
+
+            Geometry::GeometryCollection(_) => {
+                return Err(Error::UnsupportedGeometry(i, "GeometryCollection"))
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `SplitGeoSeq::try_from` except that geometries that are
+    /// invalid or unsupported get skipped instead of failing the
+    /// whole conversion. The skipped geometries are reported in the
+    /// returned `Vec<Error>`, ordered by their index. Result rows
+    /// still use indexes into `seq`, so the skipped indexes simply
+    /// never show up.
+    pub fn lenient_from(seq: &'a [Geometry<C>]) -> (Self, Vec<Error>) {
+        let mut errors = Vec::new();
+        let result = SplitGeoSeq::convert(seq.iter().map(Cow::Borrowed), 0, |e| {
+            errors.push(e);
+            Ok(())
+        })
+        .expect("lenient conversion never fails");
+        (result, errors)
+    }
+}
+
+impl<'a, C: CoordFloat> TryFrom<&'a [Geometry<C>]> for SplitGeoSeq<'a, C> {
+    type Error = Error;
+
+    fn try_from(seq: &'a [Geometry<C>]) -> Result<Self, Self::Error> {
+        SplitGeoSeq::convert(seq.iter().map(Cow::Borrowed), 0, Err)
+    }
     // FIXME: add an optimization that looks for cases where all but
     // one variants are empty and makes them implicit.
 }
 
 #[cfg(feature = "parallel")]
-impl TryFrom<SplitGeoSeq> for Par<SplitGeoSeq> {
+impl<'a, C: CoordFloat> TryFrom<SplitGeoSeq<'a, C>> for Par<SplitGeoSeq<'a, C>> {
     type Error = Error;
-    fn try_from(sgs: SplitGeoSeq) -> Result<Self, Self::Error> {
+    fn try_from(sgs: SplitGeoSeq<'a, C>) -> Result<Self, Self::Error> {
         Ok(Par(sgs))
     }
 }
 
 #[cfg(feature = "parallel")]
-impl TryFrom<&[Geometry<f64>]> for Par<SplitGeoSeq> {
+fn chunks(len: usize) -> impl ParallelIterator<Item = std::ops::Range<usize>> {
+    let step = (len / num_cpus::get()).max(1);
+    (0..len)
+        .into_par_iter()
+        .step_by(step)
+        .map(move |start| std::ops::Range {
+            start,
+            end: (start + step).min(len),
+        })
+}
+
+#[cfg(feature = "parallel")]
+impl<'a, C: CoordFloat> TryFrom<&'a [Geometry<C>]> for Par<SplitGeoSeq<'a, C>> {
     type Error = Error;
 
-    fn try_from(seq: &[Geometry<f64>]) -> Result<Self, Self::Error> {
-        let step = (seq.len() / num_cpus::get()).max(1);
-        let end = seq.len();
-        (0..seq.len())
-            .into_par_iter()
-            .step_by(step)
-            .map(|start| std::ops::Range {
-                start,
-                end: (start + step).min(end),
-            })
+    fn try_from(seq: &'a [Geometry<C>]) -> Result<Self, Self::Error> {
+        chunks(seq.len())
             .map(|range| {
-                let offset = range.start;
-                SplitGeoSeq::try_from(&seq[range]).map(|mut sgs| {
-                    sgs.indexes.points.add_offset(offset);
-                    sgs.indexes.lines.add_offset(offset);
-                    sgs.indexes.polys.add_offset(offset);
-                    sgs.indexes.line_strings.add_offset(offset);
-                    sgs.indexes.rects.add_offset(offset);
-                    sgs.indexes.tris.add_offset(offset);
-
-                    sgs
-                })
+                SplitGeoSeq::convert(
+                    seq[range.clone()].iter().map(Cow::Borrowed),
+                    range.start,
+                    Err,
+                )
             })
             .try_reduce(SplitGeoSeq::default, |a, b| Ok(SplitGeoSeq::merge(a, b)))
             .map(Par)
     }
 }
 
-// FIXME: make versions of these two that consume the arg (i.e., Vec instead of &Vec)
-impl TryFrom<&Vec<Geometry<f64>>> for SplitGeoSeq {
+#[cfg(feature = "parallel")]
+impl<'a, C: CoordFloat> Par<SplitGeoSeq<'a, C>> {
+    /// The parallel version of
+    /// [`SplitGeoSeq::lenient_from`](./struct.SplitGeoSeq.html#method.lenient_from).
+    pub fn lenient_from(seq: &'a [Geometry<C>]) -> (Self, Vec<Error>) {
+        let (result, mut errors) = chunks(seq.len())
+            .map(|range| {
+                let mut errors = Vec::new();
+                let sgs = SplitGeoSeq::convert(
+                    seq[range.clone()].iter().map(Cow::Borrowed),
+                    range.start,
+                    |e| {
+                        errors.push(e);
+                        Ok(())
+                    },
+                )
+                .expect("lenient conversion never fails");
+                (sgs, errors)
+            })
+            .reduce(
+                || (SplitGeoSeq::default(), Vec::new()),
+                |(a, mut a_errors), (b, mut b_errors)| {
+                    a_errors.append(&mut b_errors);
+                    (SplitGeoSeq::merge(a, b), a_errors)
+                },
+            );
+        // rayon's reduce makes no promises about order
+        errors.sort_by_key(|e| e.index());
+        (Par(result), errors)
+    }
+}
+
+impl<'a, C: CoordFloat> TryFrom<&'a Vec<Geometry<C>>> for SplitGeoSeq<'a, C> {
     type Error = Error;
 
-    fn try_from(seq: &Vec<Geometry<f64>>) -> Result<Self, Self::Error> {
+    fn try_from(seq: &'a Vec<Geometry<C>>) -> Result<Self, Self::Error> {
         SplitGeoSeq::try_from(&seq[..])
     }
 }
 
-impl TryFrom<Vec<Geometry<f64>>> for SplitGeoSeq {
+impl<C: CoordFloat> TryFrom<Vec<Geometry<C>>> for SplitGeoSeq<'_, C> {
     type Error = Error;
 
-    fn try_from(seq: Vec<Geometry<f64>>) -> Result<Self, Self::Error> {
-        SplitGeoSeq::try_from(&seq)
+    fn try_from(seq: Vec<Geometry<C>>) -> Result<Self, Self::Error> {
+        SplitGeoSeq::convert(seq.into_iter().map(Cow::Owned), 0, Err)
     }
 }
 
-impl TryFrom<&geo::GeometryCollection<f64>> for SplitGeoSeq {
+impl<'a, C: CoordFloat> TryFrom<&'a geo::GeometryCollection<C>> for SplitGeoSeq<'a, C> {
     type Error = Error;
 
-    fn try_from(seq: &geo::GeometryCollection<f64>) -> Result<Self, Self::Error> {
+    fn try_from(seq: &'a geo::GeometryCollection<C>) -> Result<Self, Self::Error> {
         SplitGeoSeq::try_from(&seq.0[..])
     }
 }
 
 #[cfg(feature = "parallel")]
-impl TryFrom<&Vec<Geometry<f64>>> for Par<SplitGeoSeq> {
+impl<'a, C: CoordFloat> TryFrom<&'a Vec<Geometry<C>>> for Par<SplitGeoSeq<'a, C>> {
     type Error = Error;
 
-    fn try_from(seq: &Vec<Geometry<f64>>) -> Result<Self, Self::Error> {
-        Par::<SplitGeoSeq>::try_from(&seq[..])
+    fn try_from(seq: &'a Vec<Geometry<C>>) -> Result<Self, Self::Error> {
+        Par::<SplitGeoSeq<C>>::try_from(&seq[..])
     }
 }
 
+// FIXME: consume `seq` here rather than converting a borrowed copy
+// and then cloning it.
 #[cfg(feature = "parallel")]
-impl TryFrom<Vec<Geometry<f64>>> for Par<SplitGeoSeq> {
+impl<C: CoordFloat> TryFrom<Vec<Geometry<C>>> for Par<SplitGeoSeq<'_, C>> {
     type Error = Error;
 
-    fn try_from(seq: Vec<Geometry<f64>>) -> Result<Self, Self::Error> {
-        Par::<SplitGeoSeq>::try_from(&seq)
+    fn try_from(seq: Vec<Geometry<C>>) -> Result<Self, Self::Error> {
+        Par::<SplitGeoSeq<C>>::try_from(&seq).map(|Par(sgs)| Par(sgs.into_owned()))
     }
 }
 
 #[cfg(feature = "parallel")]
-impl TryFrom<&geo::GeometryCollection<f64>> for Par<SplitGeoSeq> {
+impl<'a, C: CoordFloat> TryFrom<&'a geo::GeometryCollection<C>> for Par<SplitGeoSeq<'a, C>> {
     type Error = Error;
 
-    fn try_from(seq: &geo::GeometryCollection<f64>) -> Result<Self, Self::Error> {
-        Par::<SplitGeoSeq>::try_from(&seq.0[..])
+    fn try_from(seq: &'a geo::GeometryCollection<C>) -> Result<Self, Self::Error> {
+        Par::<SplitGeoSeq<C>>::try_from(&seq.0[..])
     }
 }
 
@@ -166,13 +279,14 @@ macro_rules! static_cond {
     };
 }
 
-// make conversions from &Vec, Vec, and slice
+// make conversions from &Vec, Vec, and slice. Slices of heap-bound
+// geometries get borrowed rather than cloned.
 macro_rules! from_impls {
-    ($ItemType:ty, $Var:ident, $IsCopyable:ident) => {
-        impl TryFrom<&[$ItemType]> for SplitGeoSeq {
+    ($Item:ident, $Var:ident, $IsCopyable:ident) => {
+        impl<'a, C: CoordFloat> TryFrom<&'a [$Item<C>]> for SplitGeoSeq<'a, C> {
             type Error = Error;
 
-            fn try_from(seq: &[$ItemType]) -> Result<Self, Self::Error> {
+            fn try_from(seq: &'a [$Item<C>]) -> Result<Self, Self::Error> {
                 seq.iter()
                     .enumerate()
                     .try_for_each(|(i, x)| (*x).is_safe(i))
@@ -181,7 +295,7 @@ macro_rules! from_impls {
                             $Var: static_cond!(
                                 $IsCopyable,
                                 seq.to_vec(),
-                                seq.iter().map(|poly| poly.clone()).collect()
+                                seq.iter().map(Cow::Borrowed).collect()
                             ),
                             ..Default::default()
                         },
@@ -194,10 +308,10 @@ macro_rules! from_impls {
         }
 
         #[cfg(feature = "parallel")]
-        impl TryFrom<&[$ItemType]> for Par<SplitGeoSeq> {
+        impl<'a, C: CoordFloat> TryFrom<&'a [$Item<C>]> for Par<SplitGeoSeq<'a, C>> {
             type Error = Error;
 
-            fn try_from(seq: &[$ItemType]) -> Result<Self, Self::Error> {
+            fn try_from(seq: &'a [$Item<C>]) -> Result<Self, Self::Error> {
                 seq.par_iter()
                     .enumerate()
                     .try_for_each(|(i, x)| (*x).is_safe(i))
@@ -207,7 +321,7 @@ macro_rules! from_impls {
                                 $Var: static_cond!(
                                     $IsCopyable,
                                     seq.to_vec(),
-                                    seq.iter().map(|poly| poly.clone()).collect()
+                                    seq.iter().map(Cow::Borrowed).collect()
                                 ),
                                 ..Default::default()
                             },
@@ -220,10 +334,10 @@ macro_rules! from_impls {
             }
         }
 
-        impl TryFrom<Vec<$ItemType>> for SplitGeoSeq {
+        impl<C: CoordFloat> TryFrom<Vec<$Item<C>>> for SplitGeoSeq<'_, C> {
             type Error = Error;
 
-            fn try_from(seq: Vec<$ItemType>) -> Result<Self, Self::Error> {
+            fn try_from(seq: Vec<$Item<C>>) -> Result<Self, Self::Error> {
                 seq.iter()
                     .enumerate()
                     .try_for_each(|(i, x)| (*x).is_safe(i))
@@ -233,7 +347,11 @@ macro_rules! from_impls {
                             ..Default::default()
                         },
                         geos: SplitGeo {
-                            $Var: seq,
+                            $Var: static_cond!(
+                                $IsCopyable,
+                                seq,
+                                seq.into_iter().map(Cow::Owned).collect()
+                            ),
                             ..Default::default()
                         },
                     })
@@ -241,10 +359,10 @@ macro_rules! from_impls {
         }
 
         #[cfg(feature = "parallel")]
-        impl TryFrom<Vec<$ItemType>> for Par<SplitGeoSeq> {
+        impl<C: CoordFloat> TryFrom<Vec<$Item<C>>> for Par<SplitGeoSeq<'_, C>> {
             type Error = Error;
 
-            fn try_from(seq: Vec<$ItemType>) -> Result<Self, Self::Error> {
+            fn try_from(seq: Vec<$Item<C>>) -> Result<Self, Self::Error> {
                 seq.par_iter()
                     .enumerate()
                     .try_for_each(|(i, x)| (*x).is_safe(i))
@@ -255,7 +373,11 @@ macro_rules! from_impls {
                                 ..Default::default()
                             },
                             geos: SplitGeo {
-                                $Var: seq,
+                                $Var: static_cond!(
+                                    $IsCopyable,
+                                    seq,
+                                    seq.into_iter().map(Cow::Owned).collect()
+                                ),
                                 ..Default::default()
                             },
                         })
@@ -263,31 +385,34 @@ macro_rules! from_impls {
             }
         }
 
-        impl TryFrom<&Vec<$ItemType>> for SplitGeoSeq {
+        impl<'a, C: CoordFloat> TryFrom<&'a Vec<$Item<C>>> for SplitGeoSeq<'a, C> {
             type Error = Error;
 
-            fn try_from(seq: &Vec<$ItemType>) -> Result<Self, Self::Error> {
+            fn try_from(seq: &'a Vec<$Item<C>>) -> Result<Self, Self::Error> {
                 SplitGeoSeq::try_from(&seq[..])
             }
         }
 
         #[cfg(feature = "parallel")]
-        impl TryFrom<&Vec<$ItemType>> for Par<SplitGeoSeq> {
+        impl<'a, C: CoordFloat> TryFrom<&'a Vec<$Item<C>>> for Par<SplitGeoSeq<'a, C>> {
             type Error = Error;
 
-            fn try_from(seq: &Vec<$ItemType>) -> Result<Self, Self::Error> {
-                Par::<SplitGeoSeq>::try_from(&seq[..])
+            fn try_from(seq: &'a Vec<$Item<C>>) -> Result<Self, Self::Error> {
+                Par::<SplitGeoSeq<C>>::try_from(&seq[..])
             }
         }
     };
 }
 
-from_impls!(Point<f64>, points, true);
-from_impls!(Line<f64>, lines, true);
-from_impls!(Polygon<f64>, polys, false);
-from_impls!(LineString<f64>, line_strings, false);
-from_impls!(Rect<f64>, rects, true);
-from_impls!(Triangle<f64>, tris, true);
+from_impls!(Point, points, true);
+from_impls!(Line, lines, true);
+from_impls!(Polygon, polys, false);
+from_impls!(LineString, line_strings, false);
+from_impls!(Rect, rects, true);
+from_impls!(Triangle, tris, true);
+from_impls!(MultiPoint, multi_points, false);
+from_impls!(MultiLineString, multi_line_strings, false);
+from_impls!(MultiPolygon, multi_polys, false);
 
 #[cfg(test)]
 mod tests {
@@ -303,14 +428,18 @@ mod tests {
             geo::LineString::from(vec![(0., 0.), (1., 1.), (1., 0.), (0., 0.)]),
             vec![],
         );
+        let mpoly = MultiPolygon(vec![poly.clone()]);
         let expected = Ok(SplitGeoSeq {
             geos: SplitGeo {
                 points: vec![pt],
                 lines: vec![ln1, ln2],
-                polys: vec![poly.clone()],
+                polys: vec![Cow::Owned(poly.clone())],
                 line_strings: vec![],
                 rects: vec![],
                 tris: vec![],
+                multi_points: vec![],
+                multi_line_strings: vec![],
+                multi_polys: vec![Cow::Owned(mpoly.clone())],
             },
             indexes: SplitGeoIndexes {
                 points: Indexes::Range(0..1),
@@ -319,6 +448,9 @@ mod tests {
                 line_strings: Indexes::default(),
                 rects: Indexes::default(),
                 tris: Indexes::default(),
+                multi_points: Indexes::default(),
+                multi_line_strings: Indexes::default(),
+                multi_polys: Indexes::Range(4..5),
             },
         });
         let geos = vec![
@@ -326,10 +458,12 @@ mod tests {
             Geometry::Line(ln1),
             Geometry::Polygon(poly.clone()),
             Geometry::Line(ln2),
+            Geometry::MultiPolygon(mpoly.clone()),
         ];
         let slice_result = SplitGeoSeq::try_from(&geos[..]);
         let vec_result = SplitGeoSeq::try_from(&geos);
-        let geo_collection_result = SplitGeoSeq::try_from(&geo::GeometryCollection(geos));
+        let geo_collection = geo::GeometryCollection(geos.clone());
+        let geo_collection_result = SplitGeoSeq::try_from(&geo_collection);
         assert_eq!(expected, slice_result);
         assert_eq!(expected, vec_result);
         assert_eq!(expected, geo_collection_result);
@@ -351,9 +485,83 @@ mod tests {
         });
         let slice_result = SplitGeoSeq::try_from(&pts[..]);
         let vec_result = SplitGeoSeq::try_from(&pts);
-        let vec_into_result = SplitGeoSeq::try_from(pts);
+        let vec_into_result = SplitGeoSeq::try_from(pts.clone());
         assert_eq!(expected, slice_result);
         assert_eq!(expected, vec_result);
         assert_eq!(expected, vec_into_result);
     }
+
+    #[test]
+    fn from_multis() {
+        let pt: Point<f64> = (1.1, 2.2).into();
+        let mpt = MultiPoint(vec![pt, pt]);
+        let empty = MultiPoint::<f64>(vec![]);
+        let bad = MultiPoint(vec![pt, Point::new(f64::NAN, 0.)]);
+        let geos = vec![Geometry::Point(pt), Geometry::MultiPoint(mpt.clone())];
+
+        assert_eq!(
+            SplitGeoSeq::try_from(&geos),
+            Ok(SplitGeoSeq {
+                geos: SplitGeo {
+                    points: vec![pt],
+                    multi_points: vec![Cow::Owned(mpt.clone())],
+                    ..Default::default()
+                },
+                indexes: SplitGeoIndexes {
+                    points: Indexes::Range(0..1),
+                    multi_points: Indexes::Range(1..2),
+                    ..Default::default()
+                },
+            })
+        );
+        assert_eq!(
+            SplitGeoSeq::try_from(vec![mpt.clone(), empty]),
+            Err(Error::EmptyMultiGeometry(1))
+        );
+        // NaN != NaN so we can't compare the whole error
+        match SplitGeoSeq::try_from(vec![mpt, bad]) {
+            Err(Error::BadCoordinateValue(1, Geometry::MultiPoint(_))) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lenient() {
+        let pt: Point<f64> = (1.1, 2.2).into();
+        let geos = vec![
+            Geometry::Point(pt),
+            Geometry::GeometryCollection(geo::GeometryCollection(vec![Geometry::Point(pt)])),
+            Geometry::LineString(LineString::from(vec![(0., 0.)])),
+            Geometry::Point(pt),
+        ];
+        let expected_errors = vec![
+            Error::UnsupportedGeometry(1, "GeometryCollection"),
+            Error::LineStringTooSmall(2),
+        ];
+        let expected = SplitGeoSeq {
+            geos: SplitGeo {
+                points: vec![pt, pt],
+                ..Default::default()
+            },
+            indexes: SplitGeoIndexes {
+                points: Indexes::Explicit(vec![0, 3]),
+                ..Default::default()
+            },
+        };
+
+        assert_eq!(
+            SplitGeoSeq::try_from(&geos),
+            Err(Error::UnsupportedGeometry(1, "GeometryCollection"))
+        );
+        assert_eq!(
+            SplitGeoSeq::lenient_from(&geos),
+            (expected.clone(), expected_errors.clone())
+        );
+
+        #[cfg(feature = "parallel")]
+        {
+            let (par, par_errors) = Par::<SplitGeoSeq>::lenient_from(&geos);
+            assert_eq!((par.0, par_errors), (expected, expected_errors));
+        }
+    }
 }
@@ -0,0 +1,37 @@
+use num_traits::{Float, FloatConst, Signed};
+use rstar::RTreeNum;
+
+/// The coordinate types geometries can have: `f64`, or `f32` to halve
+/// the memory an index takes. `geo` 0.14 has no `CoordFloat` of its
+/// own, so this names everything we need from a coordinate.
+///
+/// Whatever the coordinates, distances come out as `f64`, and the
+/// DE-9IM matrices and geodesic distances are worked out in `f64`
+/// too.
+pub trait CoordFloat:
+    geo::CoordinateType + Float + FloatConst + Signed + RTreeNum + Default + Send + Sync + 'static
+{
+    fn as_f64(self) -> f64;
+
+    fn from_f64(value: f64) -> Self;
+}
+
+impl CoordFloat for f64 {
+    fn as_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+impl CoordFloat for f32 {
+    fn as_f64(self) -> f64 {
+        self.into()
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
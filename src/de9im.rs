@@ -0,0 +1,861 @@
+use geo::{
+    Coordinate, Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point,
+    Polygon, Rect, Triangle,
+};
+
+use crate::relates::rect_lines;
+use crate::validation::IsSafe;
+use crate::{CoordFloat, Error, RelatePattern};
+
+// This module computes DE-9IM intersection matrices for the
+// predicates `geo` doesn't give us (touches, crosses, overlaps,
+// covers). We don't build a full topology graph the way libgeos
+// does. Instead we node the linework of both shapes against each
+// other and then sample the pieces: every node tells us about a
+// zero-dimensional intersection, the midpoint of every piece of
+// linework between two nodes tells us about a one-dimensional
+// intersection, and points just to either side of every piece of an
+// area's boundary tell us about the two-dimensional ones. Each piece
+// has a single location relative to both shapes, so sampling it once
+// is enough.
+//
+// Coordinates are compared with a small tolerance scaled to the
+// input, so shapes that miss each other by a rounding error are
+// treated as touching.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Location {
+    Interior = 0,
+    Boundary = 1,
+    Exterior = 2,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Dimensions {
+    Empty,
+    ZeroDimensional,
+    OneDimensional,
+    TwoDimensional,
+}
+
+/// The [DE-9IM](https://en.wikipedia.org/wiki/DE-9IM) intersection
+/// matrix of two shapes, as `Interaction::Relate` matches it against
+/// a [`RelatePattern`](struct.RelatePattern.html). It displays as the
+/// usual nine characters, like `"FF2FF1212"`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntersectionMatrix([[Dimensions; 3]; 3]);
+
+impl IntersectionMatrix {
+    /// Computes the matrix of `a` against `b`: the rows are for `a`,
+    /// like `small` in a join, and the columns for `b`. The
+    /// geometries are checked the way conversions into a
+    /// `SplitGeoSeq` check them, with `a` at index 0 and `b` at 1.
+    pub fn new<C: CoordFloat>(
+        a: &Geometry<C>,
+        b: &Geometry<C>,
+    ) -> Result<IntersectionMatrix, Error> {
+        Ok(relate(&geometry_parts(a, 0)?, &geometry_parts(b, 1)?))
+    }
+
+    fn empty() -> IntersectionMatrix {
+        IntersectionMatrix([[Dimensions::Empty; 3]; 3])
+    }
+
+    fn add(&mut self, a: Location, b: Location, dimensions: Dimensions) {
+        let entry = &mut self.0[a as usize][b as usize];
+        *entry = (*entry).max(dimensions);
+    }
+
+    pub(crate) fn get(&self, a: Location, b: Location) -> Dimensions {
+        self.0[a as usize][b as usize]
+    }
+
+    fn is_set(&self, a: Location, b: Location) -> bool {
+        self.get(a, b) != Dimensions::Empty
+    }
+
+    /// Whether the matrix matches `pattern`.
+    pub fn matches(&self, pattern: &RelatePattern) -> bool {
+        self.0
+            .iter()
+            .flat_map(|row| row.iter())
+            .zip(pattern.0.iter())
+            .all(|(entry, p)| match p {
+                b'T' => *entry != Dimensions::Empty,
+                b'F' => *entry == Dimensions::Empty,
+                b'0' => *entry == Dimensions::ZeroDimensional,
+                b'1' => *entry == Dimensions::OneDimensional,
+                b'2' => *entry == Dimensions::TwoDimensional,
+                _ => true,
+            })
+    }
+
+    pub(crate) fn is_touches(&self) -> bool {
+        use Location::*;
+        !self.is_set(Interior, Interior)
+            && (self.is_set(Interior, Boundary)
+                || self.is_set(Boundary, Interior)
+                || self.is_set(Boundary, Boundary))
+    }
+
+    pub(crate) fn is_crosses(&self, dim_a: u8, dim_b: u8) -> bool {
+        use Location::*;
+        if dim_a < dim_b {
+            self.is_set(Interior, Interior) && self.is_set(Interior, Exterior)
+        } else if dim_a > dim_b {
+            self.is_set(Interior, Interior) && self.is_set(Exterior, Interior)
+        } else if dim_a == 1 {
+            self.get(Interior, Interior) == Dimensions::ZeroDimensional
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn is_overlaps(&self, dim_a: u8, dim_b: u8) -> bool {
+        use Location::*;
+        if dim_a != dim_b {
+            return false;
+        }
+        let interiors = if dim_a == 1 {
+            self.get(Interior, Interior) == Dimensions::OneDimensional
+        } else {
+            self.is_set(Interior, Interior)
+        };
+        interiors && self.is_set(Interior, Exterior) && self.is_set(Exterior, Interior)
+    }
+
+    pub(crate) fn is_contains(&self) -> bool {
+        use Location::*;
+        self.is_set(Interior, Interior)
+            && !self.is_set(Exterior, Interior)
+            && !self.is_set(Exterior, Boundary)
+    }
+
+    pub(crate) fn is_covers(&self) -> bool {
+        use Location::*;
+        (self.is_set(Interior, Interior)
+            || self.is_set(Interior, Boundary)
+            || self.is_set(Boundary, Interior)
+            || self.is_set(Boundary, Boundary))
+            && !self.is_set(Exterior, Interior)
+            && !self.is_set(Exterior, Boundary)
+    }
+
+    pub(crate) fn is_covered_by(&self) -> bool {
+        self.transpose().is_covers()
+    }
+
+    fn transpose(&self) -> IntersectionMatrix {
+        let mut result = IntersectionMatrix::empty();
+        for (i, row) in self.0.iter().enumerate() {
+            for (j, entry) in row.iter().enumerate() {
+                result.0[j][i] = *entry;
+            }
+        }
+        result
+    }
+}
+
+impl std::fmt::Display for IntersectionMatrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in self.0.iter().flat_map(|row| row.iter()) {
+            let c = match entry {
+                Dimensions::Empty => 'F',
+                Dimensions::ZeroDimensional => '0',
+                Dimensions::OneDimensional => '1',
+                Dimensions::TwoDimensional => '2',
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// The pieces of a shape that the DE-9IM computation works with.
+pub struct Parts {
+    // 0 for points, 1 for lines and 2 for areas.
+    dimension: u8,
+    // Only puntal shapes have isolated points.
+    points: Vec<Coordinate<f64>>,
+    // Lines for lineal shapes, rings for areal ones.
+    segments: Vec<Line<f64>>,
+    // Lineal shapes only: the endpoints that appear an odd number of
+    // times (the "mod 2" rule).
+    boundary: Vec<Coordinate<f64>>,
+    min: Coordinate<f64>,
+    max: Coordinate<f64>,
+}
+
+impl Parts {
+    fn new(
+        dimension: u8,
+        points: Vec<Coordinate<f64>>,
+        segments: Vec<Line<f64>>,
+        boundary: Vec<Coordinate<f64>>,
+    ) -> Parts {
+        let mut min: Coordinate<f64> = (f64::INFINITY, f64::INFINITY).into();
+        let mut max: Coordinate<f64> = (f64::NEG_INFINITY, f64::NEG_INFINITY).into();
+        for c in points.iter().chain(
+            segments
+                .iter()
+                .flat_map(|s| std::iter::once(&s.start).chain(std::iter::once(&s.end))),
+        ) {
+            min = (min.x.min(c.x), min.y.min(c.y)).into();
+            max = (max.x.max(c.x), max.y.max(c.y)).into();
+        }
+        Parts {
+            dimension,
+            points,
+            segments,
+            boundary,
+            min,
+            max,
+        }
+    }
+
+    fn lineal<'a, C: CoordFloat>(
+        segments: Vec<Line<f64>>,
+        endpoints: impl Iterator<Item = &'a LineString<C>>,
+    ) -> Parts {
+        let mut ends: Vec<Coordinate<f64>> = Vec::new();
+        for ls in endpoints {
+            if let (Some(first), Some(last)) = (ls.0.first(), ls.0.last()) {
+                ends.push(wide(*first));
+                ends.push(wide(*last));
+            }
+        }
+        Parts::new(1, Vec::new(), segments, mod2(ends))
+    }
+
+    fn areal(segments: Vec<Line<f64>>) -> Parts {
+        Parts::new(2, Vec::new(), segments, Vec::new())
+    }
+
+    pub(crate) fn dimension(&self) -> u8 {
+        self.dimension
+    }
+
+    fn interior_dimensions(&self) -> Dimensions {
+        if self.points.is_empty() && self.segments.is_empty() {
+            return Dimensions::Empty;
+        }
+        match self.dimension {
+            0 => Dimensions::ZeroDimensional,
+            1 => Dimensions::OneDimensional,
+            _ => Dimensions::TwoDimensional,
+        }
+    }
+
+    fn boundary_dimensions(&self) -> Dimensions {
+        match self.dimension {
+            1 if !self.boundary.is_empty() => Dimensions::ZeroDimensional,
+            2 if !self.segments.is_empty() => Dimensions::OneDimensional,
+            _ => Dimensions::Empty,
+        }
+    }
+
+    pub(crate) fn segments(&self) -> &[Line<f64>] {
+        &self.segments
+    }
+
+    pub(crate) fn vertices(&self) -> impl Iterator<Item = Coordinate<f64>> + '_ {
+        self.points.iter().cloned().chain(
+            self.segments
+                .iter()
+                .flat_map(|s| std::iter::once(s.start).chain(std::iter::once(s.end))),
+        )
+    }
+
+    fn locate(&self, c: Coordinate<f64>, eps: f64) -> Location {
+        if c.x < self.min.x - eps
+            || c.y < self.min.y - eps
+            || c.x > self.max.x + eps
+            || c.y > self.max.y + eps
+        {
+            return Location::Exterior;
+        }
+        match self.dimension {
+            0 => {
+                if self.points.iter().any(|p| distance(*p, c) <= eps) {
+                    Location::Interior
+                } else {
+                    Location::Exterior
+                }
+            }
+            1 => {
+                if self.boundary.iter().any(|p| distance(*p, c) <= eps) {
+                    Location::Boundary
+                } else if self.segments.iter().any(|s| segment_distance(s, c) <= eps) {
+                    Location::Interior
+                } else {
+                    Location::Exterior
+                }
+            }
+            _ => {
+                if self.segments.iter().any(|s| segment_distance(s, c) <= eps) {
+                    return Location::Boundary;
+                }
+                // Even-odd ray casting over every ring; this works
+                // for holes and for the members of a valid
+                // MultiPolygon alike.
+                let mut inside = false;
+                for s in self.segments.iter() {
+                    if (s.start.y > c.y) != (s.end.y > c.y) {
+                        let x = s.start.x
+                            + (c.y - s.start.y) * (s.end.x - s.start.x) / (s.end.y - s.start.y);
+                        if c.x < x {
+                            inside = !inside;
+                        }
+                    }
+                }
+                if inside {
+                    Location::Interior
+                } else {
+                    Location::Exterior
+                }
+            }
+        }
+    }
+}
+
+fn mod2(mut ends: Vec<Coordinate<f64>>) -> Vec<Coordinate<f64>> {
+    ends.sort_by(|a, b| {
+        a.x.partial_cmp(&b.x)
+            .unwrap()
+            .then(a.y.partial_cmp(&b.y).unwrap())
+    });
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < ends.len() {
+        let mut j = i;
+        while j < ends.len() && ends[j] == ends[i] {
+            j += 1;
+        }
+        if (j - i) % 2 == 1 {
+            result.push(ends[i]);
+        }
+        i = j;
+    }
+    result
+}
+
+fn distance(a: Coordinate<f64>, b: Coordinate<f64>) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+fn cross(a: Coordinate<f64>, b: Coordinate<f64>) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+fn dot(a: Coordinate<f64>, b: Coordinate<f64>) -> f64 {
+    a.x * b.x + a.y * b.y
+}
+
+// Where `c` projects onto the segment, as a fraction of its length.
+fn param(s: &Line<f64>, c: Coordinate<f64>) -> f64 {
+    let d = s.delta();
+    let len2 = dot(d, d);
+    if len2 == 0. {
+        0.
+    } else {
+        dot(c - s.start, d) / len2
+    }
+}
+
+fn at(s: &Line<f64>, t: f64) -> Coordinate<f64> {
+    let d = s.delta();
+    (s.start.x + t * d.x, s.start.y + t * d.y).into()
+}
+
+fn segment_distance(s: &Line<f64>, c: Coordinate<f64>) -> f64 {
+    distance(at(s, param(s, c).clamp(0., 1.)), c)
+}
+
+fn boxes_overlap(a: &Line<f64>, b: &Line<f64>, eps: f64) -> bool {
+    a.start.x.min(a.end.x) <= b.start.x.max(b.end.x) + eps
+        && b.start.x.min(b.end.x) <= a.start.x.max(a.end.x) + eps
+        && a.start.y.min(a.end.y) <= b.start.y.max(b.end.y) + eps
+        && b.start.y.min(b.end.y) <= a.start.y.max(a.end.y) + eps
+}
+
+// Adds the points where `a` and `b` meet to `cuts_a` and `cuts_b`
+// (as parameters along each segment) and to `nodes`.
+fn node_segments(
+    a: &Line<f64>,
+    b: &Line<f64>,
+    eps: f64,
+    cuts_a: &mut Vec<f64>,
+    cuts_b: &mut Vec<f64>,
+    nodes: &mut Vec<Coordinate<f64>>,
+) {
+    if !boxes_overlap(a, b, eps) {
+        return;
+    }
+    let r = a.delta();
+    let s = b.delta();
+    let denom = cross(r, s);
+    let len_r = dot(r, r).sqrt();
+    let len_s = dot(s, s).sqrt();
+    if denom.abs() <= 1e-12 * len_r * len_s {
+        // Parallel segments only meet where one's endpoints sit on
+        // the other.
+        for c in [b.start, b.end].iter() {
+            if segment_distance(a, *c) <= eps {
+                cuts_a.push(param(a, *c));
+                nodes.push(*c);
+            }
+        }
+        for c in [a.start, a.end].iter() {
+            if segment_distance(b, *c) <= eps {
+                cuts_b.push(param(b, *c));
+                nodes.push(*c);
+            }
+        }
+        return;
+    }
+    let qp = b.start - a.start;
+    let t = cross(qp, s) / denom;
+    let u = cross(qp, r) / denom;
+    let tol_t = if len_r > 0. { eps / len_r } else { 0. };
+    let tol_u = if len_s > 0. { eps / len_s } else { 0. };
+    if t >= -tol_t && t <= 1. + tol_t && u >= -tol_u && u <= 1. + tol_u {
+        let t = t.clamp(0., 1.);
+        let u = u.clamp(0., 1.);
+        cuts_a.push(t);
+        cuts_b.push(u);
+        nodes.push(at(a, t));
+    }
+}
+
+// The linework of two shapes noded against each other.
+struct Noded {
+    // The tolerance coordinates are compared with.
+    eps: f64,
+    // The sorted cuts, as parameters from 0 to 1, along every segment
+    // of each shape.
+    cuts_a: Vec<Vec<f64>>,
+    cuts_b: Vec<Vec<f64>>,
+    nodes: Vec<Coordinate<f64>>,
+}
+
+fn node(a: &Parts, b: &Parts) -> Noded {
+    let scale = a
+        .vertices()
+        .chain(b.vertices())
+        .map(|c| c.x.abs().max(c.y.abs()))
+        .fold(0., f64::max);
+    let eps = 1e-10 * scale.max(1e-300);
+
+    let mut cuts_a: Vec<Vec<f64>> = vec![Vec::new(); a.segments.len()];
+    let mut cuts_b: Vec<Vec<f64>> = vec![Vec::new(); b.segments.len()];
+    let mut nodes: Vec<Coordinate<f64>> = a.vertices().chain(b.vertices()).collect();
+
+    for (sa, ca) in a.segments.iter().zip(cuts_a.iter_mut()) {
+        for (sb, cb) in b.segments.iter().zip(cuts_b.iter_mut()) {
+            node_segments(sa, sb, eps, ca, cb, &mut nodes);
+        }
+    }
+    // Isolated points and the mod 2 boundary of lineal shapes also
+    // split linework, whichever shape they belong to.
+    for (segments, cuts) in [(&a.segments, &mut cuts_a), (&b.segments, &mut cuts_b)].iter_mut() {
+        for (s, c) in segments.iter().zip(cuts.iter_mut()) {
+            for p in a
+                .points
+                .iter()
+                .chain(b.points.iter())
+                .chain(a.boundary.iter())
+                .chain(b.boundary.iter())
+            {
+                if segment_distance(s, *p) <= eps {
+                    c.push(param(s, *p));
+                }
+            }
+            c.push(0.);
+            c.push(1.);
+            c.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        }
+    }
+    Noded {
+        eps,
+        cuts_a,
+        cuts_b,
+        nodes,
+    }
+}
+
+// The pieces `cuts` split `s` into, leaving out any no longer than
+// `eps`. The ends of the segment are kept exactly, so the pieces of
+// consecutive segments meet exactly too.
+fn pieces<'a>(s: &'a Line<f64>, cuts: &'a [f64], eps: f64) -> impl Iterator<Item = Line<f64>> + 'a {
+    let length = dot(s.delta(), s.delta()).sqrt();
+    let point = move |t: f64| {
+        if t == 0. {
+            s.start
+        } else if t == 1. {
+            s.end
+        } else {
+            at(s, t)
+        }
+    };
+    cuts.windows(2)
+        .filter(move |w| (w[1] - w[0]) * length > eps)
+        .map(move |w| Line::new(point(w[0]), point(w[1])))
+}
+
+// Points just to the left and right of the middle of `piece`, close
+// enough to stay inside the faces next to it.
+fn sides(piece: &Line<f64>, eps: f64) -> [Coordinate<f64>; 2] {
+    let mid = at(piece, 0.5);
+    let d = piece.delta();
+    let length = dot(d, d).sqrt();
+    let step = (length * 1e-6).max(eps * 10.) / length;
+    [
+        (mid.x - step * d.y, mid.y + step * d.x).into(),
+        (mid.x + step * d.y, mid.y - step * d.x).into(),
+    ]
+}
+
+pub(crate) fn relate(a: &Parts, b: &Parts) -> IntersectionMatrix {
+    let Noded {
+        eps,
+        cuts_a,
+        cuts_b,
+        nodes,
+    } = node(a, b);
+
+    let mut matrix = IntersectionMatrix::empty();
+    matrix.add(
+        Location::Exterior,
+        Location::Exterior,
+        Dimensions::TwoDimensional,
+    );
+    for n in nodes {
+        matrix.add(
+            a.locate(n, eps),
+            b.locate(n, eps),
+            Dimensions::ZeroDimensional,
+        );
+    }
+
+    for (parts, cuts) in [(a, &cuts_a), (b, &cuts_b)].iter() {
+        for (s, c) in parts.segments.iter().zip(cuts.iter()) {
+            for piece in pieces(s, c, eps) {
+                let mid = at(&piece, 0.5);
+                matrix.add(
+                    a.locate(mid, eps),
+                    b.locate(mid, eps),
+                    Dimensions::OneDimensional,
+                );
+                if parts.dimension == 2 {
+                    // Step off the boundary to both sides, staying
+                    // well inside the faces next to this piece.
+                    for c in sides(&piece, eps).iter() {
+                        let (la, lb) = (a.locate(*c, eps), b.locate(*c, eps));
+                        if la != Location::Boundary && lb != Location::Boundary {
+                            matrix.add(la, lb, Dimensions::TwoDimensional);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    matrix
+}
+
+// For shapes we already know don't meet, the interior and boundary
+// of each lie in the other's exterior, so the matrix follows from
+// the shapes on their own without noding anything.
+pub(crate) fn disjoint_matrix(a: &Parts, b: &Parts) -> IntersectionMatrix {
+    use Location::*;
+    let mut matrix = IntersectionMatrix::empty();
+    matrix.add(Interior, Exterior, a.interior_dimensions());
+    matrix.add(Boundary, Exterior, a.boundary_dimensions());
+    matrix.add(Exterior, Interior, b.interior_dimensions());
+    matrix.add(Exterior, Boundary, b.boundary_dimensions());
+    matrix.add(Exterior, Exterior, Dimensions::TwoDimensional);
+    matrix
+}
+
+/// What two shapes have in common, short of building the shape of
+/// their intersection.
+pub(crate) struct Overlay {
+    // The area both cover; zero unless both are areal.
+    pub(crate) area: f64,
+    // The linework of the lineal shape (the first one, if both are)
+    // that lies inside or on the other shape, joined up where pieces
+    // meet end to start.
+    pub(crate) lines: Vec<Vec<Coordinate<f64>>>,
+    pub(crate) length: f64,
+}
+
+// Built from the same noding as `relate`. For the area we walk the
+// boundary of the intersection, which is made of pieces of either
+// shape's boundary, and add up the shoelace terms with its inside on
+// the left (Green's theorem). That holds for holes, multi-polygons
+// and either ring orientation, since we look at which side is inside
+// rather than trusting the order of the vertices.
+pub(crate) fn overlay(a: &Parts, b: &Parts) -> Overlay {
+    let Noded {
+        eps,
+        cuts_a,
+        cuts_b,
+        ..
+    } = node(a, b);
+    let mut result = Overlay {
+        area: 0.,
+        lines: Vec::new(),
+        length: 0.,
+    };
+
+    if a.dimension == 2 && b.dimension == 2 {
+        // Measuring from a nearby origin keeps the shoelace terms
+        // from losing precision far from (0, 0).
+        let origin = a.min;
+        let inside = |c: Coordinate<f64>| {
+            a.locate(c, eps) == Location::Interior && b.locate(c, eps) == Location::Interior
+        };
+        let mut twice_area = 0.;
+        for (i, (parts, cuts)) in [(a, &cuts_a), (b, &cuts_b)].iter().enumerate() {
+            for (s, c) in parts.segments.iter().zip(cuts.iter()) {
+                for piece in pieces(s, c, eps) {
+                    // Boundary the shapes share counts once, from `a`.
+                    if i == 1 && a.locate(at(&piece, 0.5), eps) == Location::Boundary {
+                        continue;
+                    }
+                    let [left, right] = sides(&piece, eps);
+                    let (start, end) = (piece.start - origin, piece.end - origin);
+                    match (inside(left), inside(right)) {
+                        (true, false) => twice_area += cross(start, end),
+                        (false, true) => twice_area += cross(end, start),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        result.area = (twice_area / 2.).max(0.);
+        return result;
+    }
+
+    let (line, other, cuts) = match (a.dimension, b.dimension) {
+        (1, _) => (a, b, &cuts_a),
+        (_, 1) => (b, a, &cuts_b),
+        _ => return result,
+    };
+    for (s, c) in line.segments.iter().zip(cuts.iter()) {
+        for piece in pieces(s, c, eps) {
+            if other.locate(at(&piece, 0.5), eps) == Location::Exterior {
+                continue;
+            }
+            result.length += distance(piece.start, piece.end);
+            match result.lines.last_mut() {
+                Some(last) if last.last() == Some(&piece.start) => last.push(piece.end),
+                _ => result.lines.push(vec![piece.start, piece.end]),
+            }
+        }
+    }
+    result
+}
+
+pub trait Topology {
+    fn parts(&self) -> Parts;
+}
+
+fn geometry_parts<C: CoordFloat>(geo: &Geometry<C>, position: usize) -> Result<Parts, Error> {
+    macro_rules! checked {
+        ($geo:ident) => {{
+            $geo.is_safe(position)?;
+            $geo.parts()
+        }};
+    }
+    Ok(match geo {
+        Geometry::Point(pt) => checked!(pt),
+        Geometry::Line(ln) => checked!(ln),
+        Geometry::Rect(r) => checked!(r),
+        Geometry::Triangle(tri) => checked!(tri),
+        Geometry::Polygon(poly) => checked!(poly),
+        Geometry::LineString(ls) => checked!(ls),
+        Geometry::MultiPoint(mp) => checked!(mp),
+        Geometry::MultiLineString(mls) => checked!(mls),
+        Geometry::MultiPolygon(mpoly) => checked!(mpoly),
+        Geometry::GeometryCollection(_) => {
+            return Err(Error::UnsupportedGeometry(position, "GeometryCollection"))
+        }
+    })
+}
+
+// The matrices are worked out in `f64` whatever the coordinates are.
+fn wide<C: CoordFloat>(c: Coordinate<C>) -> Coordinate<f64> {
+    (c.x.as_f64(), c.y.as_f64()).into()
+}
+
+fn wide_line<C: CoordFloat>(line: Line<C>) -> Line<f64> {
+    Line::new(wide(line.start), wide(line.end))
+}
+
+impl<C: CoordFloat> Topology for Point<C> {
+    fn parts(&self) -> Parts {
+        Parts::new(0, vec![wide(self.0)], Vec::new(), Vec::new())
+    }
+}
+
+impl<C: CoordFloat> Topology for MultiPoint<C> {
+    fn parts(&self) -> Parts {
+        Parts::new(
+            0,
+            self.0.iter().map(|p| wide(p.0)).collect(),
+            Vec::new(),
+            Vec::new(),
+        )
+    }
+}
+
+impl<C: CoordFloat> Topology for Line<C> {
+    fn parts(&self) -> Parts {
+        let line = wide_line(*self);
+        Parts::new(1, Vec::new(), vec![line], mod2(vec![line.start, line.end]))
+    }
+}
+
+impl<C: CoordFloat> Topology for LineString<C> {
+    fn parts(&self) -> Parts {
+        Parts::lineal(self.lines().map(wide_line).collect(), std::iter::once(self))
+    }
+}
+
+impl<C: CoordFloat> Topology for MultiLineString<C> {
+    fn parts(&self) -> Parts {
+        Parts::lineal(
+            self.0
+                .iter()
+                .flat_map(|ls| ls.lines())
+                .map(wide_line)
+                .collect(),
+            self.0.iter(),
+        )
+    }
+}
+
+fn polygon_segments<C: CoordFloat>(p: &Polygon<C>) -> impl Iterator<Item = Line<f64>> + '_ {
+    p.exterior()
+        .lines()
+        .chain(p.interiors().iter().flat_map(|ring| ring.lines()))
+        .map(wide_line)
+}
+
+impl<C: CoordFloat> Topology for Polygon<C> {
+    fn parts(&self) -> Parts {
+        Parts::areal(polygon_segments(self).collect())
+    }
+}
+
+impl<C: CoordFloat> Topology for Rect<C> {
+    fn parts(&self) -> Parts {
+        Parts::areal(rect_lines(self).iter().copied().map(wide_line).collect())
+    }
+}
+
+impl<C: CoordFloat> Topology for Triangle<C> {
+    fn parts(&self) -> Parts {
+        Parts::areal(self.to_lines().iter().copied().map(wide_line).collect())
+    }
+}
+
+impl<C: CoordFloat> Topology for MultiPolygon<C> {
+    fn parts(&self) -> Parts {
+        Parts::areal(self.0.iter().flat_map(polygon_segments).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Dimensions::*;
+    use Location::*;
+
+    fn square(x: f64, y: f64, side: f64) -> Polygon<f64> {
+        Rect::new((x, y), (x + side, y + side)).to_polygon()
+    }
+
+    #[test]
+    fn adjacent_squares() {
+        let m = relate(&square(0., 0., 1.).parts(), &square(1., 0., 1.).parts());
+        assert_eq!(m.get(Interior, Interior), Empty);
+        assert_eq!(m.get(Boundary, Boundary), OneDimensional);
+        assert_eq!(m.get(Interior, Exterior), TwoDimensional);
+        assert!(m.is_touches());
+        assert!(!m.is_overlaps(2, 2));
+        assert!(!m.is_covers());
+    }
+
+    #[test]
+    fn nested_squares() {
+        let m = relate(&square(0., 0., 2.).parts(), &square(0., 0., 1.).parts());
+        assert!(m.is_covers());
+        assert!(!m.is_covered_by());
+        assert!(!m.is_touches());
+        assert_eq!(m.get(Boundary, Boundary), OneDimensional);
+        assert_eq!(m.get(Exterior, Interior), Empty);
+    }
+
+    #[test]
+    fn line_crossing_square() {
+        let line = Line::new((-1., 0.5), (2., 0.5));
+        let m = relate(&line.parts(), &square(0., 0., 1.).parts());
+        assert_eq!(m.get(Interior, Interior), OneDimensional);
+        assert_eq!(m.get(Interior, Boundary), ZeroDimensional);
+        assert_eq!(m.get(Boundary, Exterior), ZeroDimensional);
+        assert!(m.is_crosses(1, 2));
+    }
+
+    #[test]
+    fn patterns() {
+        let m = relate(&square(0., 0., 1.).parts(), &square(0., 0., 2.).parts());
+        assert!(m.matches(&RelatePattern::new("T*F**F***").unwrap()));
+        assert!(m.matches(&RelatePattern::new("2FF11F212").unwrap()));
+        assert!(!m.matches(&RelatePattern::new("FF*FF****").unwrap()));
+        assert!(!m.matches(&RelatePattern::new("2120*****").unwrap()));
+    }
+
+    #[test]
+    fn disjoint_matrices() {
+        let ring: LineString<f64> = vec![(0., 0.), (1., 0.), (1., 1.), (0., 0.)].into();
+        let line = Line::new((5., 5.), (6., 5.));
+        let shapes = [
+            Point::new(9., 0.).parts(),
+            ring.parts(),
+            line.parts(),
+            square(3., 3., 1.).parts(),
+        ];
+        for a in shapes.iter() {
+            for b in shapes.iter().filter(|b| !std::ptr::eq(*b, a)) {
+                assert_eq!(disjoint_matrix(a, b), relate(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn public_matrices() {
+        let m = IntersectionMatrix::new(
+            &Geometry::Polygon(square(0., 0., 1.)),
+            &Geometry::Line(Line::new((-1., 0.5), (2., 0.5))),
+        )
+        .unwrap();
+        assert_eq!(m.to_string(), "1F20F1102");
+        assert!(m.matches(&RelatePattern::new("T********").unwrap()));
+        let bad = Geometry::Point(Point::new(f64::NAN, 0.));
+        assert!(matches!(
+            IntersectionMatrix::new(&Geometry::Point(Point::new(0., 0.)), &bad),
+            Err(Error::BadCoordinateValue(1, _))
+        ));
+    }
+
+    #[test]
+    fn closed_line_string_has_no_boundary() {
+        let ring: LineString<f64> = vec![(0., 0.), (1., 0.), (1., 1.), (0., 0.)].into();
+        let m = relate(&ring.parts(), &Point::new(0., 0.).parts());
+        assert_eq!(m.get(Interior, Interior), ZeroDimensional);
+        assert_eq!(m.get(Boundary, Interior), Empty);
+    }
+}
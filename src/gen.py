@@ -26,7 +26,7 @@ class MethodImpl:
 
     def __str__(self):
         prefix = '_' if 'other' not in self.body else ''
-        return (f'    fn {self.method.name}(&self,  {prefix}other: &{self.other_t}<f64>) -> {self.method.type} ' +
+        return (f'    fn {self.method.name}(&self,  {prefix}other: &{self.other_t}<C>) -> {self.method.type} ' +
                 '{\n        ' + self.body + '\n    }')
 
 
@@ -37,7 +37,7 @@ class Group:
     impls: T.List[MethodImpl]
 
     def __str__(self):
-        header = f'impl Relates<{self.other_t}<f64>> for {self.self_t}<f64> ' + '{\n'
+        header = f'impl<C: CoordFloat> Relates<{self.other_t}<C>> for {self.self_t}<C> ' + '{\n'
         return header + '\n'.join(map(str, self.impls)) + '\n}\n'
 
     @staticmethod
@@ -79,21 +79,31 @@ contains = allothers(
      for b in types
      if a.dimensions < b.dimensions] +
     [MethodImpl(Contains, 'LineString', 'LineString', 'other.lines().all(|oline| self.lines().any(|sline| sline.Contains(&oline)))'),
-     MethodImpl(Contains, 'Rect', 'Line', 'self.contains(&other.start_point()) && self.contains(&other.end_point())'),
-     MethodImpl(Contains, 'Rect', 'LineString', 'other.points_iter().all(|pt| self.Contains(&pt))'),
+     # geo counts the endpoints of a line as inside it, but they're its boundary.
+     MethodImpl(Contains, 'Line', 'Point', 'self.intersects(other) && other.0 != self.start && other.0 != self.end'),
+     MethodImpl(Contains, 'LineString', 'Point', 'self.contains(other) && (self.is_closed() || (self.0.first() != Some(&other.0) && self.0.last() != Some(&other.0)))'),
+     # Rects and triangles go through the convex helpers in relates.rs, since geo's
+     # versions count the boundary as inside.
+     MethodImpl(Contains, 'Rect', 'Point', 'convex_contains(&rect_lines(self), other.0)'),
+     MethodImpl(Contains, 'Rect', 'Line', 'let edges = rect_lines(self); convex_covers(&edges, other.start) && convex_covers(&edges, other.end) && !along_edge(&edges, other)'),
+     MethodImpl(Contains, 'Rect', 'LineString', 'let edges = rect_lines(self); other.0.iter().all(|c| convex_covers(&edges, *c)) && other.lines().any(|oline| !along_edge(&edges, &oline))'),
      MethodImpl(Contains, 'Rect', 'Triangle', 'self.contains(&Point(other.0)) && self.contains(&Point(other.1)) && self.contains(&Point(other.2))'),
-     MethodImpl(Contains, 'Rect', 'Polygon', 'other.exterior().points_iter().all(|pt| self.Contains(&pt))'),
-     # FIX for bug in geo-types, https://github.com/georust/geo/issues/473, delete when they fix it:
-     MethodImpl(Contains, 'Triangle', 'Point', 'if self.0 == self.1 && self.1 == self.2 {self.0 == other.0} else {self.contains(other)}'),
-     # We're using Contains instead of contains only to work around the geo-types bug
-     MethodImpl(Contains, 'Triangle', 'Line', 'self.Contains(&other.start_point()) && self.Contains(&other.end_point())'),
-     MethodImpl(Contains, 'Triangle', 'LineString', 'other.lines().all(|line| self.Contains(&line))'),
-     # We're using Contains instead of contains only to work around the geo-types bug
-     MethodImpl(Contains, 'Triangle', 'Triangle', 'self.Contains(&Point(other.0)) && self.Contains(&Point(other.1)) && self.Contains(&Point(other.2))'),
-     MethodImpl(Contains, 'Triangle', 'Polygon', 'other.exterior().points_iter().all(|pt| self.Contains(&pt))'),
-     MethodImpl(Contains, 'Triangle', 'Rect', 'rect_lines(other).iter().all(|line| self.Contains(line))'),
-     MethodImpl(Contains, 'Polygon', 'Rect', 'rect_lines(other).iter().all(|line| self.contains(line))'),
-     MethodImpl(Contains, 'Polygon', 'Triangle', 'self.contains(&Point(other.0)) && self.contains(&Point(other.1)) && self.contains(&Point(other.2))'),
+     MethodImpl(Contains, 'Rect', 'Polygon', 'other.exterior().points_iter().all(|pt| self.contains(&pt))'),
+     # A triangle squashed to a point still contains that point, see https://github.com/georust/geo/issues/473
+     MethodImpl(Contains, 'Triangle', 'Point', 'if self.0 == self.1 && self.1 == self.2 {self.0 == other.0} else {convex_contains(&self.to_lines(), other.0)}'),
+     MethodImpl(Contains, 'Triangle', 'Line', 'let edges = self.to_lines(); convex_covers(&edges, other.start) && convex_covers(&edges, other.end) && !along_edge(&edges, other)'),
+     MethodImpl(Contains, 'Triangle', 'LineString', 'let edges = self.to_lines(); other.0.iter().all(|c| convex_covers(&edges, *c)) && other.lines().any(|oline| !along_edge(&edges, &oline))'),
+     MethodImpl(Contains, 'Triangle', 'Triangle', 'let edges = self.to_lines(); other.to_array().iter().all(|c| convex_covers(&edges, *c))'),
+     MethodImpl(Contains, 'Triangle', 'Polygon', 'let edges = self.to_lines(); other.exterior().0.iter().all(|c| convex_covers(&edges, *c))'),
+     MethodImpl(Contains, 'Triangle', 'Rect', 'let edges = self.to_lines(); rect_points(other).iter().all(|c| convex_covers(&edges, *c))'),
+     # geo's Polygon::contains wants the other shape strictly inside. One that touches
+     # the boundary from inside is still contained, so once all of its vertices are on
+     # or inside the polygon we let the intersection matrix decide.
+     MethodImpl(Contains, 'Polygon', 'Line', 'self.contains(other) || (self.Intersects(&other.start_point()) && self.Intersects(&other.end_point()) && self.relate_matrix(other).is_contains())'),
+     MethodImpl(Contains, 'Polygon', 'LineString', 'self.contains(other) || (other.points_iter().all(|pt| self.Intersects(&pt)) && self.relate_matrix(other).is_contains())'),
+     MethodImpl(Contains, 'Polygon', 'Polygon', 'self.contains(other) || (other.exterior().points_iter().all(|pt| self.Intersects(&pt)) && self.relate_matrix(other).is_contains())'),
+     MethodImpl(Contains, 'Polygon', 'Rect', 'rect_lines(other).iter().all(|line| self.contains(line)) || (rect_points(other).iter().all(|c| self.Intersects(&Point(*c))) && self.relate_matrix(other).is_contains())'),
+     MethodImpl(Contains, 'Polygon', 'Triangle', 'other.to_lines().iter().all(|line| self.contains(line)) || (other.to_array().iter().all(|c| self.Intersects(&Point(*c))) && self.relate_matrix(other).is_contains())'),
     ],
     Contains, 'self.contains(other)')
 
@@ -101,17 +111,21 @@ contains = allothers(
 intersects = allothers(
     swapped(
         [MethodImpl(Intersects, 'Point', 'Point', 'self == other'), # FIXME: should be relative_eq!(0.0, line distance between self and other) to match geo-types
-         MethodImpl(Intersects, 'Polygon', 'Point', 'self.contains(other)'),
+         # Polygon's contains leaves out the boundary, so we check the rings too.
+         MethodImpl(Intersects, 'Polygon', 'Point', 'self.contains(other) || self.exterior().contains(other) || self.interiors().iter().any(|hole| hole.contains(other))'),
          MethodImpl(Intersects, 'LineString', 'Point', 'self.contains(other)'),
-         MethodImpl(Intersects, 'Rect', 'Point', 'self.Contains(other)'),
+         # geo's LineString::intersects skips parallel segments, so overlapping ones go unnoticed.
+         MethodImpl(Intersects, 'LineString', 'LineString', 'self.lines().any(|sline| other.lines().any(|oline| sline.intersects(&oline)))'),
+         MethodImpl(Intersects, 'Polygon', 'LineString', 'other.lines().any(|oline| self.intersects(&oline))'),
+         MethodImpl(Intersects, 'Rect', 'Point', 'self.contains(other)'),
          MethodImpl(Intersects, 'Rect', 'Line',       'self.Contains(other) || rect_lines(self).iter().any(|sline| sline.intersects(other))'),
          MethodImpl(Intersects, 'Rect', 'LineString', 'self.Contains(other) || rect_lines(self).iter().any(|sline| other.lines().any(|oline| sline.intersects(&oline)))'),
          MethodImpl(Intersects, 'Rect', 'Triangle',   'self.Contains(other) || rect_lines(self).iter().any(|sline| other.to_lines().iter().any(|oline| sline.intersects(oline))) || other.Contains(self)'),
-         MethodImpl(Intersects, 'Triangle', 'Point', 'self.Contains(other)'),
+         MethodImpl(Intersects, 'Triangle', 'Point', 'convex_covers(&self.to_lines(), other.0)'),
          MethodImpl(Intersects, 'Triangle', 'Line',       'self.Contains(other) || self.to_lines().iter().any(|sline| sline.intersects(other))'),
          MethodImpl(Intersects, 'Triangle', 'LineString', 'self.Contains(other) || self.to_lines().iter().any(|sline| other.lines().any(|oline| sline.intersects(&oline)))'),
          MethodImpl(Intersects, 'Triangle', 'Triangle',   'self.Contains(other) || self.to_lines().iter().any(|sline| other.to_lines().iter().any(|oline| sline.intersects(oline))) || other.Contains(self)'),
-         MethodImpl(Intersects, 'Triangle', 'Polygon', 'self.Intersects(other.exterior()) || (other.exterior().Contains(self) || if other.interiors().is_empty() {false} else {other.interiors().iter().all(|hole| !hole.Contains(self))} )'),
+         MethodImpl(Intersects, 'Triangle', 'Polygon', 'self.to_polygon().intersects(other)'),
         ]),
     Intersects, 'self.intersects(other)')
 
@@ -119,19 +133,19 @@ intersects = allothers(
 # https://github.com/georust/geo/issues/476 means that Rect/Polygon distances and probably Tri/Poly are busted
 dists = allothers(
     swapped(
-    [MethodImpl(EuclideanDistance, 'Polygon', 'Line', 'if self.intersects(other) {0.0} else {self.exterior().lines().map(|sline| sline.euclidean_distance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'), # this is only needed until https://github.com/georust/geo/issues/476 gets fixed
-     MethodImpl(EuclideanDistance, 'Rect',       'Point', 'if self.Intersects(other) {0.0} else {rect_lines(self).iter().map(|sline| sline.euclidean_distance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
-     MethodImpl(EuclideanDistance, 'Rect',       'Line', 'if self.Intersects(other) {0.0} else {rect_lines(self).iter().map(|sline| sline.euclidean_distance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
-     MethodImpl(EuclideanDistance, 'Rect',       'LineString', 'if self.Intersects(other) {0.0} else {rect_lines(self).iter().map(|sline| sline.euclidean_distance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
+    [MethodImpl(EuclideanDistance, 'Polygon', 'Line', 'if self.intersects(other) {0.0} else {self.exterior().lines().map(|sline| sline.euclidean_distance(other).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'), # this is only needed until https://github.com/georust/geo/issues/476 gets fixed
+     MethodImpl(EuclideanDistance, 'Rect',       'Point', 'if self.Intersects(other) {0.0} else {rect_lines(self).iter().map(|sline| sline.euclidean_distance(other).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
+     MethodImpl(EuclideanDistance, 'Rect',       'Line', 'if self.Intersects(other) {0.0} else {rect_lines(self).iter().map(|sline| sline.euclidean_distance(other).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
+     MethodImpl(EuclideanDistance, 'Rect',       'LineString', 'if self.Intersects(other) {0.0} else {rect_lines(self).iter().map(|sline| sline.euclidean_distance(other).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
      MethodImpl(EuclideanDistance, 'Rect',       'Triangle', 'if self.Intersects(other) {0.0} else {rect_lines(self).iter().map(|sline| sline.EuclideanDistance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
      MethodImpl(EuclideanDistance, 'Rect',       'Rect', 'if self.Intersects(other) {0.0} else {rect_lines(other).iter().map(|oline| oline.EuclideanDistance(self)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
      MethodImpl(EuclideanDistance, 'Rect',       'Polygon', 'if self.Intersects(other.exterior()) {0.0} else {rect_lines(self).iter().map(|sline| sline.EuclideanDistance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'), # we can switch back to euclidean_distance after 476 gets fixed
-     MethodImpl(EuclideanDistance, 'Triangle',   'Point', 'if self.Intersects(other) {0.0} else {self.to_lines().iter().map(|sline| sline.euclidean_distance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
-     MethodImpl(EuclideanDistance, 'Triangle',   'Line', 'if self.Intersects(other) {0.0} else {self.to_lines().iter().map(|sline| sline.euclidean_distance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
-     MethodImpl(EuclideanDistance, 'Triangle',   'LineString', 'if self.Intersects(other) {0.0} else {self.to_lines().iter().map(|sline| other.lines().map(|oline| oline.euclidean_distance(sline)).min_by(|a, b| a.partial_cmp(b).unwrap())).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap().unwrap()}'),
-     MethodImpl(EuclideanDistance, 'Triangle',   'Triangle', 'if self.Intersects(other) {0.0} else {other.to_lines().iter().map(|oline| self.to_lines().iter().map(|sline| sline.euclidean_distance(oline)).min_by(|a, b| a.partial_cmp(b).unwrap())).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap().unwrap()}'),
+     MethodImpl(EuclideanDistance, 'Triangle',   'Point', 'if self.Intersects(other) {0.0} else {self.to_lines().iter().map(|sline| sline.euclidean_distance(other).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
+     MethodImpl(EuclideanDistance, 'Triangle',   'Line', 'if self.Intersects(other) {0.0} else {self.to_lines().iter().map(|sline| sline.euclidean_distance(other).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'),
+     MethodImpl(EuclideanDistance, 'Triangle',   'LineString', 'if self.Intersects(other) {0.0} else {self.to_lines().iter().map(|sline| other.lines().map(|oline| oline.euclidean_distance(sline).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap())).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap().unwrap()}'),
+     MethodImpl(EuclideanDistance, 'Triangle',   'Triangle', 'if self.Intersects(other) {0.0} else {other.to_lines().iter().map(|oline| self.to_lines().iter().map(|sline| sline.euclidean_distance(oline).as_f64()).min_by(|a, b| a.partial_cmp(b).unwrap())).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap().unwrap()}'),
      MethodImpl(EuclideanDistance, 'Triangle',   'Polygon', 'if self.Intersects(other.exterior()) {0.0} else {self.to_lines().iter().map(|sline| sline.EuclideanDistance(other)).min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()}'), # we can switch back to euclidean_distance after 476 gets fixed
-     ]), EuclideanDistance, 'self.euclidean_distance(other)')
+     ]), EuclideanDistance, 'self.euclidean_distance(other).as_f64()')
 
 
 for group in Group.from_impls(contains + intersects + dists):
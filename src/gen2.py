@@ -1,4 +1,4 @@
-cases = 'Line Point Polygon LineString Rect Triangle'.split()
+cases = 'Line Point Polygon LineString Rect Triangle MultiPoint MultiLineString MultiPolygon'.split()
 print("macro_rules! enum_dispatch { ($a:ident, $b:ident, $expr:expr) => { match ($a, $b) {")
 for a in cases:
     for b in cases:
@@ -0,0 +1,288 @@
+use geo::algorithm::vincenty_distance::VincentyDistance;
+use geo::{Coordinate, Point};
+
+use crate::de9im::Parts;
+
+type RTreeEnvelope = rstar::AABB<[f64; 2]>;
+
+// Mean radius of the WGS84 ellipsoid, in meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+// Distances on the ellipsoid differ from distances on a sphere with
+// the mean radius by under 0.6%, so this much slack makes the
+// spherical bounds below safe for ellipsoidal distances too.
+const SLACK: f64 = 0.01;
+
+// We find closest points on the sphere, treating every edge as a
+// great-circle arc, and then measure the distance between those two
+// points on the WGS84 ellipsoid with Vincenty's formula. Shapes that
+// intersect (by the usual planar rules on longitude and latitude)
+// are 0 meters apart; that check happens before we get here. Arcs
+// can still cross where their planar segments don't, away from any
+// vertex, so we look for that before measuring from the vertices.
+pub(crate) fn distance(a: &Parts, b: &Parts) -> f64 {
+    for s in a.segments() {
+        for t in b.segments() {
+            if arcs_cross(unit(s.start), unit(s.end), unit(t.start), unit(t.end)) {
+                return 0.;
+            }
+        }
+    }
+
+    let mut best: Option<(f64, [f64; 3], [f64; 3])> = None;
+    let mut consider = |p: [f64; 3], q: [f64; 3]| {
+        let d = angle(p, q);
+        if !matches!(best, Some((best_d, _, _)) if best_d <= d) {
+            best = Some((d, p, q));
+        }
+    };
+
+    for p in a.vertices().map(unit) {
+        if b.segments().is_empty() {
+            for q in b.vertices().map(unit) {
+                consider(p, q);
+            }
+        }
+        for s in b.segments() {
+            consider(p, closest_on_arc(p, unit(s.start), unit(s.end)));
+        }
+    }
+    for q in b.vertices().map(unit) {
+        for s in a.segments() {
+            consider(closest_on_arc(q, unit(s.start), unit(s.end)), q);
+        }
+    }
+
+    let (d, p, q) = best.expect("geometries always have at least one vertex");
+    let (p, q): (Point<f64>, Point<f64>) = (coord(p).into(), coord(q).into());
+    // Vincenty doesn't converge for nearly antipodal points; the
+    // sphere is close enough there.
+    p.vincenty_distance(&q).unwrap_or(d * EARTH_RADIUS)
+}
+
+/// Grows `bbox` (in degrees) by `meters` in every direction, allowing
+/// for meridians converging towards the poles and for great-circle
+/// edges bulging out of the box.
+pub(crate) fn buffer(bbox: RTreeEnvelope, meters: f64) -> RTreeEnvelope {
+    let (lower, upper) = (bbox.lower(), bbox.upper());
+    let delta = meters * (1. + SLACK) / EARTH_RADIUS;
+    let bulge = arc_bulge(&bbox);
+    let lat_max = (lower[1].abs().max(upper[1].abs()) + bulge)
+        .min(90.)
+        .to_radians();
+    // From latitude `lat_max` the furthest you can get in longitude
+    // within an angular distance `delta` is asin(sin delta / cos
+    // lat), unless you can reach the pole.
+    let dlon = if delta >= std::f64::consts::FRAC_PI_2 - lat_max {
+        180.
+    } else {
+        (delta.sin() / lat_max.cos()).asin().to_degrees()
+    };
+    let dlat = delta.to_degrees() + bulge;
+    RTreeEnvelope::from_corners(
+        [lower[0] - dlon, lower[1] - dlat],
+        [upper[0] + dlon, upper[1] + dlat],
+    )
+}
+
+// How far (in degrees of latitude) a great-circle arc between two
+// points in `bbox` can get beyond the box. An arc at latitude `lat`
+// spanning `2 w` of longitude peaks at atan(tan lat / cos w), and the
+// overshoot is largest where tan lat = sqrt(cos w).
+fn arc_bulge(bbox: &RTreeEnvelope) -> f64 {
+    let (lower, upper) = (bbox.lower(), bbox.upper());
+    let half_width = ((upper[0] - lower[0]) / 2.).to_radians();
+    if half_width >= std::f64::consts::FRAC_PI_2 {
+        // Arcs that long can pass over a pole.
+        return 90.;
+    }
+    let c = half_width.cos();
+    let lat = lower[1]
+        .abs()
+        .max(upper[1].abs())
+        .min(90.)
+        .to_radians()
+        .min(c.sqrt().atan());
+    ((lat.tan() / c).atan() - lat).to_degrees()
+}
+
+/// A lower bound, in meters, on the distance between anything inside
+/// `a` and anything inside `b`.
+pub(crate) fn env_distance(a: &RTreeEnvelope, b: &RTreeEnvelope) -> f64 {
+    let (a_lower, a_upper) = (a.lower(), a.upper());
+    let (b_lower, b_upper) = (b.lower(), b.upper());
+    let dlat = (b_lower[1] - a_upper[1])
+        .max(a_lower[1] - b_upper[1])
+        .max(0.)
+        .to_radians();
+    // Longitudes wrap around, so a big gap one way may be a small
+    // one the other way.
+    let span = a_upper[0].max(b_upper[0]) - a_lower[0].min(b_lower[0]);
+    let dlon = (b_lower[0] - a_upper[0])
+        .max(a_lower[0] - b_upper[0])
+        .min(360. - span)
+        .clamp(0., 90.)
+        .to_radians();
+    let lat_max = [a_lower[1], a_upper[1], b_lower[1], b_upper[1]]
+        .iter()
+        .fold(0., |acc: f64, lat| acc.max(lat.abs()))
+        .min(90.)
+        .to_radians();
+    // Anything dlon away in longitude is at least as far as the
+    // nearest meridian that far away.
+    let angle = dlat.max((lat_max.cos() * dlon.sin()).asin());
+    angle * EARTH_RADIUS * (1. - SLACK)
+}
+
+fn unit(c: Coordinate<f64>) -> [f64; 3] {
+    let lat = c.y.clamp(-90., 90.).to_radians();
+    let lon = c.x.to_radians();
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn coord(v: [f64; 3]) -> Coordinate<f64> {
+    (
+        v[1].atan2(v[0]).to_degrees(),
+        v[2].atan2(v[0].hypot(v[1])).to_degrees(),
+    )
+        .into()
+}
+
+fn dot(u: [f64; 3], v: [f64; 3]) -> f64 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+fn cross(u: [f64; 3], v: [f64; 3]) -> [f64; 3] {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+fn scale(u: [f64; 3], s: f64) -> [f64; 3] {
+    [u[0] * s, u[1] * s, u[2] * s]
+}
+
+fn norm(u: [f64; 3]) -> f64 {
+    dot(u, u).sqrt()
+}
+
+fn angle(u: [f64; 3], v: [f64; 3]) -> f64 {
+    norm(cross(u, v)).atan2(dot(u, v))
+}
+
+// The unit normal of the great circle through `a` and `b`, or None
+// if they're too close together (or apart) to pick one out.
+fn normal(a: [f64; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let n = cross(a, b);
+    let n_len = norm(n);
+    if n_len < 1e-15 {
+        None
+    } else {
+        Some(scale(n, 1. / n_len))
+    }
+}
+
+// Whether `c`, a point on the great circle with normal `n` through
+// `a` and `b`, lies on the minor arc between them.
+fn on_arc(c: [f64; 3], a: [f64; 3], b: [f64; 3], n: [f64; 3]) -> bool {
+    dot(cross(a, c), n) >= 0. && dot(cross(c, b), n) >= 0.
+}
+
+// Whether the minor arcs from `a` to `b` and from `c` to `d` cross.
+// Two great circles meet at a pair of antipodal points, so we check
+// both. Arcs on the same great circle only meet at a vertex, which
+// the vertex-to-arc distances already find.
+fn arcs_cross(a: [f64; 3], b: [f64; 3], c: [f64; 3], d: [f64; 3]) -> bool {
+    let (m, n) = match (normal(a, b), normal(c, d)) {
+        (Some(m), Some(n)) => (m, n),
+        _ => return false,
+    };
+    let x = match normal(m, n) {
+        Some(x) => x,
+        None => return false,
+    };
+    [x, scale(x, -1.)]
+        .iter()
+        .any(|&x| on_arc(x, a, b, m) && on_arc(x, c, d, n))
+}
+
+// The point on the (minor) great-circle arc from `a` to `b` nearest
+// to `p`.
+fn closest_on_arc(p: [f64; 3], a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    let nearer_end = if angle(p, a) <= angle(p, b) { a } else { b };
+    let n = match normal(a, b) {
+        Some(n) => n,
+        None => return nearer_end,
+    };
+    let pn = dot(p, n);
+    let c = [p[0] - pn * n[0], p[1] - pn * n[1], p[2] - pn * n[2]];
+    let c_len = norm(c);
+    if c_len < 1e-15 {
+        // p is a pole of the arc's great circle, so every point on
+        // the arc is equally far away.
+        return nearer_end;
+    }
+    let c = scale(c, 1. / c_len);
+    if on_arc(c, a, b, n) {
+        c
+    } else {
+        nearer_end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de9im::Topology;
+    use geo::Line;
+    use rstar::Envelope;
+
+    #[test]
+    fn point_to_meridian() {
+        // One degree of longitude along the equator is about 111 km.
+        let p = Point::new(1., 0.).parts();
+        let l = Line::new((0., -10.), (0., 10.)).parts();
+        assert!((distance(&p, &l) - 111_319.5).abs() < 1.);
+        // Past the end of the segment we measure to the endpoint.
+        let p = Point::new(0., 11.).parts();
+        assert!((distance(&p, &l) - 110_600.).abs() < 1000.);
+    }
+
+    #[test]
+    fn crossing_arcs() {
+        // The arc along latitude 10 bulges north to about 10.15 at
+        // longitude 0, so it crosses the meridian segment even though
+        // the planar segments don't meet and every vertex is over
+        // 5 km from the other arc.
+        let a = Line::new((-10., 10.), (10., 10.)).parts();
+        let b = Line::new((0., 10.05), (0., 10.3)).parts();
+        assert_eq!(distance(&a, &b), 0.);
+        assert_eq!(distance(&b, &a), 0.);
+        let b = Line::new((0., 10.2), (0., 10.3)).parts();
+        assert!((distance(&a, &b) - 5_400.).abs() < 200.);
+    }
+
+    #[test]
+    fn buffer_widens_towards_the_poles() {
+        let at_equator = buffer(RTreeEnvelope::from_point([0., 0.]), 100_000.);
+        let at_sixty = buffer(RTreeEnvelope::from_point([0., 60.]), 100_000.);
+        assert!(at_sixty.upper()[0] > 1.9 * at_equator.upper()[0]);
+        assert!((at_sixty.upper()[1] - 60. - at_equator.upper()[1]).abs() < 1e-9);
+        let at_pole = buffer(RTreeEnvelope::from_point([0., 89.5]), 100_000.);
+        assert_eq!(at_pole.upper()[0], 180.);
+    }
+
+    #[test]
+    fn buffer_covers_arc_bulge() {
+        // The arc between these endpoints peaks about 17 m north of
+        // latitude 1 and passes about 5 m from `p`.
+        let l = Line::new((-1., 1.), (1., 1.));
+        let p = Point::new(0., 1.0002);
+        let d = distance(&p.parts(), &l.parts());
+        assert!(d < 10.);
+        let bbox = buffer(RTreeEnvelope::from_corners([-1., 1.], [1., 1.]), d);
+        assert!(bbox.contains_point(&[0., 1.0002]));
+    }
+}
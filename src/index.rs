@@ -1,14 +1,27 @@
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::convert::TryInto;
+use std::iter::Peekable;
+#[cfg(feature = "parallel")]
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use geo::{
+    Coordinate, Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point,
+    Polygon, Rect, Triangle,
+};
 use smallvec::SmallVec;
 
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
-use crate::rtrees::Envelope;
+use crate::antimeridian::{shifted, unwrap_all};
+use crate::de9im::{overlay, Topology};
+use crate::rtrees::{candidates, farthest, grow, own_distance, query_envelope, Envelope};
+use crate::structs::Stored;
+use crate::validation::IsSafe;
 use crate::{
-    Config, Error, Interaction, ProxMapGeoRow, ProxMapRow, SJoinGeoRow, SJoinRow, SpatialIndex,
-    SplitGeoSeq,
+    Config, CoordFloat, DistanceMetric, Error, Interaction, ProxMapGeoRow, ProxMapLeftRow,
+    ProxMapRow, SJoinGeoRow, SJoinLeftRow, SJoinOverlapRow, SJoinRow, SpatialIndex, SplitGeoSeq,
 };
 #[cfg(feature = "parallel")]
 use crate::{Par, ParSpatialIndex};
@@ -71,6 +84,33 @@ macro_rules! join_outer {
                 .line_strings
                 .into_iter()
                 .zip($big.indexes.line_strings.into_iter())
+                .flat_map(move |($geo_big, $ext_index_big)| {
+                    let $env = $geo_big.to_env();
+                    let $geo_big = &$geo_big;
+                    $expr_noncopyable.into_iter()
+                }),
+            $big.geos
+                .multi_points
+                .into_iter()
+                .zip($big.indexes.multi_points.into_iter())
+                .flat_map(move |($geo_big, $ext_index_big)| {
+                    let $env = $geo_big.to_env();
+                    let $geo_big = &$geo_big;
+                    $expr_noncopyable.into_iter()
+                }),
+            $big.geos
+                .multi_line_strings
+                .into_iter()
+                .zip($big.indexes.multi_line_strings.into_iter())
+                .flat_map(move |($geo_big, $ext_index_big)| {
+                    let $env = $geo_big.to_env();
+                    let $geo_big = &$geo_big;
+                    $expr_noncopyable.into_iter()
+                }),
+            $big.geos
+                .multi_polys
+                .into_iter()
+                .zip($big.indexes.multi_polys.into_iter())
                 .flat_map(move |($geo_big, $ext_index_big)| {
                     let $env = $geo_big.to_env();
                     let $geo_big = &$geo_big;
@@ -131,6 +171,33 @@ macro_rules! par_join_outer {
                 .line_strings
                 .into_par_iter()
                 .zip($big.indexes.line_strings.into_par_iter())
+                .flat_map(move |($geo_big, $ext_index_big)| {
+                    let $env = $geo_big.to_env();
+                    let $geo_big = &$geo_big;
+                    $expr_noncopyable.into_iter().par_bridge()
+                }),
+            $big.geos
+                .multi_points
+                .into_par_iter()
+                .zip($big.indexes.multi_points.into_par_iter())
+                .flat_map(move |($geo_big, $ext_index_big)| {
+                    let $env = $geo_big.to_env();
+                    let $geo_big = &$geo_big;
+                    $expr_noncopyable.into_iter().par_bridge()
+                }),
+            $big.geos
+                .multi_line_strings
+                .into_par_iter()
+                .zip($big.indexes.multi_line_strings.into_par_iter())
+                .flat_map(move |($geo_big, $ext_index_big)| {
+                    let $env = $geo_big.to_env();
+                    let $geo_big = &$geo_big;
+                    $expr_noncopyable.into_iter().par_bridge()
+                }),
+            $big.geos
+                .multi_polys
+                .into_par_iter()
+                .zip($big.indexes.multi_polys.into_par_iter())
                 .flat_map(move |($geo_big, $ext_index_big)| {
                     let $env = $geo_big.to_env();
                     let $geo_big = &$geo_big;
@@ -140,175 +207,431 @@ macro_rules! par_join_outer {
     }};
 }
 
+// With `Config::geographic` a small geometry can come up more than
+// once, shifted by different numbers of turns (see antimeridian.rs);
+// we keep the first shift that matches.
 macro_rules! join_inner_copyable {
-    ($pm:expr;
+    ($pm:expr, $reach:expr;
      $geo_big:ident, $ext_index_big:ident, $env:ident,
-     $geo_small:ident, $ext_index_small:ident;
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
      $expr:expr) => {{
+        let $env = grow(
+            query_envelope($env, &$pm.config),
+            $reach,
+            $pm.config.distance_metric,
+        );
         chain!(
-            $pm.point_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    let $geo_big = $geo_big.clone();
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.points[index_small];
-                        let $ext_index_small = $pm.small.indexes.points.get(index_small);
-                        let $geo_big = &$geo_big;
-                        $expr
+            candidates(&$pm.point_tree, &$env, &$pm.config).filter_map({
+                let $geo_big = $geo_big.clone();
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
                     }
-                }),
-            $pm.line_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    let $geo_big = $geo_big.clone();
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.lines[index_small];
-                        let $ext_index_small = $pm.small.indexes.lines.get(index_small);
-                        let $geo_big = &$geo_big;
-                        $expr
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.points[index_small];
+                    let $geo_small =
+                        shifted($pm.small.geos.points[index_small].geo(), shift, &mut slot);
+                    let $ext_index_small = $pm.small.indexes.points.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
                     }
-                }),
-            $pm.poly_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.polys[index_small];
-                        let $ext_index_small = $pm.small.indexes.polys.get(index_small);
-                        let $geo_big = &$geo_big;
-                        $expr
+                    row
+                }
+            }),
+            candidates(&$pm.line_tree, &$env, &$pm.config).filter_map({
+                let $geo_big = $geo_big.clone();
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
                     }
-                }),
-            $pm.ls_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.line_strings[index_small];
-                        let $ext_index_small = $pm.small.indexes.line_strings.get(index_small);
-                        let $geo_big = &$geo_big;
-                        $expr
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.lines[index_small];
+                    let $geo_small =
+                        shifted($pm.small.geos.lines[index_small].geo(), shift, &mut slot);
+                    let $ext_index_small = $pm.small.indexes.lines.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
                     }
-                }),
-            $pm.rect_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    let $geo_big = $geo_big.clone();
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.rects[index_small];
-                        let $ext_index_small = $pm.small.indexes.rects.get(index_small);
-                        let $geo_big = &$geo_big;
-                        $expr
+                    row
+                }
+            }),
+            candidates(&$pm.poly_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
                     }
-                }),
-            $pm.tri_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    let $geo_big = $geo_big.clone();
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.tris[index_small];
-                        let $ext_index_small = $pm.small.indexes.tris.get(index_small);
-                        let $geo_big = &$geo_big;
-                        $expr
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.polys[index_small];
+                    let $geo_small =
+                        shifted($pm.small.geos.polys[index_small].geo(), shift, &mut slot);
+                    let $ext_index_small = $pm.small.indexes.polys.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
                     }
-                })
+                    row
+                }
+            }),
+            candidates(&$pm.ls_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
+                    }
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.line_strings[index_small];
+                    let $geo_small = shifted(
+                        $pm.small.geos.line_strings[index_small].geo(),
+                        shift,
+                        &mut slot,
+                    );
+                    let $ext_index_small = $pm.small.indexes.line_strings.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
+                    }
+                    row
+                }
+            }),
+            candidates(&$pm.rect_tree, &$env, &$pm.config).filter_map({
+                let $geo_big = $geo_big.clone();
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
+                    }
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.rects[index_small];
+                    let $geo_small =
+                        shifted($pm.small.geos.rects[index_small].geo(), shift, &mut slot);
+                    let $ext_index_small = $pm.small.indexes.rects.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
+                    }
+                    row
+                }
+            }),
+            candidates(&$pm.tri_tree, &$env, &$pm.config).filter_map({
+                let $geo_big = $geo_big.clone();
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
+                    }
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.tris[index_small];
+                    let $geo_small =
+                        shifted($pm.small.geos.tris[index_small].geo(), shift, &mut slot);
+                    let $ext_index_small = $pm.small.indexes.tris.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
+                    }
+                    row
+                }
+            }),
+            candidates(&$pm.multi_point_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
+                    }
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.multi_points[index_small];
+                    let $geo_small = shifted(
+                        $pm.small.geos.multi_points[index_small].geo(),
+                        shift,
+                        &mut slot,
+                    );
+                    let $ext_index_small = $pm.small.indexes.multi_points.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
+                    }
+                    row
+                }
+            }),
+            candidates(&$pm.multi_ls_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
+                    }
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.multi_line_strings[index_small];
+                    let $geo_small = shifted(
+                        $pm.small.geos.multi_line_strings[index_small].geo(),
+                        shift,
+                        &mut slot,
+                    );
+                    let $ext_index_small = $pm.small.indexes.multi_line_strings.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
+                    }
+                    row
+                }
+            }),
+            candidates(&$pm.multi_poly_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
+                    }
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.multi_polys[index_small];
+                    let $geo_small = shifted(
+                        $pm.small.geos.multi_polys[index_small].geo(),
+                        shift,
+                        &mut slot,
+                    );
+                    let $ext_index_small = $pm.small.indexes.multi_polys.get(index_small);
+                    let $geo_big = &$geo_big;
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
+                    }
+                    row
+                }
+            })
         )
     }};
 }
 
 macro_rules! join_inner_noncopyable {
-    ($pm:expr; $expr_type:ty;
+    ($pm:expr, $reach:expr; $expr_type:ty;
      $geo_big:ident, $ext_index_big:ident, $env:ident,
-     $geo_small:ident, $ext_index_small:ident;
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
      $expr:expr) => {{
+        let $env = grow(
+            query_envelope($env, &$pm.config),
+            $reach,
+            $pm.config.distance_metric,
+        );
         let mut result = SmallVec::<[$expr_type; 10]>::new();
+        result.extend(candidates(&$pm.point_tree, &$env, &$pm.config).filter_map({
+            let mut emitted = None;
+            move |(index_small, shift)| {
+                if emitted == Some(index_small) {
+                    return None;
+                }
+                let mut slot = None;
+                #[allow(unused_variables)]
+                let $stored_small = &$pm.small.geos.points[index_small];
+                let $geo_small =
+                    shifted($pm.small.geos.points[index_small].geo(), shift, &mut slot);
+                let $ext_index_small = $pm.small.indexes.points.get(index_small);
+                let row = $expr;
+                if row.is_some() {
+                    emitted = Some(index_small);
+                }
+                row
+            }
+        }));
+        result.extend(candidates(&$pm.line_tree, &$env, &$pm.config).filter_map({
+            let mut emitted = None;
+            move |(index_small, shift)| {
+                if emitted == Some(index_small) {
+                    return None;
+                }
+                let mut slot = None;
+                #[allow(unused_variables)]
+                let $stored_small = &$pm.small.geos.lines[index_small];
+                let $geo_small = shifted($pm.small.geos.lines[index_small].geo(), shift, &mut slot);
+                let $ext_index_small = $pm.small.indexes.lines.get(index_small);
+                let row = $expr;
+                if row.is_some() {
+                    emitted = Some(index_small);
+                }
+                row
+            }
+        }));
+        result.extend(candidates(&$pm.poly_tree, &$env, &$pm.config).filter_map({
+            let mut emitted = None;
+            move |(index_small, shift)| {
+                if emitted == Some(index_small) {
+                    return None;
+                }
+                let mut slot = None;
+                #[allow(unused_variables)]
+                let $stored_small = &$pm.small.geos.polys[index_small];
+                let $geo_small = shifted($pm.small.geos.polys[index_small].geo(), shift, &mut slot);
+                let $ext_index_small = $pm.small.indexes.polys.get(index_small);
+                let row = $expr;
+                if row.is_some() {
+                    emitted = Some(index_small);
+                }
+                row
+            }
+        }));
+        result.extend(candidates(&$pm.ls_tree, &$env, &$pm.config).filter_map({
+            let mut emitted = None;
+            move |(index_small, shift)| {
+                if emitted == Some(index_small) {
+                    return None;
+                }
+                let mut slot = None;
+                #[allow(unused_variables)]
+                let $stored_small = &$pm.small.geos.line_strings[index_small];
+                let $geo_small = shifted(
+                    $pm.small.geos.line_strings[index_small].geo(),
+                    shift,
+                    &mut slot,
+                );
+                let $ext_index_small = $pm.small.indexes.line_strings.get(index_small);
+                let row = $expr;
+                if row.is_some() {
+                    emitted = Some(index_small);
+                }
+                row
+            }
+        }));
+        result.extend(candidates(&$pm.rect_tree, &$env, &$pm.config).filter_map({
+            let mut emitted = None;
+            move |(index_small, shift)| {
+                if emitted == Some(index_small) {
+                    return None;
+                }
+                let mut slot = None;
+                #[allow(unused_variables)]
+                let $stored_small = &$pm.small.geos.rects[index_small];
+                let $geo_small = shifted($pm.small.geos.rects[index_small].geo(), shift, &mut slot);
+                let $ext_index_small = $pm.small.indexes.rects.get(index_small);
+                let row = $expr;
+                if row.is_some() {
+                    emitted = Some(index_small);
+                }
+                row
+            }
+        }));
+        result.extend(candidates(&$pm.tri_tree, &$env, &$pm.config).filter_map({
+            let mut emitted = None;
+            move |(index_small, shift)| {
+                if emitted == Some(index_small) {
+                    return None;
+                }
+                let mut slot = None;
+                #[allow(unused_variables)]
+                let $stored_small = &$pm.small.geos.tris[index_small];
+                let $geo_small = shifted($pm.small.geos.tris[index_small].geo(), shift, &mut slot);
+                let $ext_index_small = $pm.small.indexes.tris.get(index_small);
+                let row = $expr;
+                if row.is_some() {
+                    emitted = Some(index_small);
+                }
+                row
+            }
+        }));
         result.extend(
-            $pm.point_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.points[index_small];
-                        let $ext_index_small = $pm.small.indexes.points.get(index_small);
-                        $expr
+            candidates(&$pm.multi_point_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
                     }
-                }),
-        );
-        result.extend(
-            $pm.line_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.lines[index_small];
-                        let $ext_index_small = $pm.small.indexes.lines.get(index_small);
-                        $expr
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.multi_points[index_small];
+                    let $geo_small = shifted(
+                        $pm.small.geos.multi_points[index_small].geo(),
+                        shift,
+                        &mut slot,
+                    );
+                    let $ext_index_small = $pm.small.indexes.multi_points.get(index_small);
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
                     }
-                }),
+                    row
+                }
+            }),
         );
         result.extend(
-            $pm.poly_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.polys[index_small];
-                        let $ext_index_small = $pm.small.indexes.polys.get(index_small);
-                        $expr
+            candidates(&$pm.multi_ls_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
                     }
-                }),
-        );
-        result.extend(
-            $pm.ls_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.line_strings[index_small];
-                        let $ext_index_small = $pm.small.indexes.line_strings.get(index_small);
-                        $expr
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.multi_line_strings[index_small];
+                    let $geo_small = shifted(
+                        $pm.small.geos.multi_line_strings[index_small].geo(),
+                        shift,
+                        &mut slot,
+                    );
+                    let $ext_index_small = $pm.small.indexes.multi_line_strings.get(index_small);
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
                     }
-                }),
+                    row
+                }
+            }),
         );
         result.extend(
-            $pm.rect_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.rects[index_small];
-                        let $ext_index_small = $pm.small.indexes.rects.get(index_small);
-                        $expr
+            candidates(&$pm.multi_poly_tree, &$env, &$pm.config).filter_map({
+                let mut emitted = None;
+                move |(index_small, shift)| {
+                    if emitted == Some(index_small) {
+                        return None;
                     }
-                }),
-        );
-        result.extend(
-            $pm.tri_tree
-                .locate_in_envelope_intersecting(&$env)
-                .map(|fake| fake.id)
-                .filter_map({
-                    move |index_small| {
-                        let $geo_small = &$pm.small.geos.tris[index_small];
-                        let $ext_index_small = $pm.small.indexes.tris.get(index_small);
-                        $expr
+                    let mut slot = None;
+                    #[allow(unused_variables)]
+                    let $stored_small = &$pm.small.geos.multi_polys[index_small];
+                    let $geo_small = shifted(
+                        $pm.small.geos.multi_polys[index_small].geo(),
+                        shift,
+                        &mut slot,
+                    );
+                    let $ext_index_small = $pm.small.indexes.multi_polys.get(index_small);
+                    let row = $expr;
+                    if row.is_some() {
+                        emitted = Some(index_small);
                     }
-                }),
+                    row
+                }
+            }),
         );
         result
     }};
 }
 
 macro_rules! join {
+    ($pm:expr, $expr_type:ty, $big:expr; $($rest:tt)*) => {
+        join!($pm, $expr_type, $big, 0.; $($rest)*)
+    };
     ($pm:expr,
      $expr_type:ty,
-     $big:expr;
+     $big:expr,
+     $reach:expr;
 
      $geo_big:ident, $ext_index_big:ident, $env:ident,
-     $geo_small:ident, $ext_index_small:ident;
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
 
      $expr:expr) => {
         join_outer!(
@@ -317,20 +640,22 @@ macro_rules! join {
             $ext_index_big,
             $env;
             join_inner_copyable!(
-                $pm;
+                $pm, $reach;
                 $geo_big,
                 $ext_index_big,
                 $env,
                 $geo_small,
+                $stored_small,
                 $ext_index_small;
 		$expr
             ),
 	    join_inner_noncopyable!(
-                $pm; $expr_type;
+                $pm, $reach; $expr_type;
                 $geo_big,
                 $ext_index_big,
                 $env,
                 $geo_small,
+                $stored_small,
                 $ext_index_small;
 		$expr
             )
@@ -341,12 +666,16 @@ macro_rules! join {
 
 #[cfg(feature = "parallel")]
 macro_rules! par_join {
+    ($pm:expr, $expr_type:ty, $big:expr; $($rest:tt)*) => {
+        par_join!($pm, $expr_type, $big, 0.; $($rest)*)
+    };
     ($pm:expr,
      $expr_type:ty,
-     $big:expr;
+     $big:expr,
+     $reach:expr;
 
      $geo_big:ident, $ext_index_big:ident, $env:ident,
-     $geo_small:ident, $ext_index_small:ident;
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
 
      $expr:expr) => {
         par_join_outer!(
@@ -355,20 +684,22 @@ macro_rules! par_join {
             $ext_index_big,
             $env;
             join_inner_copyable!(
-                $pm;
+                $pm, $reach;
                 $geo_big,
                 $ext_index_big,
                 $env,
                 $geo_small,
+                $stored_small,
                 $ext_index_small;
 		$expr
             ),
 	    join_inner_noncopyable!(
-                $pm; $expr_type;
+                $pm, $reach; $expr_type;
                 $geo_big,
                 $ext_index_big,
                 $env,
                 $geo_small,
+                $stored_small,
                 $ext_index_small;
 		$expr
             )
@@ -377,264 +708,551 @@ macro_rules! par_join {
     }
 }
 
-pub(crate) fn sgs_try_into<T, U>(thing: T) -> Result<SplitGeoSeq, Error>
-where
-    T: TryInto<SplitGeoSeq, Error = U>,
-    U: std::any::Any,
-{
-    let thing: Result<SplitGeoSeq, _> = thing.try_into();
-    // FIXME: maybe map_error
-    match thing {
-        Ok(thing) => {
-            // conversion into SplitGeoSeq worked!
-            Ok(thing)
-        }
-        Err(e) => {
-            let any_e = &e as &dyn std::any::Any;
-            Err(any_e.downcast_ref::<Error>().expect("impossible").clone())
+// Some interactions can match pairs that don't intersect at all,
+// like Disjoint or a Relate pattern such as "FF*FF****". The R-trees
+// can't narrow those down, but they still help: a small shape that
+// isn't among the candidates for a big one is certainly disjoint
+// from it. So for each big shape we visit every small shape, using
+// `$expr_near` for the candidates and `$expr_far` for the rest.
+macro_rules! join_inner_all {
+    ($pm:expr; $expr_type:ty;
+     $geo_big:ident, $ext_index_big:ident, $env:ident,
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
+     $expr_near:expr, $expr_far:expr) => {{
+        let $env = query_envelope($env, &$pm.config);
+        let mut result = SmallVec::<[$expr_type; 10]>::new();
+        macro_rules! all_bucket {
+            ($tree:ident, $field:ident) => {{
+                let mut near: SmallVec<[(usize, i32); 10]> =
+                    candidates(&$pm.$tree, &$env, &$pm.config).collect();
+                near.sort_unstable();
+                result.extend((0..$pm.small.geos.$field.len()).filter_map(|index_small| {
+                    let $ext_index_small = $pm.small.indexes.$field.get(index_small);
+                    let start = near.partition_point(|&(id, _)| id < index_small);
+                    if near.get(start).map(|&(id, _)| id) == Some(index_small) {
+                        // Unlike the other joins, a pair that comes up
+                        // under several shifts has to match under all
+                        // of them: that's what Disjoint means.
+                        let mut row = None;
+                        for &(_, shift) in near[start..]
+                            .iter()
+                            .take_while(|&&(id, _)| id == index_small)
+                        {
+                            let mut slot = None;
+                            #[allow(unused_variables)]
+                            let $stored_small = &$pm.small.geos.$field[index_small];
+                            let $geo_small =
+                                shifted($pm.small.geos.$field[index_small].geo(), shift, &mut slot);
+                            row = $expr_near;
+                            if row.is_none() {
+                                break;
+                            }
+                        }
+                        row
+                    } else {
+                        #[allow(unused_variables)]
+                        let $stored_small = &$pm.small.geos.$field[index_small];
+                        #[allow(unused_variables)]
+                        let $geo_small = $stored_small.geo();
+                        $expr_far
+                    }
+                }));
+            }};
         }
-    }
+        all_bucket!(point_tree, points);
+        all_bucket!(line_tree, lines);
+        all_bucket!(poly_tree, polys);
+        all_bucket!(ls_tree, line_strings);
+        all_bucket!(rect_tree, rects);
+        all_bucket!(tri_tree, tris);
+        all_bucket!(multi_point_tree, multi_points);
+        all_bucket!(multi_ls_tree, multi_line_strings);
+        all_bucket!(multi_poly_tree, multi_polys);
+        result
+    }};
 }
 
-impl SpatialIndex {
-    pub fn new<T, U>(small: T, config: Config) -> Result<Self, Error>
-    where
-        T: TryInto<SplitGeoSeq, Error = U>,
-        U: std::any::Any,
-    {
-        let max_distance = config.max_distance;
-        let small = sgs_try_into(small)?;
-
-        let [point_tree, line_tree, poly_tree, ls_tree, rect_tree, tri_tree] =
-            small.to_rtrees(max_distance);
-        Ok(SpatialIndex {
-            small,
-            point_tree,
-            line_tree,
-            poly_tree,
-            ls_tree,
-            rect_tree,
-            tri_tree,
-            config,
-        })
-    }
-
-    pub fn proximity_map<'a, T: 'a, U>(
-        &'a self,
-        big: T,
-    ) -> Result<impl Iterator<Item = ProxMapRow> + 'a, Error>
-    where
-        T: TryInto<SplitGeoSeq, Error = U>,
-        U: std::any::Any + std::fmt::Debug,
-    {
-        let big = sgs_try_into(big)?;
-        Ok(join!(self, ProxMapRow, big;
-                  geo_big, ext_index_big, env,
-                  geo_small, ext_index_small;
-                  {
-              let distance = geo_big.EuclideanDistance(geo_small);
-              assert!(distance.is_finite());
+macro_rules! join_all {
+    ($join_outer:ident,
+     $pm:expr,
+     $expr_type:ty,
+     $big:expr;
 
-              if distance <= self.config.max_distance {
-                  Some(ProxMapRow {big_index: ext_index_big,
-                   small_index: ext_index_small,
-                   distance})
-              } else {
-                  None
-              }
-                  }
-        ))
-    }
+     $geo_big:ident, $ext_index_big:ident, $env:ident,
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
 
-    pub fn proximity_map_with_geos<'a, T: 'a, U>(
-        &'a self,
-        big: T,
-    ) -> Result<impl Iterator<Item = ProxMapGeoRow> + 'a, Error>
-    where
-        T: TryInto<SplitGeoSeq, Error = U>,
-        U: std::any::Any + std::fmt::Debug,
-    {
-        let big = sgs_try_into(big)?;
-        Ok(join!(self, ProxMapGeoRow, big;
-                geo_big, ext_index_big, env,
-                geo_small, ext_index_small;
-                {
-            let distance = geo_big.EuclideanDistance(geo_small);
-            assert!(distance.is_finite());
-
-            if distance <= self.config.max_distance {
-        Some(ProxMapGeoRow {big_index: ext_index_big,
-                small_index: ext_index_small,
-              big: geo_big.clone().into(),
-                small: geo_small.clone().into(), distance})
-            } else {
-                None
+     $expr_near:expr, $expr_far:expr) => {
+        $join_outer!(
+            $big;
+            $geo_big,
+            $ext_index_big,
+            $env;
+            {
+                let $geo_big = &$geo_big;
+                join_inner_all!(
+                    $pm; $expr_type;
+                    $geo_big,
+                    $ext_index_big,
+                    $env,
+                    $geo_small,
+                    $stored_small,
+                    $ext_index_small;
+                    $expr_near, $expr_far
+                )
             }
-                }
-          ))
-    }
-
-    pub fn spatial_join<'a, T, U>(
-        &'a self,
-        big: T,
-        interaction: Interaction,
-    ) -> Result<impl Iterator<Item = SJoinRow> + 'a, Error>
-    where
-        T: TryInto<SplitGeoSeq, Error = U>,
-        U: std::any::Any + std::fmt::Debug,
-    {
-        let big = sgs_try_into(big)?;
-        // This is a weird structure designed to solve an odd
-        // problem. For performance, I want to have monomorphized code
-        // for each `Interaction` branch; in other words, I don't want
-        // to do a `match interaction` in the innermost loop. But
-        // trying to put a `match interaction` as the main body of
-        // this method fails since the different arms have different
-        // types that can't be unified! Hence this approach: we always
-        // run all three arms and chain them together, but two of the
-        // three arms get empty inputs. That way we satisfy the type
-        // system since we always emit the same type.
-        let (big_intersects, big_contains, big_within) = match interaction {
-            Interaction::Intersects => (big, SplitGeoSeq::default(), SplitGeoSeq::default()),
-            Interaction::Contains => (SplitGeoSeq::default(), big, SplitGeoSeq::default()),
-            Interaction::Within => (SplitGeoSeq::default(), SplitGeoSeq::default(), big),
-        };
-        Ok(chain!(
-            // These calls are identical except for the big_ variable
-            // and the geo_big.Interaction call.
-            join!(self, SJoinRow, big_intersects;
-                    geo_big, ext_index_big, env,
-                    geo_small, ext_index_small;
-
-            if geo_small.Intersects(geo_big)  {
-                Some(SJoinRow {big_index: ext_index_big, small_index: ext_index_small })
-                    } else {
-                        None
-                    }
-              ),
-            join!(self, SJoinRow, big_contains;
-                    geo_big, ext_index_big, env,
-                    geo_small, ext_index_small;
-            if geo_small.Contains(geo_big) {
-                Some(SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
-                    } else {
-                        None
-                    }
-              ),
-            join!(self, SJoinRow, big_within;
-                    geo_big, ext_index_big, env,
-                    geo_small, ext_index_small;
-
-            if (geo_big).Contains(geo_small) {
-                Some(SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
-                    } else {
-                        None
-                    }
-              )
-        ))
-    }
+            .into_iter(),
+            join_inner_all!(
+                $pm; $expr_type;
+                $geo_big,
+                $ext_index_big,
+                $env,
+                $geo_small,
+                $stored_small,
+                $ext_index_small;
+                $expr_near, $expr_far
+            )
+        )
+    };
+}
 
-    pub fn spatial_join_with_geos<'a, T, U>(
-        &'a self,
-        big: T,
-        interaction: Interaction,
-    ) -> Result<impl Iterator<Item = SJoinGeoRow> + 'a, Error>
-    where
-        T: TryInto<SplitGeoSeq, Error = U>,
-        U: std::any::Any + std::fmt::Debug,
-    {
-        let big = sgs_try_into(big)?;
-
-        // This is a weird structure designed to solve an odd
-        // problem. For performance, I want to have monomorphized code
-        // for each `Interaction` branch; in other words, I don't want
-        // to do a `match interaction` in the innermost loop. But
-        // trying to put a `match interaction` as the main body of
-        // this method fails since the different arms have different
-        // types that can't be unified! Hence this approach: we always
-        // run all three arms and chain them together, but two of the
-        // three arms get empty inputs. That way we satisfy the type
-        // system since we always emit the same type.
-        let (big_intersects, big_contains, big_within) = match interaction {
-            Interaction::Intersects => (big, SplitGeoSeq::default(), SplitGeoSeq::default()),
-            Interaction::Contains => (SplitGeoSeq::default(), big, SplitGeoSeq::default()),
-            Interaction::Within => (SplitGeoSeq::default(), SplitGeoSeq::default(), big),
-        };
-        Ok(chain!(
-            // These calls are identical except for the big_ variable
-            // and the geo_big.Interaction call.
-            join!(self, SJoinGeoRow, big_intersects;
-                        geo_big, ext_index_big, env,
-                        geo_small, ext_index_small;
-
-                if geo_small.Intersects(geo_big)  {
-            Some(SJoinGeoRow{big_index: ext_index_big, small_index: ext_index_small,
-                     big: geo_big.clone().into(), small: geo_small.clone().into()})
-                        } else {
-                            None
-                        }
-                  ),
-            join!(self, SJoinGeoRow, big_contains;
-                        geo_big, ext_index_big, env,
-                        geo_small, ext_index_small;
-                if geo_small.Contains(geo_big) {
-            Some(SJoinGeoRow{big_index: ext_index_big, small_index: ext_index_small,
-                     big: geo_big.clone().into(), small: geo_small.clone().into()})
-                        } else {
-                            None
-                        }
-                  ),
-            join!(self, SJoinGeoRow, big_within;
-                        geo_big, ext_index_big, env,
-                        geo_small, ext_index_small;
-
-                if geo_big.Contains(geo_small) {
-            Some(SJoinGeoRow{big_index: ext_index_big, small_index: ext_index_small,
-                     big: geo_big.clone().into(), small: geo_small.clone().into()})
-                        } else {
-                            None
-                        }
-                  )
-        ))
-    }
+// The semi and anti joins only care whether a big geometry matches
+// anything, so these stop at its first row and hand that back (or
+// `None`) next to its index. The inner joins are lazy, so nothing
+// past the first match gets looked at, except in the `join_all!`
+// arm, which has to check every small geometry anyway.
+macro_rules! first_outer {
+    ($big:expr;
+     $geo_big:ident, $ext_index_big:ident, $env:ident;
+     $expr_copyable:expr, $expr_noncopyable:expr) => {
+        join_outer!(
+            $big;
+            $geo_big,
+            $ext_index_big,
+            $env;
+            std::iter::once(($ext_index_big, $expr_copyable.into_iter().next())),
+            std::iter::once(($ext_index_big, $expr_noncopyable.into_iter().next()))
+        )
+    };
 }
 
 #[cfg(feature = "parallel")]
-pub(crate) fn par_sgs_try_into<T, U>(thing: T) -> Result<SplitGeoSeq, Error>
-where
-    T: TryInto<Par<SplitGeoSeq>, Error = U>,
-    U: std::any::Any,
-{
-    let thing: Result<Par<SplitGeoSeq>, _> = thing.try_into();
-    // FIXME: maybe map_error
-    match thing {
-        Ok(thing) => {
-            // conversion into SplitGeoSeq worked!
-            Ok(thing.0)
-        }
-        Err(e) => {
+macro_rules! par_first_outer {
+    ($big:expr;
+     $geo_big:ident, $ext_index_big:ident, $env:ident;
+     $expr_copyable:expr, $expr_noncopyable:expr) => {
+        par_join_outer!(
+            $big;
+            $geo_big,
+            $ext_index_big,
+            $env;
+            std::iter::once(($ext_index_big, $expr_copyable.into_iter().next())),
+            std::iter::once(($ext_index_big, $expr_noncopyable.into_iter().next()))
+        )
+    };
+}
+
+// `join_inner_copyable!` is lazy, which is all we need here, and
+// borrowing a heap geometry once more keeps it from cloning the
+// geometry itself.
+macro_rules! join_first {
+    ($outer:ident, $pm:expr, $expr_type:ty, $big:expr; $($rest:tt)*) => {
+        join_first!($outer, $pm, $expr_type, $big, 0.; $($rest)*)
+    };
+    ($outer:ident,
+     $pm:expr,
+     $expr_type:ty,
+     $big:expr,
+     $reach:expr;
+
+     $geo_big:ident, $ext_index_big:ident, $env:ident,
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
+
+     $expr:expr) => {
+        $outer!(
+            $big;
+            $geo_big,
+            $ext_index_big,
+            $env;
+            join_inner_copyable!(
+                $pm, $reach;
+                $geo_big,
+                $ext_index_big,
+                $env,
+                $geo_small,
+                $stored_small,
+                $ext_index_small;
+                $expr
+            ),
+            {
+                let $geo_big = &$geo_big;
+                join_inner_copyable!(
+                    $pm, $reach;
+                    $geo_big,
+                    $ext_index_big,
+                    $env,
+                    $geo_small,
+                    $stored_small,
+                    $ext_index_small;
+                    $expr
+                )
+            }
+        )
+    };
+}
+
+macro_rules! serial_join_first {
+    ($($args:tt)*) => { join_first!(first_outer, $($args)*) };
+}
+
+#[cfg(feature = "parallel")]
+macro_rules! par_join_first {
+    ($($args:tt)*) => { join_first!(par_first_outer, $($args)*) };
+}
+
+// The left joins need every big geometry to show up, so these pair
+// each of its rows with its index and fill in a lone `None` when it
+// has none.
+macro_rules! left_outer {
+    ($big:expr;
+     $geo_big:ident, $ext_index_big:ident, $env:ident;
+     $expr_copyable:expr, $expr_noncopyable:expr) => {
+        join_outer!(
+            $big;
+            $geo_big,
+            $ext_index_big,
+            $env;
+            or_none($expr_copyable.into_iter()).map(move |row| ($ext_index_big, row)),
+            or_none($expr_noncopyable.into_iter()).map(move |row| ($ext_index_big, row))
+        )
+    };
+}
+
+#[cfg(feature = "parallel")]
+macro_rules! par_left_outer {
+    ($big:expr;
+     $geo_big:ident, $ext_index_big:ident, $env:ident;
+     $expr_copyable:expr, $expr_noncopyable:expr) => {
+        par_join_outer!(
+            $big;
+            $geo_big,
+            $ext_index_big,
+            $env;
+            or_none($expr_copyable.into_iter()).map(move |row| ($ext_index_big, row)),
+            or_none($expr_noncopyable.into_iter()).map(move |row| ($ext_index_big, row))
+        )
+    };
+}
+
+// `join!` with the outer loop left to the caller.
+macro_rules! join_via {
+    ($outer:ident, $pm:expr, $expr_type:ty, $big:expr; $($rest:tt)*) => {
+        join_via!($outer, $pm, $expr_type, $big, 0.; $($rest)*)
+    };
+    ($outer:ident,
+     $pm:expr,
+     $expr_type:ty,
+     $big:expr,
+     $reach:expr;
+
+     $geo_big:ident, $ext_index_big:ident, $env:ident,
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
+
+     $expr:expr) => {
+        $outer!(
+            $big;
+            $geo_big,
+            $ext_index_big,
+            $env;
+            join_inner_copyable!(
+                $pm, $reach;
+                $geo_big,
+                $ext_index_big,
+                $env,
+                $geo_small,
+                $stored_small,
+                $ext_index_small;
+                $expr
+            ),
+            join_inner_noncopyable!(
+                $pm, $reach; $expr_type;
+                $geo_big,
+                $ext_index_big,
+                $env,
+                $geo_small,
+                $stored_small,
+                $ext_index_small;
+                $expr
+            )
+        )
+    };
+}
+
+macro_rules! serial_join_left {
+    ($($args:tt)*) => { join_via!(left_outer, $($args)*) };
+}
+
+#[cfg(feature = "parallel")]
+macro_rules! par_join_left {
+    ($($args:tt)*) => { join_via!(par_left_outer, $($args)*) };
+}
+
+// `join_outer!` for a `Probe`, which holds at most one geometry.
+macro_rules! probe_outer {
+    ($probe:expr;
+     $geo_big:ident, $ext_index_big:ident, $env:ident;
+     $expr_copyable:expr, $expr_noncopyable:expr) => {{
+        let probe = $probe;
+        let (reach, metric) = (probe.reach, probe.metric);
+        macro_rules! copyable {
+            ($field:ident) => {
+                probe.$field.into_iter().flat_map(move |$geo_big| {
+                    #[allow(unused_variables)]
+                    let $ext_index_big = 0;
+                    let $env = grow($geo_big.to_env(), reach, metric);
+                    $expr_copyable
+                })
+            };
+        }
+        macro_rules! noncopyable {
+            ($field:ident) => {
+                probe.$field.into_iter().flat_map(move |$geo_big| {
+                    #[allow(unused_variables)]
+                    let $ext_index_big = 0;
+                    let $env = grow($geo_big.to_env(), reach, metric);
+                    let $geo_big = &$geo_big;
+                    $expr_noncopyable.into_iter()
+                })
+            };
+        }
+        chain!(
+            copyable!(points),
+            copyable!(lines),
+            copyable!(rects),
+            copyable!(tris),
+            noncopyable!(polys),
+            noncopyable!(line_strings),
+            noncopyable!(multi_points),
+            noncopyable!(multi_line_strings),
+            noncopyable!(multi_polys)
+        )
+    }};
+}
+
+macro_rules! probe_join {
+    ($($args:tt)*) => { join_via!(probe_outer, $($args)*) };
+}
+
+fn or_none<I: Iterator>(rows: I) -> impl Iterator<Item = Option<I::Item>> {
+    let mut rows = rows.peekable();
+    let none = match rows.peek() {
+        Some(_) => None,
+        None => Some(None),
+    };
+    rows.map(Some).chain(none)
+}
+
+// This is a weird structure designed to solve an odd problem. For
+// performance, I want to have monomorphized code for each
+// `Interaction` branch; in other words, I don't want to do a `match
+// interaction` in the innermost loop. But trying to put a `match
+// interaction` as the main body of a method fails since the
+// different arms have different types that can't be unified! Hence
+// this approach: we always run every arm and chain them together,
+// but all arms except one get empty inputs. That way we satisfy the
+// type system since we always emit the same type.
+//
+// `$row` builds the output row for a matching pair; the arms only
+// differ in the predicate they check before building it. `$keep`, if
+// given, is checked first and can rule a pair out by its indexes
+// alone, before we spend anything on the predicate.
+macro_rules! interaction_join {
+    ($join:ident, $join_outer:ident,
+     $pm:expr,
+     $expr_type:ty,
+     $big:expr,
+     $interaction:expr;
+
+     $geo_big:ident, $ext_index_big:ident,
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
+
+     keep_if $keep:expr; $row:expr) => {{
+        let interaction = $interaction;
+        let mut big_intersects = Default::default();
+        let mut big_contains = Default::default();
+        let mut big_within = Default::default();
+        let mut big_matrix = Default::default();
+        let mut big_all = Default::default();
+        match interaction {
+            Interaction::Intersects => big_intersects = $big,
+            Interaction::Contains => big_contains = $big,
+            Interaction::Within => big_within = $big,
+            Interaction::Touches
+            | Interaction::Crosses
+            | Interaction::Overlaps
+            | Interaction::Covers
+            | Interaction::CoveredBy => big_matrix = $big,
+            Interaction::Relate(pattern) if pattern.needs_intersection() => big_matrix = $big,
+            Interaction::Disjoint | Interaction::Relate(_) => big_all = $big,
+            Interaction::WithinDistance(_) if interaction.validate().is_some() => (),
+            Interaction::WithinDistance(_) => big_matrix = $big,
+        }
+        // The R-trees only turn up pairs within the distance the small
+        // envelopes were buffered by, so to look further we grow the
+        // big envelopes by the difference, like `within_distance` does.
+        let reach = match interaction {
+            Interaction::WithinDistance(distance) => distance - $pm.buffered(),
+            _ => 0.,
+        };
+        chain!(
+            // These calls are identical except for the big_ variable
+            // and the geo_big.Interaction call.
+            $join!($pm, $expr_type, big_intersects;
+                   $geo_big, $ext_index_big, env,
+                   $geo_small, $stored_small, $ext_index_small;
+                   if $keep && $geo_small.Intersects($geo_big.geo()) { Some($row) } else { None }),
+            $join!($pm, $expr_type, big_contains;
+                   $geo_big, $ext_index_big, env,
+                   $geo_small, $stored_small, $ext_index_small;
+                   if $keep && $geo_small.Contains($geo_big.geo()) { Some($row) } else { None }),
+            $join!($pm, $expr_type, big_within;
+                   $geo_big, $ext_index_big, env,
+                   $geo_small, $stored_small, $ext_index_small;
+                   if $keep && $geo_big.geo().Contains($geo_small) { Some($row) } else { None }),
+            // The predicates that go through the intersection matrix
+            // share an arm, and so does WithinDistance: computing the
+            // matrix or the distance dwarfs the cost of this match,
+            // and every extra arm makes the compiler churn through
+            // another copy of the whole join.
+            $join!($pm, $expr_type, big_matrix, reach;
+                   $geo_big, $ext_index_big, env,
+                   $geo_small, $stored_small, $ext_index_small;
+                   if $keep && match interaction {
+                       Interaction::Touches => $geo_small.Touches($geo_big.geo()),
+                       Interaction::Crosses => $geo_small.Crosses($geo_big.geo()),
+                       Interaction::Overlaps => $geo_small.Overlaps($geo_big.geo()),
+                       Interaction::Covers => $geo_small.Covers($geo_big.geo()),
+                       Interaction::CoveredBy => $geo_small.CoveredBy($geo_big.geo()),
+                       Interaction::Relate(pattern) => $geo_small.Relate($geo_big.geo(), &pattern),
+                       Interaction::WithinDistance(distance) => $pm.dwithin($geo_big.geo(), $geo_small, distance),
+                       _ => unreachable!(),
+                   } { Some($row) } else { None }),
+            join_all!($join_outer, $pm, $expr_type, big_all;
+                   $geo_big, $ext_index_big, env,
+                   $geo_small, $stored_small, $ext_index_small;
+                   if $keep && match interaction {
+                       Interaction::Disjoint => $geo_small.Disjoint($geo_big.geo()),
+                       Interaction::Relate(pattern) => $geo_small.Relate($geo_big.geo(), &pattern),
+                       _ => unreachable!(),
+                   } { Some($row) } else { None },
+                   if $keep && match interaction {
+                       Interaction::Disjoint => true,
+                       Interaction::Relate(pattern) => $geo_small.relate_disjoint($geo_big.geo(), &pattern),
+                       _ => unreachable!(),
+                   } { Some($row) } else { None })
+        )
+    }};
+    ($join:ident, $join_outer:ident,
+     $pm:expr,
+     $expr_type:ty,
+     $big:expr,
+     $interaction:expr;
+
+     $geo_big:ident, $ext_index_big:ident,
+     $geo_small:ident, $stored_small:ident, $ext_index_small:ident;
+
+     $row:expr) => {
+        interaction_join!($join, $join_outer, $pm, $expr_type, $big, $interaction;
+                          $geo_big, $ext_index_big, $geo_small, $stored_small, $ext_index_small;
+                          keep_if true; $row)
+    };
+}
+
+fn overlap_row<C: CoordFloat, B: Topology, S: Topology>(
+    big_index: usize,
+    small_index: usize,
+    big: &B,
+    small: &S,
+) -> SJoinOverlapRow<C> {
+    let shared = overlay(&small.parts(), &big.parts());
+    let narrow = |c: Coordinate<f64>| Coordinate {
+        x: C::from_f64(c.x),
+        y: C::from_f64(c.y),
+    };
+    SJoinOverlapRow {
+        big_index,
+        small_index,
+        area: shared.area,
+        lines: shared
+            .lines
+            .into_iter()
+            .map(|line| LineString(line.into_iter().map(narrow).collect()))
+            .collect(),
+        length: shared.length,
+    }
+}
+
+pub(crate) fn sgs_try_into<'a, C: CoordFloat, T, U>(thing: T) -> Result<SplitGeoSeq<'a, C>, Error>
+where
+    T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+    U: std::any::Any,
+{
+    let thing: Result<SplitGeoSeq<C>, _> = thing.try_into();
+    // FIXME: maybe map_error
+    match thing {
+        Ok(thing) => {
+            // conversion into SplitGeoSeq worked!
+            Ok(thing)
+        }
+        Err(e) => {
             let any_e = &e as &dyn std::any::Any;
             Err(any_e.downcast_ref::<Error>().expect("impossible").clone())
         }
     }
 }
 
-// We have to handle parallel with separate methods because parallel
-// and serial iterator have different types.
+// Calls `distances` once for every index up to `end`, gaps left by
+// `lenient_from` included.
+fn own_distances<F: Fn(usize) -> f64>(end: usize, distances: F) -> Result<Vec<f64>, Error> {
+    (0..end)
+        .map(|index| match distances(index) {
+            distance if distance.is_finite() && distance >= 0. => Ok(distance),
+            distance => Err(Error::BadMaxDistance(distance)),
+        })
+        .collect()
+}
 
-#[cfg(feature = "parallel")]
-impl ParSpatialIndex {
+impl<'s, C: CoordFloat> SpatialIndex<'s, C> {
     pub fn new<T, U>(small: T, config: Config) -> Result<Self, Error>
     where
-        T: TryInto<Par<SplitGeoSeq>, Error = U>,
+        T: TryInto<SplitGeoSeq<'s, C>, Error = U>,
+        U: std::any::Any,
+    {
+        let small = sgs_try_into(small)?.prepare(&config);
+        Ok(SpatialIndex::build(small, config, Vec::new()))
+    }
+
+    pub(crate) fn with_distances<T, U, F>(
+        small: T,
+        config: Config,
+        distances: F,
+    ) -> Result<Self, Error>
+    where
+        T: TryInto<SplitGeoSeq<'s, C>, Error = U>,
         U: std::any::Any,
+        F: Fn(usize) -> f64,
     {
-        let max_distance = config.max_distance;
-        let small = par_sgs_try_into(small)?;
+        let small = sgs_try_into(small)?.prepare(&config);
+        let distances = own_distances(small.indexes.end(), distances)?;
+        Ok(SpatialIndex::build(small, config, distances))
+    }
 
-        let [point_tree, line_tree, poly_tree, ls_tree, rect_tree, tri_tree] =
-            small.to_rtrees(max_distance);
-        Ok(ParSpatialIndex(SpatialIndex {
+    // `distances` holds each small geometry's own `max_distance` by
+    // index, or is empty when they all use `config.max_distance`.
+    pub(crate) fn build(small: SplitGeoSeq<'s, C>, config: Config, distances: Vec<f64>) -> Self {
+        let [point_tree, line_tree, poly_tree, ls_tree, rect_tree, tri_tree, multi_point_tree, multi_ls_tree, multi_poly_tree] =
+            small.to_buffered_rtrees(&config, &distances);
+        let next_index = small.indexes.end();
+        SpatialIndex {
             small,
             point_tree,
             line_tree,
@@ -642,188 +1260,1272 @@ impl ParSpatialIndex {
             ls_tree,
             rect_tree,
             tri_tree,
+            multi_point_tree,
+            multi_ls_tree,
+            multi_poly_tree,
+            distances,
+            next_index,
             config,
-        }))
+        }
     }
 
-    pub fn proximity_map<'a, T: 'a, U>(
+    pub fn proximity_map<'a, T, U>(
         &'a self,
         big: T,
-    ) -> Result<impl ParallelIterator<Item = ProxMapRow> + 'a, Error>
+    ) -> Result<impl Iterator<Item = ProxMapRow> + 'a, Error>
     where
-        T: TryInto<Par<SplitGeoSeq>, Error = U>,
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U> + 'a,
         U: std::any::Any + std::fmt::Debug,
     {
-        let big = par_sgs_try_into(big)?;
-
-        Ok(par_join!(self.0, ProxMapRow, big;
-                  geo_big, ext_index_big, env,
-                  geo_small, ext_index_small;
-                  {
-              let distance = geo_big.EuclideanDistance(geo_small);
-              assert!(distance.is_finite());
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(self.proximity_map_impl(big))
+    }
 
-              if distance <= self.0.config.max_distance {
-                  Some(ProxMapRow {big_index: ext_index_big,
-                   small_index: ext_index_small,
-                   distance})
+    /// Finds the pairs of small geometries within `max_distance` of
+    /// each other. Each pair comes out once, with `small_index` less
+    /// than `big_index`, and no geometry is paired with itself. With
+    /// per-geometry distances, a pair is held to the distance of its
+    /// `small_index` geometry.
+    pub fn self_proximity_map(&self) -> impl Iterator<Item = ProxMapRow> + '_ {
+        join!(self, ProxMapRow, self.small.borrowed();
+              geo_big, ext_index_big, env,
+              geo_small, stored_small, ext_index_small;
+              if ext_index_small < ext_index_big {
+                  self.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapRow {
+                      big_index: ext_index_big,
+                      small_index: ext_index_small,
+                      distance,
+                  })
               } else {
                   None
               }
+        )
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but pulls `big`
+    /// from an iterator one geometry at a time instead of converting
+    /// it all up front, so memory use doesn't grow with `big`. A
+    /// geometry that fails validation shows up as an `Err` in its
+    /// place and the join carries on with the next one.
+    pub fn proximity_map_stream<'a, G, I>(
+        &'a self,
+        big: I,
+    ) -> impl Iterator<Item = Result<ProxMapRow, Error>> + 'a
+    where
+        I: IntoIterator<Item = G>,
+        I::IntoIter: 'a,
+        G: Into<Geometry<C>>,
+    {
+        big.into_iter().enumerate().flat_map(move |(i, geo)| {
+            let (one, error) = single(i, geo.into(), &self.config);
+            self.proximity_map_impl(one).map(Ok).chain(error.map(Err))
+        })
+    }
+
+    pub(crate) fn proximity_map_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+    ) -> impl Iterator<Item = ProxMapRow> + 'a {
+        join!(self, ProxMapRow, big;
+                  geo_big, ext_index_big, env,
+                  geo_small, stored_small, ext_index_small;
+                  {
+              self.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapRow {
+                  big_index: ext_index_big,
+                  small_index: ext_index_small,
+                  distance,
+              })
                   }
+        )
+    }
+
+    // How far apart `big` and `small` are, if that's within the band
+    // from `min_distance` to the small geometry's `max_distance`. Every
+    // point of a geometry is inside its envelope, so when even the far
+    // corners of the two envelopes are closer than `min_distance`, the
+    // pair is too close and we needn't measure it.
+    fn band_distance<B, S>(&self, big: &B, small: &S, ext_index_small: usize) -> Option<f64>
+    where
+        B: Relates<S> + Envelope<C>,
+        S: Topology + Envelope<C>,
+    {
+        let config = &self.config;
+        if config.min_distance > 0.
+            && config.distance_metric == DistanceMetric::Euclidean
+            && farthest(&big.to_env(), &small.to_env()) < config.min_distance
+        {
+            return None;
+        }
+        let distance = match config.distance_metric {
+            DistanceMetric::Euclidean => big.EuclideanDistance(small),
+            DistanceMetric::Geodesic => big.GeodesicDistance(small),
+        };
+        assert!(distance.is_finite());
+
+        let max_distance = own_distance(config, &self.distances, ext_index_small);
+        if config.min_distance <= distance && distance <= max_distance {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+
+    // Whether `small` is within `distance` of `big`, measured from
+    // `big` the way proximity maps measure. Shapes that meet are
+    // within any distance, so we skip measuring them.
+    pub(crate) fn dwithin<B, S>(&self, big: &B, small: &S, distance: f64) -> bool
+    where
+        B: Relates<S>,
+        S: Topology,
+    {
+        match self.config.distance_metric {
+            DistanceMetric::Euclidean => {
+                big.Intersects(small) || big.EuclideanDistance(small) <= distance
+            }
+            // GeodesicDistance checks Intersects itself.
+            DistanceMetric::Geodesic => big.GeodesicDistance(small) <= distance,
+        }
+    }
+
+    // How far every small envelope in the R-trees is buffered. With
+    // distances of their own, they are only sure to be buffered by
+    // zero.
+    pub(crate) fn buffered(&self) -> f64 {
+        if self.distances.is_empty() {
+            self.config.max_distance
+        } else {
+            0.
+        }
+    }
+
+    pub fn proximity_map_with_geos<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl Iterator<Item = ProxMapGeoRow<'a, C>> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U> + 'a,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(join!(self, ProxMapGeoRow<C>, big;
+              geo_big, ext_index_big, env,
+              geo_small, stored_small, ext_index_small;
+              {
+          self.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapGeoRow {
+              big_index: ext_index_big,
+              small_index: ext_index_small,
+              big: geo_big.to_row(),
+              small: stored_small.row_ref(),
+              distance,
+          })
+              }
         ))
     }
 
-    pub fn proximity_map_with_geos<'a, T: 'a, U>(
+    /// Finds the `k` elements of `small` nearest to each element of
+    /// `big`, however far away they are.
+    ///
+    /// Rows for each element of `big` come out nearest first; ties
+    /// are broken by `small_index`.
+    pub fn nearest_neighbors<'a, T, U>(
         &'a self,
         big: T,
-    ) -> Result<impl ParallelIterator<Item = ProxMapGeoRow> + 'a, Error>
+        k: usize,
+    ) -> Result<impl Iterator<Item = ProxMapRow> + 'a, Error>
     where
-        T: TryInto<Par<SplitGeoSeq>, Error = U>,
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
         U: std::any::Any + std::fmt::Debug,
     {
-        let big = par_sgs_try_into(big)?;
-        Ok(par_join!(self.0, ProxMapGeoRow, big;
-                geo_big, ext_index_big, env,
-                geo_small, ext_index_small;
-                {
-            let distance = geo_big.EuclideanDistance(geo_small);
-            assert!(distance.is_finite());
-
-            if distance <= self.0.config.max_distance {
-        Some(ProxMapGeoRow {big_index: ext_index_big,
-                small_index: ext_index_small,
-              big: geo_big.clone().into(),
-                small: geo_small.clone().into(), distance})
-            } else {
-                None
-            }
-                }
-          ))
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(self.nearest_neighbors_impl(big, k, f64::INFINITY))
+    }
+
+    /// Like [`nearest_neighbors`](#method.nearest_neighbors), but
+    /// ignores elements of `small` further than `max_distance` away,
+    /// so some elements of `big` may get fewer than `k` rows.
+    pub fn nearest_neighbors_within<'a, T, U>(
+        &'a self,
+        big: T,
+        k: usize,
+        max_distance: f64,
+    ) -> Result<impl Iterator<Item = ProxMapRow> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = Config::new().max_distance(max_distance).validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(self.nearest_neighbors_impl(big, k, max_distance))
+    }
+
+    fn nearest_neighbors_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+        k: usize,
+        max_distance: f64,
+    ) -> impl Iterator<Item = ProxMapRow> + 'a {
+        join_outer!(big; geo_big, ext_index_big, env;
+                    self.nearest(&geo_big, env, ext_index_big, k, max_distance).into_iter(),
+                    self.nearest(geo_big.geo(), env, ext_index_big, k, max_distance))
     }
 
     pub fn spatial_join<'a, T, U>(
         &'a self,
         big: T,
         interaction: Interaction,
-    ) -> Result<impl ParallelIterator<Item = SJoinRow> + 'a, Error>
+    ) -> Result<impl Iterator<Item = SJoinRow> + 'a, Error>
     where
-        T: TryInto<Par<SplitGeoSeq>, Error = U>,
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
         U: std::any::Any + std::fmt::Debug,
     {
-        let big = par_sgs_try_into(big)?;
-        // This is a weird structure designed to solve an odd
-        // problem. For performance, I want to have monomorphized code
-        // for each `Interaction` branch; in other words, I don't want
-        // to do a `match interaction` in the innermost loop. But
-        // trying to put a `match interaction` as the main body of
-        // this method fails since the different arms have different
-        // types that can't be unified! Hence this approach: we always
-        // run all three arms and chain them together, but two of the
-        // three arms get empty inputs. That way we satisfy the type
-        // system since we always emit the same type.
-        let (big_intersects, big_contains, big_within) = match interaction {
-            Interaction::Intersects => (big, SplitGeoSeq::default(), SplitGeoSeq::default()),
-            Interaction::Contains => (SplitGeoSeq::default(), big, SplitGeoSeq::default()),
-            Interaction::Within => (SplitGeoSeq::default(), SplitGeoSeq::default(), big),
-        };
-        Ok(chain!(
-            // These calls are identical except for the big_ variable
-            // and the geo_big.Interaction call.
-            par_join!(self.0, SJoinRow, big_intersects;
-                    geo_big, ext_index_big, env,
-                    geo_small, ext_index_small;
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(self.spatial_join_impl(big, interaction))
+    }
 
-            if geo_small.Intersects(geo_big)  {
-                Some(SJoinRow {big_index: ext_index_big, small_index: ext_index_small })
-                    } else {
-                        None
-                    }
-              ),
-            par_join!(self.0, SJoinRow, big_contains;
-                    geo_big, ext_index_big, env,
-                    geo_small, ext_index_small;
-            if geo_small.Contains(geo_big) {
-                Some(SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
-                    } else {
-                        None
-                    }
-              ),
-            par_join!(self.0, SJoinRow, big_within;
-                    geo_big, ext_index_big, env,
-                    geo_small, ext_index_small;
+    /// Joins the small geometries against each other, without
+    /// converting or indexing anything again.
+    ///
+    /// For the interactions where order doesn't matter (`Intersects`,
+    /// `Touches`, `Crosses`, `Overlaps`, `Disjoint` and `Relate` with
+    /// a symmetric pattern) each pair comes out once, with
+    /// `small_index` less than `big_index`. For the others, like
+    /// `Within`, a row says which way round the pair matched, so you
+    /// get every matching pair either way round. Either way no
+    /// geometry is paired with itself.
+    pub fn self_join(&self, interaction: Interaction) -> impl Iterator<Item = SJoinRow> + '_ {
+        let symmetric = interaction.is_symmetric();
+        interaction_join!(join, join_outer, self, SJoinRow, self.small.borrowed(), interaction;
+                          geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                          keep_if self_join_keeps(ext_index_small, ext_index_big, symmetric);
+                          SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
+    }
 
-            if (geo_big).Contains(geo_small) {
-                Some(SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
-                    } else {
-                        None
-                    }
-              )
-        ))
+    /// Like [`spatial_join`](#method.spatial_join), but pulls `big`
+    /// from an iterator one geometry at a time; see
+    /// [`proximity_map_stream`](#method.proximity_map_stream).
+    pub fn spatial_join_stream<'a, G, I>(
+        &'a self,
+        big: I,
+        interaction: Interaction,
+    ) -> impl Iterator<Item = Result<SJoinRow, Error>> + 'a
+    where
+        I: IntoIterator<Item = G>,
+        I::IntoIter: 'a,
+        G: Into<Geometry<C>>,
+    {
+        // A bad interaction comes out first, and then joins nothing.
+        interaction
+            .validate()
+            .map(Err)
+            .into_iter()
+            .chain(big.into_iter().enumerate().flat_map(move |(i, geo)| {
+                let (one, error) = single(i, geo.into(), &self.config);
+                self.spatial_join_impl(one, interaction)
+                    .map(Ok)
+                    .chain(error.map(Err))
+            }))
+    }
+
+    pub(crate) fn spatial_join_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+        interaction: Interaction,
+    ) -> impl Iterator<Item = SJoinRow> + 'a {
+        interaction_join!(join, join_outer, self, SJoinRow, big, interaction;
+                          geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                          SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
     }
 
     pub fn spatial_join_with_geos<'a, T, U>(
         &'a self,
         big: T,
         interaction: Interaction,
-    ) -> Result<impl ParallelIterator<Item = SJoinGeoRow> + 'a, Error>
+    ) -> Result<impl Iterator<Item = SJoinGeoRow<'a, C>> + 'a, Error>
     where
-        T: TryInto<Par<SplitGeoSeq>, Error = U>,
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
         U: std::any::Any + std::fmt::Debug,
     {
-        let big = par_sgs_try_into(big)?;
-
-        // This is a weird structure designed to solve an odd
-        // problem. For performance, I want to have monomorphized code
-        // for each `Interaction` branch; in other words, I don't want
-        // to do a `match interaction` in the innermost loop. But
-        // trying to put a `match interaction` as the main body of
-        // this method fails since the different arms have different
-        // types that can't be unified! Hence this approach: we always
-        // run all three arms and chain them together, but two of the
-        // three arms get empty inputs. That way we satisfy the type
-        // system since we always emit the same type.
-        let (big_intersects, big_contains, big_within) = match interaction {
-            Interaction::Intersects => (big, SplitGeoSeq::default(), SplitGeoSeq::default()),
-            Interaction::Contains => (SplitGeoSeq::default(), big, SplitGeoSeq::default()),
-            Interaction::Within => (SplitGeoSeq::default(), SplitGeoSeq::default(), big),
-        };
-        Ok(chain!(
-            // These calls are identical except for the big_ variable
-            // and the geo_big.Interaction call.
-            par_join!(self.0, SJoinGeoRow, big_intersects;
-                        geo_big, ext_index_big, env,
-                        geo_small, ext_index_small;
-
-                if geo_small.Intersects(geo_big)  {
-            Some(SJoinGeoRow{big_index: ext_index_big, small_index: ext_index_small,
-                     big: geo_big.clone().into(), small: geo_small.clone().into()})
-                        } else {
-                            None
-                        }
-                  ),
-            par_join!(self.0, SJoinGeoRow, big_contains;
-                        geo_big, ext_index_big, env,
-                        geo_small, ext_index_small;
-                if geo_small.Contains(geo_big) {
-            Some(SJoinGeoRow{big_index: ext_index_big, small_index: ext_index_small,
-                     big: geo_big.clone().into(), small: geo_small.clone().into()})
-                        } else {
-                            None
-                        }
-                  ),
-            par_join!(self.0, SJoinGeoRow, big_within;
-                        geo_big, ext_index_big, env,
-                        geo_small, ext_index_small;
-
-                if geo_big.Contains(geo_small) {
-            Some(SJoinGeoRow{big_index: ext_index_big, small_index: ext_index_small,
-                     big: geo_big.clone().into(), small: geo_small.clone().into()})
-                        } else {
-                            None
-                        }
-                  )
-        ))
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+
+        Ok(
+            interaction_join!(join, join_outer, self, SJoinGeoRow<C>, big, interaction;
+                             geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                             SJoinGeoRow {big_index: ext_index_big, small_index: ext_index_small,
+                                          big: geo_big.to_row(), small: stored_small.row_ref()}),
+        )
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join) with
+    /// `Interaction::Intersects`, but each row also says what the pair
+    /// shares: the area of their overlap, or the lines one clips out
+    /// of the other. See [`SJoinOverlapRow`](struct.SJoinOverlapRow.html).
+    /// With `Config::geographic`, the lines are placed next to the big
+    /// geometry.
+    pub fn spatial_join_with_overlap<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl Iterator<Item = SJoinOverlapRow<C>> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(join!(self, SJoinOverlapRow<C>, big;
+        geo_big, ext_index_big, env,
+        geo_small, stored_small, ext_index_small;
+        if geo_small.Intersects(geo_big.geo()) {
+            Some(overlap_row(ext_index_big, ext_index_small, geo_big.geo(), geo_small))
+        } else {
+            None
+        }))
+    }
+
+    /// Finds the small geometries that interact with `geo`, without
+    /// converting it into a sequence first, for looking up one
+    /// geometry at a time. As in the joins, `geo` plays the part of
+    /// `big`, so "which small polygons contain this point" is
+    /// `query(&point, Interaction::Contains)`.
+    ///
+    /// Finding the candidates doesn't allocate, except with
+    /// `Config::geographic`, for a heap-bound `geo` that matches more
+    /// than ten small geometries, or for the interactions like
+    /// `Disjoint` that have to visit every small geometry. Checking a
+    /// candidate doesn't allocate for `Intersects`, but does whenever
+    /// it builds an intersection matrix: for `Touches`, `Crosses`,
+    /// `Overlaps`, `Covers`, `CoveredBy` and `Relate`, and for
+    /// `Contains` and `Within` when a multi-geometry is involved or
+    /// one shape touches a polygon's boundary from inside.
+    /// `WithinDistance` allocates to measure the distance between
+    /// polygons, and for any pair with `DistanceMetric::Geodesic`.
+    pub fn query<'a>(
+        &'a self,
+        geo: &'a Geometry<C>,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = usize> + 'a, Error> {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let probe = Probe::new(geo, 0., &self.config)?;
+        Ok(
+            interaction_join!(probe_join, probe_outer, self, usize, probe, interaction;
+                              geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                              ext_index_small),
+        )
+    }
+
+    /// Finds the small geometries within `distance` of `geo`, along
+    /// with how far away each one is, measured the way
+    /// [`proximity_map`](#method.proximity_map) measures. `distance`
+    /// needn't match `Config::max_distance`, though looking further
+    /// than it means searching more of the R-trees, but like it has to
+    /// be finite and at least zero. Allocates as much as
+    /// [`query`](#method.query) does with `Interaction::WithinDistance`.
+    pub fn within_distance<'a>(
+        &'a self,
+        geo: &'a Geometry<C>,
+        distance: f64,
+    ) -> Result<impl Iterator<Item = (usize, f64)> + 'a, Error> {
+        if let Some(e) = Config::new().max_distance(distance).validate() {
+            return Err(e);
+        }
+        let probe = Probe::new(geo, distance - self.buffered(), &self.config)?;
+        Ok(probe_join!(self, (usize, f64), probe;
+                       geo_big, ext_index_big, env,
+                       geo_small, stored_small, ext_index_small;
+                       {
+            let found = match self.config.distance_metric {
+                DistanceMetric::Euclidean => geo_big.geo().EuclideanDistance(geo_small),
+                DistanceMetric::Geodesic => geo_big.geo().GeodesicDistance(geo_small),
+            };
+            assert!(found.is_finite());
+
+            if found <= distance {
+                Some((ext_index_small, found))
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join), but rows come out
+    /// ordered by `big_index` and then `small_index`. `big` is split
+    /// and joined one geometry at a time, and the rows for each big
+    /// geometry are collected and sorted before they come out.
+    pub fn spatial_join_sorted<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = SJoinRow> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(Singles::new(big).flat_map(move |one| sorted(self.spatial_join_impl(one, interaction))))
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but rows come
+    /// out ordered by `big_index` and then `small_index`; see
+    /// [`spatial_join_sorted`](#method.spatial_join_sorted).
+    pub fn proximity_map_sorted<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl Iterator<Item = ProxMapRow> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(Singles::new(big).flat_map(move |one| sorted(self.proximity_map_impl(one))))
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join), but every element
+    /// of `big` comes out at least once: one that matched nothing gets
+    /// a single row with `small_index: None`.
+    pub fn spatial_join_left<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = SJoinLeftRow> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(
+            interaction_join!(serial_join_left, left_outer, self, usize, big, interaction;
+                              geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                              ext_index_small)
+            .map(|(big_index, small_index)| SJoinLeftRow {
+                big_index,
+                small_index,
+            }),
+        )
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but every
+    /// element of `big` comes out at least once: one with nothing in
+    /// range gets a single row with `small_index` and `distance` set
+    /// to `None`.
+    pub fn proximity_map_left<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl Iterator<Item = ProxMapLeftRow> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(serial_join_left!(self, ProxMapRow, big;
+                  geo_big, ext_index_big, env,
+                  geo_small, stored_small, ext_index_small;
+                  {
+              self.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapRow {
+                  big_index: ext_index_big,
+                  small_index: ext_index_small,
+                  distance,
+              })
+                  }
+        )
+        .map(|(big_index, row)| ProxMapLeftRow {
+            big_index,
+            small_index: row.map(|row| row.small_index),
+            distance: row.map(|row| row.distance),
+        }))
+    }
+
+    /// Finds the elements of `big` that match at least one small
+    /// geometry under `interaction`, in no particular order. Unlike
+    /// `spatial_join`, each big geometry is done with at its first
+    /// match. To ask the same of the small geometries, see
+    /// [`small_semi_join`](#method.small_semi_join).
+    pub fn big_semi_join<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = usize> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(self
+            .first_match_impl(big, interaction)
+            .filter_map(|(index_big, first)| first.map(|_| index_big)))
+    }
+
+    /// Finds the elements of `big` that match no small geometry under
+    /// `interaction`, in no particular order, stopping at the first
+    /// match for the rest.
+    pub fn big_anti_join<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = usize> + 'a, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        Ok(self
+            .first_match_impl(big, interaction)
+            .filter_map(|(index_big, first)| match first {
+                Some(_) => None,
+                None => Some(index_big),
+            }))
+    }
+
+    /// Finds the small geometries that match at least one element of
+    /// `big` under `interaction`, in ascending order. Once a small
+    /// geometry has matched, the join skips it for every later big
+    /// geometry without checking the pair. The whole join runs before
+    /// this returns.
+    pub fn small_semi_join<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = usize>, Error>
+    where
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = sgs_try_into(big)?.prepare(&self.config);
+        let matched = vec![Cell::new(false); self.next_index];
+        {
+            let matched = &matched;
+            let rows = interaction_join!(join, join_outer, self, usize, big, interaction;
+                                         geo_big, _ext_index_big, geo_small, stored_small, ext_index_small;
+                                         keep_if !matched[ext_index_small].get();
+                                         ext_index_small);
+            for small_index in rows {
+                matched[small_index].set(true);
+            }
+        }
+        Ok(matched
+            .into_iter()
+            .enumerate()
+            .filter_map(|(small_index, matched)| matched.get().then_some(small_index)))
+    }
+
+    // Every big index, with the first small index it matched if any.
+    fn first_match_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+        interaction: Interaction,
+    ) -> impl Iterator<Item = (usize, Option<usize>)> + 'a {
+        interaction_join!(serial_join_first, first_outer, self, usize, big, interaction;
+                          geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                          ext_index_small)
+    }
+}
+
+// The streaming joins run the ordinary join on each big geometry by
+// itself. A bad geometry gets an empty join, so both cases have the
+// same type, followed by its error.
+pub(crate) fn single<C: CoordFloat>(
+    i: usize,
+    geo: Geometry<C>,
+    config: &Config,
+) -> (SplitGeoSeq<'static, C>, Option<Error>) {
+    match SplitGeoSeq::single(i, geo) {
+        Ok(one) => (one.prepare(config), None),
+        Err(e) => (SplitGeoSeq::default(), Some(e)),
+    }
+}
+
+// The one geometry `query` and `within_distance` look up, filed
+// under its type like in a `SplitGeoSeq` but in Options instead of
+// Vecs, so a lookup doesn't allocate. `reach` is how much further
+// than `max_distance` to search.
+#[derive(Default)]
+struct Probe<'g, C: CoordFloat> {
+    points: Option<Point<C>>,
+    lines: Option<Line<C>>,
+    rects: Option<Rect<C>>,
+    tris: Option<Triangle<C>>,
+    polys: Option<Cow<'g, Polygon<C>>>,
+    line_strings: Option<Cow<'g, LineString<C>>>,
+    multi_points: Option<Cow<'g, MultiPoint<C>>>,
+    multi_line_strings: Option<Cow<'g, MultiLineString<C>>>,
+    multi_polys: Option<Cow<'g, MultiPolygon<C>>>,
+    reach: f64,
+    metric: DistanceMetric,
+}
+
+impl<'g, C: CoordFloat> Probe<'g, C> {
+    fn new(geo: &'g Geometry<C>, reach: f64, config: &Config) -> Result<Self, Error> {
+        let mut probe = Probe {
+            reach,
+            metric: config.distance_metric,
+            ..Probe::default()
+        };
+        macro_rules! set {
+            ($geo:ident, $field:ident, $value:expr) => {{
+                $geo.is_safe(0)?;
+                probe.$field = Some($value);
+            }};
+        }
+        match geo {
+            Geometry::Point(pt) => set!(pt, points, *pt),
+            Geometry::Line(ln) => set!(ln, lines, *ln),
+            Geometry::Rect(r) => set!(r, rects, *r),
+            Geometry::Triangle(tri) => set!(tri, tris, *tri),
+            Geometry::Polygon(poly) => set!(poly, polys, Cow::Borrowed(poly)),
+            Geometry::LineString(ls) => set!(ls, line_strings, Cow::Borrowed(ls)),
+            Geometry::MultiPoint(mp) => set!(mp, multi_points, Cow::Borrowed(mp)),
+            Geometry::MultiLineString(mls) => set!(mls, multi_line_strings, Cow::Borrowed(mls)),
+            Geometry::MultiPolygon(mpoly) => set!(mpoly, multi_polys, Cow::Borrowed(mpoly)),
+            Geometry::GeometryCollection(_) => {
+                return Err(Error::UnsupportedGeometry(0, "GeometryCollection"))
+            }
+        }
+        if config.geographic {
+            macro_rules! unwrap {
+                ($field:ident) => {
+                    if let Some(geo) = probe.$field.as_mut() {
+                        unwrap_all(std::slice::from_mut(geo));
+                    }
+                };
+            }
+            unwrap!(points);
+            unwrap!(lines);
+            unwrap!(rects);
+            unwrap!(tris);
+            unwrap!(polys);
+            unwrap!(line_strings);
+            unwrap!(multi_points);
+            unwrap!(multi_line_strings);
+            unwrap!(multi_polys);
+        }
+        Ok(probe)
+    }
+}
+
+// The sorted joins go through `big` one geometry at a time in index
+// order, like the streaming ones, and only ever sort the rows for a
+// single big geometry. Each type's indexes already ascend, so taking
+// the lowest of the nine next indexes each time puts the whole of
+// `big` in order without sorting it or splitting it up front.
+struct Singles<'a, C: CoordFloat> {
+    streams: Vec<Peekable<SingleStream<'a, C>>>,
+}
+
+type SingleStream<'a, C> = Box<dyn Iterator<Item = (usize, SplitGeoSeq<'a, C>)> + 'a>;
+
+impl<'a, C: CoordFloat + 'a> Singles<'a, C> {
+    fn new(big: SplitGeoSeq<'a, C>) -> Self {
+        let SplitGeoSeq { geos, indexes } = big;
+        let mut streams = Vec::with_capacity(9);
+        macro_rules! split {
+            ($field:ident) => {
+                let stream: SingleStream<'a, C> =
+                    Box::new(geos.$field.into_iter().zip(indexes.$field.into_iter()).map(
+                        |(geo, index)| {
+                            let mut one = SplitGeoSeq::default();
+                            one.geos.$field.push(geo);
+                            one.indexes.$field.push(index);
+                            (index, one)
+                        },
+                    ));
+                streams.push(stream.peekable());
+            };
+        }
+        split!(points);
+        split!(lines);
+        split!(polys);
+        split!(line_strings);
+        split!(rects);
+        split!(tris);
+        split!(multi_points);
+        split!(multi_line_strings);
+        split!(multi_polys);
+        Singles { streams }
+    }
+}
+
+impl<'a, C: CoordFloat> Iterator for Singles<'a, C> {
+    type Item = SplitGeoSeq<'a, C>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, lowest) = self
+            .streams
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, stream)| stream.peek().map(|(index, _)| (*index, i)))
+            .min()?;
+        self.streams[lowest].next().map(|(_, one)| one)
+    }
+}
+
+// rayon can only keep the order of something it can index, so the
+// parallel sorted joins split all of `big` before they start.
+#[cfg(feature = "parallel")]
+fn par_singles<C: CoordFloat>(
+    big: SplitGeoSeq<C>,
+) -> impl IndexedParallelIterator<Item = SplitGeoSeq<C>> {
+    Singles::new(big).collect::<Vec<_>>().into_par_iter()
+}
+
+fn sorted<T: Ord>(rows: impl Iterator<Item = T>) -> Vec<T> {
+    let mut rows: Vec<T> = rows.collect();
+    rows.sort_unstable();
+    rows
+}
+
+// In a self join the same pair turns up both ways round, and every
+// geometry matches itself for most interactions, so we skip a pair
+// before checking it unless it's one we'd report.
+fn self_join_keeps(small_index: usize, big_index: usize, symmetric: bool) -> bool {
+    if symmetric {
+        small_index < big_index
+    } else {
+        small_index != big_index
+    }
+}
+
+#[cfg(feature = "parallel")]
+pub(crate) fn par_sgs_try_into<'a, C: CoordFloat, T, U>(
+    thing: T,
+) -> Result<SplitGeoSeq<'a, C>, Error>
+where
+    T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+    U: std::any::Any,
+{
+    let thing: Result<Par<SplitGeoSeq<C>>, _> = thing.try_into();
+    // FIXME: maybe map_error
+    match thing {
+        Ok(thing) => {
+            // conversion into SplitGeoSeq worked!
+            Ok(thing.0)
+        }
+        Err(e) => {
+            let any_e = &e as &dyn std::any::Any;
+            Err(any_e.downcast_ref::<Error>().expect("impossible").clone())
+        }
+    }
+}
+
+// We have to handle parallel with separate methods because parallel
+// and serial iterator have different types.
+
+#[cfg(feature = "parallel")]
+impl<'s, C: CoordFloat> ParSpatialIndex<'s, C> {
+    pub fn new<T, U>(small: T, config: Config) -> Result<Self, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'s, C>>, Error = U>,
+        U: std::any::Any,
+    {
+        let small = par_sgs_try_into(small)?.prepare(&config);
+        Ok(ParSpatialIndex(SpatialIndex::build(
+            small,
+            config,
+            Vec::new(),
+        )))
+    }
+
+    pub(crate) fn with_distances<T, U, F>(
+        small: T,
+        config: Config,
+        distances: F,
+    ) -> Result<Self, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'s, C>>, Error = U>,
+        U: std::any::Any,
+        F: Fn(usize) -> f64,
+    {
+        let small = par_sgs_try_into(small)?.prepare(&config);
+        let distances = own_distances(small.indexes.end(), distances)?;
+        Ok(ParSpatialIndex(SpatialIndex::build(
+            small, config, distances,
+        )))
+    }
+
+    pub fn proximity_map<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl ParallelIterator<Item = ProxMapRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U> + 'a,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(self.proximity_map_impl(big))
+    }
+
+    /// Finds the pairs of small geometries within `max_distance` of
+    /// each other. Each pair comes out once, with `small_index` less
+    /// than `big_index`, and no geometry is paired with itself. With
+    /// per-geometry distances, a pair is held to the distance of its
+    /// `small_index` geometry.
+    pub fn self_proximity_map(&self) -> impl ParallelIterator<Item = ProxMapRow> + '_ {
+        par_join!(self.0, ProxMapRow, self.0.small.borrowed();
+                  geo_big, ext_index_big, env,
+                  geo_small, stored_small, ext_index_small;
+                  if ext_index_small < ext_index_big {
+                      self.0.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapRow {
+                          big_index: ext_index_big,
+                          small_index: ext_index_small,
+                          distance,
+                      })
+                  } else {
+                      None
+                  }
+        )
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but pulls `big`
+    /// from an iterator one geometry at a time; see
+    /// [`SpatialIndex::proximity_map_stream`](struct.SpatialIndex.html#method.proximity_map_stream).
+    /// Pulling from the iterator happens on one thread at a time, so
+    /// it can't keep up with a cheap join.
+    pub fn proximity_map_stream<'a, G, I>(
+        &'a self,
+        big: I,
+    ) -> impl ParallelIterator<Item = Result<ProxMapRow, Error>> + 'a
+    where
+        I: IntoIterator<Item = G>,
+        I::IntoIter: Send + 'a,
+        G: Into<Geometry<C>> + Send,
+    {
+        big.into_iter()
+            .enumerate()
+            .par_bridge()
+            .flat_map(move |(i, geo)| {
+                let (one, error) = single(i, geo.into(), &self.0.config);
+                self.proximity_map_impl(one).map(Ok).chain(error.map(Err))
+            })
+    }
+
+    pub(crate) fn proximity_map_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+    ) -> impl ParallelIterator<Item = ProxMapRow> + 'a {
+        par_join!(self.0, ProxMapRow, big;
+                  geo_big, ext_index_big, env,
+                  geo_small, stored_small, ext_index_small;
+                  {
+              self.0.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapRow {
+                  big_index: ext_index_big,
+                  small_index: ext_index_small,
+                  distance,
+              })
+                  }
+        )
+    }
+
+    pub fn proximity_map_with_geos<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl ParallelIterator<Item = ProxMapGeoRow<'a, C>> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U> + 'a,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(par_join!(self.0, ProxMapGeoRow<C>, big;
+              geo_big, ext_index_big, env,
+              geo_small, stored_small, ext_index_small;
+              {
+          self.0.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapGeoRow {
+              big_index: ext_index_big,
+              small_index: ext_index_small,
+              big: geo_big.to_row(),
+              small: stored_small.row_ref(),
+              distance,
+          })
+              }
+        ))
+    }
+
+    /// Finds the `k` elements of `small` nearest to each element of
+    /// `big`, however far away they are.
+    ///
+    /// Rows for each element of `big` are nearest first, ties broken
+    /// by `small_index`, but rows for different elements of `big` may
+    /// be interleaved.
+    pub fn nearest_neighbors<'a, T, U>(
+        &'a self,
+        big: T,
+        k: usize,
+    ) -> Result<impl ParallelIterator<Item = ProxMapRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(self.nearest_neighbors_impl(big, k, f64::INFINITY))
+    }
+
+    /// Like [`nearest_neighbors`](#method.nearest_neighbors), but
+    /// ignores elements of `small` further than `max_distance` away,
+    /// so some elements of `big` may get fewer than `k` rows.
+    pub fn nearest_neighbors_within<'a, T, U>(
+        &'a self,
+        big: T,
+        k: usize,
+        max_distance: f64,
+    ) -> Result<impl ParallelIterator<Item = ProxMapRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = Config::new().max_distance(max_distance).validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(self.nearest_neighbors_impl(big, k, max_distance))
+    }
+
+    fn nearest_neighbors_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+        k: usize,
+        max_distance: f64,
+    ) -> impl ParallelIterator<Item = ProxMapRow> + 'a {
+        par_join_outer!(big; geo_big, ext_index_big, env;
+                        self.0.nearest(&geo_big, env, ext_index_big, k, max_distance).into_iter(),
+                        self.0.nearest(geo_big.geo(), env, ext_index_big, k, max_distance))
+    }
+
+    pub fn spatial_join<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = SJoinRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(self.spatial_join_impl(big, interaction))
+    }
+
+    /// Joins the small geometries against each other; see
+    /// [`SpatialIndex::self_join`](struct.SpatialIndex.html#method.self_join)
+    /// for which pairs come out.
+    pub fn self_join(
+        &self,
+        interaction: Interaction,
+    ) -> impl ParallelIterator<Item = SJoinRow> + '_ {
+        let symmetric = interaction.is_symmetric();
+        interaction_join!(par_join, par_join_outer, self.0, SJoinRow, self.0.small.borrowed(), interaction;
+                          geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                          keep_if self_join_keeps(ext_index_small, ext_index_big, symmetric);
+                          SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join), but pulls `big`
+    /// from an iterator one geometry at a time; see
+    /// [`proximity_map_stream`](#method.proximity_map_stream).
+    pub fn spatial_join_stream<'a, G, I>(
+        &'a self,
+        big: I,
+        interaction: Interaction,
+    ) -> impl ParallelIterator<Item = Result<SJoinRow, Error>> + 'a
+    where
+        I: IntoIterator<Item = G>,
+        I::IntoIter: Send + 'a,
+        G: Into<Geometry<C>> + Send,
+    {
+        // A bad interaction comes out first, and then joins nothing.
+        interaction.validate().map(Err).into_par_iter().chain(
+            big.into_iter()
+                .enumerate()
+                .par_bridge()
+                .flat_map(move |(i, geo)| {
+                    let (one, error) = single(i, geo.into(), &self.0.config);
+                    self.spatial_join_impl(one, interaction)
+                        .map(Ok)
+                        .chain(error.map(Err))
+                }),
+        )
+    }
+
+    pub(crate) fn spatial_join_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+        interaction: Interaction,
+    ) -> impl ParallelIterator<Item = SJoinRow> + 'a {
+        interaction_join!(par_join, par_join_outer, self.0, SJoinRow, big, interaction;
+                          geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                          SJoinRow {big_index: ext_index_big, small_index: ext_index_small})
+    }
+
+    pub fn spatial_join_with_geos<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = SJoinGeoRow<'a, C>> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+
+        Ok(
+            interaction_join!(par_join, par_join_outer, self.0, SJoinGeoRow<C>, big, interaction;
+                             geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                             SJoinGeoRow {big_index: ext_index_big, small_index: ext_index_small,
+                                          big: geo_big.to_row(), small: stored_small.row_ref()}),
+        )
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join) with
+    /// `Interaction::Intersects`, but each row also says what the pair
+    /// shares; see
+    /// [`SpatialIndex::spatial_join_with_overlap`](struct.SpatialIndex.html#method.spatial_join_with_overlap).
+    pub fn spatial_join_with_overlap<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl ParallelIterator<Item = SJoinOverlapRow<C>> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(par_join!(self.0, SJoinOverlapRow<C>, big;
+        geo_big, ext_index_big, env,
+        geo_small, stored_small, ext_index_small;
+        if geo_small.Intersects(geo_big.geo()) {
+            Some(overlap_row(ext_index_big, ext_index_small, geo_big.geo(), geo_small))
+        } else {
+            None
+        }))
+    }
+
+    /// Finds the small geometries that interact with `geo`; see
+    /// [`SpatialIndex::query`](struct.SpatialIndex.html#method.query).
+    /// There's only one geometry to look up, so this runs on the
+    /// calling thread.
+    pub fn query<'a>(
+        &'a self,
+        geo: &'a Geometry<C>,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = usize> + 'a, Error> {
+        self.0.query(geo, interaction)
+    }
+
+    /// Finds the small geometries within `distance` of `geo`; see
+    /// [`SpatialIndex::within_distance`](struct.SpatialIndex.html#method.within_distance).
+    pub fn within_distance<'a>(
+        &'a self,
+        geo: &'a Geometry<C>,
+        distance: f64,
+    ) -> Result<impl Iterator<Item = (usize, f64)> + 'a, Error> {
+        self.0.within_distance(geo, distance)
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join), but the rows are
+    /// ordered by `big_index` and then `small_index`: `collect` them,
+    /// or use any other consumer that keeps order, to see it. Big
+    /// geometries are still joined in parallel.
+    pub fn spatial_join_sorted<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = SJoinRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(par_singles(big).flat_map(move |one| {
+            sorted(self.0.spatial_join_impl(one, interaction)).into_par_iter()
+        }))
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but the rows are
+    /// ordered by `big_index` and then `small_index`; see
+    /// [`spatial_join_sorted`](#method.spatial_join_sorted).
+    pub fn proximity_map_sorted<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl ParallelIterator<Item = ProxMapRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(par_singles(big)
+            .flat_map(move |one| sorted(self.0.proximity_map_impl(one)).into_par_iter()))
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join), but every element
+    /// of `big` comes out at least once; see
+    /// [`SpatialIndex::spatial_join_left`](struct.SpatialIndex.html#method.spatial_join_left).
+    pub fn spatial_join_left<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = SJoinLeftRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(
+            interaction_join!(par_join_left, par_left_outer, self.0, usize, big, interaction;
+                              geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                              ext_index_small)
+            .map(|(big_index, small_index)| SJoinLeftRow {
+                big_index,
+                small_index,
+            }),
+        )
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but every
+    /// element of `big` comes out at least once; see
+    /// [`SpatialIndex::proximity_map_left`](struct.SpatialIndex.html#method.proximity_map_left).
+    pub fn proximity_map_left<'a, T, U>(
+        &'a self,
+        big: T,
+    ) -> Result<impl ParallelIterator<Item = ProxMapLeftRow> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(par_join_left!(self.0, ProxMapRow, big;
+                  geo_big, ext_index_big, env,
+                  geo_small, stored_small, ext_index_small;
+                  {
+              self.0.band_distance(geo_big.geo(), geo_small, ext_index_small).map(|distance| ProxMapRow {
+                  big_index: ext_index_big,
+                  small_index: ext_index_small,
+                  distance,
+              })
+                  }
+        )
+        .map(|(big_index, row)| ProxMapLeftRow {
+            big_index,
+            small_index: row.map(|row| row.small_index),
+            distance: row.map(|row| row.distance),
+        }))
+    }
+
+    /// Finds the elements of `big` that match at least one small
+    /// geometry under `interaction`, stopping at the first match for
+    /// each.
+    pub fn big_semi_join<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = usize> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(self
+            .first_match_impl(big, interaction)
+            .filter_map(|(index_big, first)| first.map(|_| index_big)))
+    }
+
+    /// Finds the elements of `big` that match no small geometry under
+    /// `interaction`, stopping at the first match for the rest.
+    pub fn big_anti_join<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = usize> + 'a, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        Ok(self
+            .first_match_impl(big, interaction)
+            .filter_map(|(index_big, first)| match first {
+                Some(_) => None,
+                None => Some(index_big),
+            }))
+    }
+
+    /// Finds the small geometries that match at least one element of
+    /// `big` under `interaction`; see
+    /// [`SpatialIndex::small_semi_join`](struct.SpatialIndex.html#method.small_semi_join).
+    /// A small geometry can still be checked against several big ones
+    /// at once before any of them marks it matched.
+    pub fn small_semi_join<'a, T, U>(
+        &'a self,
+        big: T,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = usize>, Error>
+    where
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any + std::fmt::Debug,
+    {
+        if let Some(e) = interaction.validate() {
+            return Err(e);
+        }
+        let big = par_sgs_try_into(big)?.prepare(&self.0.config);
+        let matched: Vec<AtomicBool> = (0..self.0.next_index)
+            .map(|_| AtomicBool::new(false))
+            .collect();
+        {
+            let matched = &matched;
+            let rows = interaction_join!(par_join, par_join_outer, self.0, usize, big, interaction;
+                                         geo_big, _ext_index_big, geo_small, stored_small, ext_index_small;
+                                         keep_if !matched[ext_index_small].load(Ordering::Relaxed);
+                                         ext_index_small);
+            rows.for_each(|small_index| matched[small_index].store(true, Ordering::Relaxed));
+        }
+        Ok(matched
+            .into_par_iter()
+            .enumerate()
+            .filter_map(|(small_index, matched)| matched.into_inner().then_some(small_index)))
+    }
+
+    fn first_match_impl<'a>(
+        &'a self,
+        big: SplitGeoSeq<'a, C>,
+        interaction: Interaction,
+    ) -> impl ParallelIterator<Item = (usize, Option<usize>)> + 'a {
+        interaction_join!(par_join_first, par_first_outer, self.0, usize, big, interaction;
+                          geo_big, ext_index_big, geo_small, stored_small, ext_index_small;
+                          ext_index_small)
     }
 }
 
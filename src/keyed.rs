@@ -0,0 +1,254 @@
+use geo::Geometry;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::index::single;
+use crate::structs::unzip_keyed;
+use crate::{Error, Interaction, KeyedSpatialIndex, ProxMapKeyRow, SJoinKeyRow, SpatialIndex};
+#[cfg(feature = "parallel")]
+use crate::{ParKeyedSpatialIndex, ParSpatialIndex};
+
+// Keyed joins run the ordinary positional join and then look the
+// positions up: small keys live in the index, and big keys either in
+// a Vec the returned iterator owns or, for the stream joins, next to
+// the one big geometry being joined. Every key type shares one copy
+// of the join code that way.
+impl<K: Clone> KeyedSpatialIndex<K> {
+    pub fn spatial_join<'a, B, G, I>(
+        &'a self,
+        big: I,
+        interaction: Interaction,
+    ) -> Result<impl Iterator<Item = SJoinKeyRow<B, K>> + 'a, Error>
+    where
+        I: IntoIterator<Item = (B, G)>,
+        G: Into<Geometry<f64>>,
+        B: Clone + 'a,
+    {
+        let (big_keys, big) = unzip_keyed(big);
+        Ok(self
+            .index
+            .spatial_join(big, interaction)?
+            .map(move |row| SJoinKeyRow {
+                big_key: big_keys[row.big_index].clone(),
+                small_key: self.keys[row.small_index].clone(),
+            }))
+    }
+
+    pub fn proximity_map<'a, B, G, I>(
+        &'a self,
+        big: I,
+    ) -> Result<impl Iterator<Item = ProxMapKeyRow<B, K>> + 'a, Error>
+    where
+        I: IntoIterator<Item = (B, G)>,
+        G: Into<Geometry<f64>>,
+        B: Clone + 'a,
+    {
+        let (big_keys, big) = unzip_keyed(big);
+        Ok(self
+            .index
+            .proximity_map(big)?
+            .map(move |row| ProxMapKeyRow {
+                big_key: big_keys[row.big_index].clone(),
+                small_key: self.keys[row.small_index].clone(),
+                distance: row.distance,
+            }))
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join), but pulls `big`
+    /// from an iterator one pair at a time instead of converting it
+    /// all up front, so neither the big geometries nor their keys pile
+    /// up in memory. A geometry that fails validation shows up as an
+    /// `Err` in its place and the join carries on with the next one.
+    pub fn spatial_join_stream<'a, B, G, I>(
+        &'a self,
+        big: I,
+        interaction: Interaction,
+    ) -> impl Iterator<Item = Result<SJoinKeyRow<B, K>, Error>> + 'a
+    where
+        I: IntoIterator<Item = (B, G)>,
+        I::IntoIter: 'a,
+        G: Into<Geometry<f64>>,
+        B: Clone + 'a,
+    {
+        // A bad interaction comes out first, and then joins nothing.
+        interaction
+            .validate()
+            .map(Err)
+            .into_iter()
+            .chain(
+                big.into_iter()
+                    .enumerate()
+                    .flat_map(move |(i, (big_key, geo))| {
+                        let (one, error) = single(i, geo.into(), &self.index.config);
+                        self.index
+                            .spatial_join_impl(one, interaction)
+                            .map(move |row| {
+                                Ok(SJoinKeyRow {
+                                    big_key: big_key.clone(),
+                                    small_key: self.keys[row.small_index].clone(),
+                                })
+                            })
+                            .chain(error.map(Err))
+                    }),
+            )
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but pulls `big`
+    /// from an iterator one pair at a time; see
+    /// [`spatial_join_stream`](#method.spatial_join_stream).
+    pub fn proximity_map_stream<'a, B, G, I>(
+        &'a self,
+        big: I,
+    ) -> impl Iterator<Item = Result<ProxMapKeyRow<B, K>, Error>> + 'a
+    where
+        I: IntoIterator<Item = (B, G)>,
+        I::IntoIter: 'a,
+        G: Into<Geometry<f64>>,
+        B: Clone + 'a,
+    {
+        big.into_iter()
+            .enumerate()
+            .flat_map(move |(i, (big_key, geo))| {
+                let (one, error) = single(i, geo.into(), &self.index.config);
+                self.index
+                    .proximity_map_impl(one)
+                    .map(move |row| {
+                        Ok(ProxMapKeyRow {
+                            big_key: big_key.clone(),
+                            small_key: self.keys[row.small_index].clone(),
+                            distance: row.distance,
+                        })
+                    })
+                    .chain(error.map(Err))
+            })
+    }
+
+    /// The positional index underneath; its `small_index` values are
+    /// positions in the sequence this index was built from.
+    pub fn index(&self) -> &SpatialIndex<'static> {
+        &self.index
+    }
+
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<K: Clone + Send + Sync> ParKeyedSpatialIndex<K> {
+    pub fn spatial_join<'a, B, G, I>(
+        &'a self,
+        big: I,
+        interaction: Interaction,
+    ) -> Result<impl ParallelIterator<Item = SJoinKeyRow<B, K>> + 'a, Error>
+    where
+        I: IntoIterator<Item = (B, G)>,
+        G: Into<Geometry<f64>>,
+        B: Clone + Send + Sync + 'a,
+    {
+        let (big_keys, big) = unzip_keyed(big);
+        Ok(self
+            .index
+            .spatial_join(big, interaction)?
+            .map(move |row| SJoinKeyRow {
+                big_key: big_keys[row.big_index].clone(),
+                small_key: self.keys[row.small_index].clone(),
+            }))
+    }
+
+    pub fn proximity_map<'a, B, G, I>(
+        &'a self,
+        big: I,
+    ) -> Result<impl ParallelIterator<Item = ProxMapKeyRow<B, K>> + 'a, Error>
+    where
+        I: IntoIterator<Item = (B, G)>,
+        G: Into<Geometry<f64>>,
+        B: Clone + Send + Sync + 'a,
+    {
+        let (big_keys, big) = unzip_keyed(big);
+        Ok(self
+            .index
+            .proximity_map(big)?
+            .map(move |row| ProxMapKeyRow {
+                big_key: big_keys[row.big_index].clone(),
+                small_key: self.keys[row.small_index].clone(),
+                distance: row.distance,
+            }))
+    }
+
+    /// Like [`spatial_join`](#method.spatial_join), but pulls `big`
+    /// from an iterator one pair at a time; see
+    /// [`KeyedSpatialIndex::spatial_join_stream`](struct.KeyedSpatialIndex.html#method.spatial_join_stream).
+    /// Pulling from the iterator happens on one thread at a time, so
+    /// it can't keep up with a cheap join.
+    pub fn spatial_join_stream<'a, B, G, I>(
+        &'a self,
+        big: I,
+        interaction: Interaction,
+    ) -> impl ParallelIterator<Item = Result<SJoinKeyRow<B, K>, Error>> + 'a
+    where
+        I: IntoIterator<Item = (B, G)>,
+        I::IntoIter: Send + 'a,
+        G: Into<Geometry<f64>> + Send,
+        B: Clone + Send + Sync + 'a,
+    {
+        // A bad interaction comes out first, and then joins nothing.
+        interaction.validate().map(Err).into_par_iter().chain(
+            big.into_iter()
+                .enumerate()
+                .par_bridge()
+                .flat_map(move |(i, (big_key, geo))| {
+                    let (one, error) = single(i, geo.into(), &self.index.0.config);
+                    self.index
+                        .spatial_join_impl(one, interaction)
+                        .map(move |row| {
+                            Ok(SJoinKeyRow {
+                                big_key: big_key.clone(),
+                                small_key: self.keys[row.small_index].clone(),
+                            })
+                        })
+                        .chain(error.map(Err))
+                }),
+        )
+    }
+
+    /// Like [`proximity_map`](#method.proximity_map), but pulls `big`
+    /// from an iterator one pair at a time; see
+    /// [`spatial_join_stream`](#method.spatial_join_stream).
+    pub fn proximity_map_stream<'a, B, G, I>(
+        &'a self,
+        big: I,
+    ) -> impl ParallelIterator<Item = Result<ProxMapKeyRow<B, K>, Error>> + 'a
+    where
+        I: IntoIterator<Item = (B, G)>,
+        I::IntoIter: Send + 'a,
+        G: Into<Geometry<f64>> + Send,
+        B: Clone + Send + Sync + 'a,
+    {
+        big.into_iter()
+            .enumerate()
+            .par_bridge()
+            .flat_map(move |(i, (big_key, geo))| {
+                let (one, error) = single(i, geo.into(), &self.index.0.config);
+                self.index
+                    .proximity_map_impl(one)
+                    .map(move |row| {
+                        Ok(ProxMapKeyRow {
+                            big_key: big_key.clone(),
+                            small_key: self.keys[row.small_index].clone(),
+                            distance: row.distance,
+                        })
+                    })
+                    .chain(error.map(Err))
+            })
+    }
+
+    pub fn index(&self) -> &ParSpatialIndex<'static> {
+        &self.index
+    }
+
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+}
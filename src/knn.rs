@@ -0,0 +1,255 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use geo::{
+    Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Rect, Triangle,
+};
+use rstar::{ParentNode, RTreeNode, AABB};
+use smallvec::{smallvec, SmallVec};
+
+use crate::antimeridian::{copies, shifted};
+use crate::relates::Relates;
+use crate::rtrees::{query_envelope, widen, Envelope, FakeRegion};
+use crate::structs::Stored;
+use crate::{geodesic, CoordFloat, DistanceMetric, ProxMapRow, SpatialIndex};
+
+// Everything a big geometry needs to be measured against every kind
+// of small geometry.
+pub trait RelatesAll<C: CoordFloat>:
+    Envelope<C>
+    + Relates<Point<C>>
+    + Relates<Line<C>>
+    + Relates<LineString<C>>
+    + Relates<Polygon<C>>
+    + Relates<Rect<C>>
+    + Relates<Triangle<C>>
+    + Relates<MultiPoint<C>>
+    + Relates<MultiLineString<C>>
+    + Relates<MultiPolygon<C>>
+{
+}
+
+impl<C: CoordFloat, T> RelatesAll<C> for T where
+    T: Envelope<C>
+        + Relates<Point<C>>
+        + Relates<Line<C>>
+        + Relates<LineString<C>>
+        + Relates<Polygon<C>>
+        + Relates<Rect<C>>
+        + Relates<Triangle<C>>
+        + Relates<MultiPoint<C>>
+        + Relates<MultiLineString<C>>
+        + Relates<MultiPolygon<C>>
+{
+}
+
+enum Step<'a, C: CoordFloat> {
+    // An R-tree node we haven't opened yet.
+    Node(&'a ParentNode<FakeRegion<C>>),
+    // A small geometry we only know the bounding box of, and the
+    // shift its R-tree entry was made with.
+    Leaf(usize, i32),
+    // A small geometry (by external index) whose true distance is
+    // the key.
+    Found(usize),
+}
+
+// Heap entries are keyed by a lower bound on the distance to anything
+// underneath them; for `Found` entries the bound is exact. Among equal
+// keys we open nodes and measure leaves before we report anything,
+// and we report ties in `small_index` order, so results don't depend
+// on how the R-trees happened to be built.
+struct Entry<'a, C: CoordFloat> {
+    key: f64,
+    bucket: usize,
+    step: Step<'a, C>,
+}
+
+impl<C: CoordFloat> Entry<'_, C> {
+    fn rank(&self) -> (f64, usize, usize) {
+        match self.step {
+            Step::Found(small_index) => (self.key, 1, small_index),
+            _ => (self.key, 0, 0),
+        }
+    }
+}
+
+impl<C: CoordFloat> PartialEq for Entry<'_, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<C: CoordFloat> Eq for Entry<'_, C> {}
+
+impl<C: CoordFloat> PartialOrd for Entry<'_, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: CoordFloat> Ord for Entry<'_, C> {
+    // Reversed, since BinaryHeap pops the largest entry first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.rank(), other.rank());
+        b.0.partial_cmp(&a.0)
+            .unwrap_or(Ordering::Equal)
+            .then(b.1.cmp(&a.1))
+            .then(b.2.cmp(&a.2))
+    }
+}
+
+fn planar_env_distance(a: &AABB<[f64; 2]>, b: &AABB<[f64; 2]>) -> f64 {
+    let (a_lower, a_upper) = (a.lower(), a.upper());
+    let (b_lower, b_upper) = (b.lower(), b.upper());
+    let dx = (b_lower[0] - a_upper[0])
+        .max(a_lower[0] - b_upper[0])
+        .max(0.);
+    let dy = (b_lower[1] - a_upper[1])
+        .max(a_lower[1] - b_upper[1])
+        .max(0.);
+    dx.hypot(dy)
+}
+
+impl<C: CoordFloat> SpatialIndex<'_, C> {
+    // Best-first search over all nine R-trees at once: every tree's
+    // root goes into a single heap, so we always expand whichever
+    // node or geometry (of any type) might be closest.
+    pub(crate) fn nearest<G: RelatesAll<C>>(
+        &self,
+        geo_big: &G,
+        env: AABB<[C; 2]>,
+        ext_index_big: usize,
+        k: usize,
+        max_distance: f64,
+    ) -> SmallVec<[ProxMapRow; 10]> {
+        let trees = [
+            &self.point_tree,
+            &self.line_tree,
+            &self.poly_tree,
+            &self.ls_tree,
+            &self.rect_tree,
+            &self.tri_tree,
+            &self.multi_point_tree,
+            &self.multi_ls_tree,
+            &self.multi_poly_tree,
+        ];
+        let queries = if self.config.geographic {
+            copies(query_envelope(env, &self.config))
+        } else {
+            smallvec![(query_envelope(env, &self.config), 0)]
+        };
+        let metric_env_distance = match self.config.distance_metric {
+            DistanceMetric::Euclidean => planar_env_distance,
+            DistanceMetric::Geodesic => geodesic::env_distance,
+        };
+        // Bounds are worked out in `f64` whatever the coordinates.
+        let wide_queries: SmallVec<[AABB<[f64; 2]>; 3]> =
+            queries.iter().map(|(query, _)| widen(*query)).collect();
+        let env_distance = |other: &AABB<[C; 2]>| {
+            let other = widen(*other);
+            wide_queries
+                .iter()
+                .map(|query| metric_env_distance(query, &other))
+                .fold(f64::INFINITY, f64::min)
+        };
+        let mut heap = BinaryHeap::new();
+        for (bucket, tree) in trees.iter().enumerate() {
+            if tree.size() > 0 {
+                heap.push(Entry {
+                    key: env_distance(&tree.root().envelope()),
+                    bucket,
+                    step: Step::Node(tree.root()),
+                });
+            }
+        }
+
+        let mut result = SmallVec::new();
+        while let Some(Entry { key, bucket, step }) = heap.pop() {
+            if result.len() >= k || key > max_distance {
+                break;
+            }
+            match step {
+                Step::Node(node) => {
+                    for child in node.children() {
+                        heap.push(match child {
+                            RTreeNode::Leaf(fake) => Entry {
+                                key: env_distance(&fake.bbox),
+                                bucket,
+                                step: Step::Leaf(fake.id, fake.shift),
+                            },
+                            RTreeNode::Parent(parent) => Entry {
+                                key: env_distance(&parent.envelope()),
+                                bucket,
+                                step: Step::Node(parent),
+                            },
+                        });
+                    }
+                }
+                Step::Leaf(index_small, shift) => {
+                    let (distance, ext_index_small) = queries
+                        .iter()
+                        .map(|(_, turns)| {
+                            self.distance_to(geo_big, bucket, index_small, shift - turns)
+                        })
+                        .fold((f64::INFINITY, 0), |a, b| if b.0 < a.0 { b } else { a });
+                    assert!(distance.is_finite());
+                    heap.push(Entry {
+                        key: distance,
+                        bucket,
+                        step: Step::Found(ext_index_small),
+                    });
+                }
+                // With `Config::geographic` a geometry can have several
+                // R-tree entries; the first one out is the nearest.
+                Step::Found(ext_index_small) => {
+                    if !result
+                        .iter()
+                        .any(|row: &ProxMapRow| row.small_index == ext_index_small)
+                    {
+                        result.push(ProxMapRow {
+                            big_index: ext_index_big,
+                            small_index: ext_index_small,
+                            distance: key,
+                        })
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    // `bucket` follows the order of the trees in `nearest`; `shift`
+    // is how many turns east to move the small geometry first.
+    fn distance_to<G: RelatesAll<C>>(
+        &self,
+        geo_big: &G,
+        bucket: usize,
+        index: usize,
+        shift: i32,
+    ) -> (f64, usize) {
+        let (geos, indexes) = (&self.small.geos, &self.small.indexes);
+        macro_rules! measure {
+            ($field:ident) => {{
+                let mut slot = None;
+                let geo_small = shifted(geos.$field[index].geo(), shift, &mut slot);
+                let distance = match self.config.distance_metric {
+                    DistanceMetric::Euclidean => geo_big.EuclideanDistance(geo_small),
+                    DistanceMetric::Geodesic => geo_big.GeodesicDistance(geo_small),
+                };
+                (distance, indexes.$field.get(index))
+            }};
+        }
+        match bucket {
+            0 => measure!(points),
+            1 => measure!(lines),
+            2 => measure!(polys),
+            3 => measure!(line_strings),
+            4 => measure!(rects),
+            5 => measure!(tris),
+            6 => measure!(multi_points),
+            7 => measure!(multi_line_strings),
+            _ => measure!(multi_polys),
+        }
+    }
+}
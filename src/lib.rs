@@ -13,6 +13,39 @@
 //! where elements of `small` contain elements of `big` or are within
 //! elements of `big` by passing different values of
 //! [Interaction](./enum.Interaction.html).
+//!
+//! The rest of the [DE-9IM](https://en.wikipedia.org/wiki/DE-9IM)
+//! predicates are there too: `Touches`, `Crosses`, `Overlaps`,
+//! `Covers`, `CoveredBy` and `Disjoint`. They follow the same
+//! definitions as libgeos, always read as "`small` touches `big`"
+//! and so on. `Disjoint` joins can't use the R-trees to skip
+//! anything, so expect them to produce (and take time proportional
+//! to) nearly every pair.
+//!
+//! For anything else, `Interaction::Relate` takes a
+//! [`RelatePattern`](./struct.RelatePattern.html) parsed from a
+//! DE-9IM mask like `"T*F**F***"`. Patterns that require the shapes
+//! to meet get the same R-tree filtering as the named predicates;
+//! patterns that don't (like `"FF*FF****"`) are as slow as
+//! `Disjoint`. To see the whole matrix for a pair of geometries, use
+//! [`IntersectionMatrix::new`](./struct.IntersectionMatrix.html#method.new).
+//!
+//! To join shapes that are near each other rather than touching, use
+//! `Interaction::WithinDistance(d)`, which keeps pairs no more than `d`
+//! apart, measured like proximity maps are. Pairs that intersect match
+//! without being measured. The small envelopes in the R-trees are
+//! buffered by [`max_distance`](./struct.Config.html#method.max_distance),
+//! and for a larger `d` each big envelope is grown by the difference
+//! before searching, which turns up more candidates to measure. An
+//! index built with per-geometry distances grows them by all of `d`.
+//!
+//! To find out how much intersecting shapes share, say the area of
+//! each parcel that falls in each flood zone, use
+//! [`spatial_join_with_overlap`](./struct.SpatialIndex.html#method.spatial_join_with_overlap).
+//! Its rows carry the area of the overlap for pairs of polygons,
+//! rects and triangles, and for a line against a shape, the line
+//! clipped to that shape along with its length. It doesn't build the
+//! polygon where two areas overlap, only measures it.
 
 //! ## Proximity Maps
 //!
@@ -23,6 +56,33 @@
 //! [`max_distance`](./struct.Config.html#method.max_distance) method
 //! on the [`Config`](./struct.Config.html) struct.
 //!
+//! To find the pairs in a distance band instead, say between 500
+//! and 2000 meters apart, also set
+//! [`min_distance`](./struct.Config.html#method.min_distance);
+//! pairs closer than that are left out. With the Euclidean metric,
+//! pairs whose bounding boxes are too close together to hold
+//! anything further apart are skipped without being measured.
+//!
+//! When each small shape needs its own threshold, say 50 meters
+//! around hydrants but 2 kilometers around plants, build the index
+//! with [`serial_with_distances`](./struct.Config.html#method.serial_with_distances)
+//! and a function giving each one's distance.
+//!
+//! If you don't know a good threshold, [`nearest_neighbors`](./struct.SpatialIndex.html#method.nearest_neighbors)
+//! finds the `k` closest elements of `small` to each element of
+//! `big` instead, however far away they are, and
+//! `nearest_neighbors_within` does the same with a cutoff.
+//!
+//! ## Self Joins
+//!
+//! To find pairs within a single sequence, like overlapping building
+//! footprints, build an index over it and call
+//! [`self_join`](./struct.SpatialIndex.html#method.self_join) or
+//! [`self_proximity_map`](./struct.SpatialIndex.html#method.self_proximity_map)
+//! instead of passing the same sequence in again. These skip pairing a
+//! shape with itself and, where order doesn't matter, report each pair
+//! once with `small_index < big_index`.
+//!
 //! ## Inputs
 //!
 //! Inputs are sequences of shapes, and shapes must be one of the
@@ -33,15 +93,23 @@
 //! * [line strings](https://docs.rs/geo/latest/geo/struct.LineString.html),
 //! * [polygons](https://docs.rs/geo/latest/geo/struct.Polygon.html),
 //! * [rectangles](https://docs.rs/geo/latest/geo/struct.Rect.html),
-//! * [triangles](https://docs.rs/geo/latest/geo/struct.Triangle.html), or
+//! * [triangles](https://docs.rs/geo/latest/geo/struct.Triangle.html),
+//! * [multi-points](https://docs.rs/geo/latest/geo/struct.MultiPoint.html),
+//! * [multi-line strings](https://docs.rs/geo/latest/geo/struct.MultiLineString.html),
+//! * [multi-polygons](https://docs.rs/geo/latest/geo/struct.MultiPolygon.html), or
 //! * the [Geometry](https://docs.rs/geo/latest/geo/enum.Geometry.html) enum
 //!
-//! `MultiPoint`, `MultiLineString`, and `MultiPolygon` are *not* supported.
+//! Multi-geometries are kept whole, so the indexes in result rows
+//! refer to the original multi-geometry rather than its members. A
+//! `GeometryCollection` nested inside another sequence is *not*
+//! supported.
 //!
-//! While the [geo] crate makes these types generic over the
-//! coordinate type, `spatial-join` only supports [geo] types
-//! parametrized with [std::f64] coordinate types (i.e.,
-//! `Polygon<f64>`).
+//! Shapes can have `f64` or `f32` coordinates (i.e., `Polygon<f64>`
+//! or `Polygon<f32>`), as long as all the shapes in one join agree;
+//! anything implementing [`CoordFloat`](./trait.CoordFloat.html)
+//! works. An index over `f32` shapes takes about half the memory.
+//! Distances in result rows are `f64` either way. Keyed indexes and
+//! saving an index with the `persist` feature only support `f64`.
 //!
 //! So what kind of sequences can you use?
 //! * slices: `&[T]`,
@@ -52,9 +120,17 @@
 //! * all coordinate values must be finite
 //! * `LineStrings` must have at least two points
 //! * `Polygon` exteriors must have at least three points
+//! * multi-geometries must have at least one member
 //!
 //! Input that doesn't meet these conditions will return an [error](./enum.Error.html).
 //!
+//! If you'd rather skip bad rows than fail the whole batch, convert
+//! your `&[Geometry<f64>]` with
+//! [`SplitGeoSeq::lenient_from`](./struct.SplitGeoSeq.html#method.lenient_from)
+//! first: it returns the geometries it could use along with an error
+//! for each one it skipped, and you can pass the `SplitGeoSeq`
+//! anywhere a sequence is expected.
+//!
 //! ## Outputs
 //!
 //! [`SpatialIndex::spatial_join`](./struct.SpatialIndex.html#method.spatial_join) returns `Result<impl
@@ -67,12 +143,16 @@
 //! [`SJoinGeoRow`](./struct.SJoinGeoRow.html) differs from
 //! [`SJoinRow`](./struct.SJoinRow.html) only in the addition of `big`
 //! and `small`
-//! [`Geometry`](https://docs.rs/geo/latest/geo/enum.Geometry.html)
+//! [`GeometryCow`](./enum.GeometryCow.html)
 //! fields so you can work directly with the source geometries without
-//! having to keep the original sequences around. This convenience
-//! comes at the cost of cloning the source geometries which can be
-//! expensive for geometries that use heap storage like `LineString`
-//! and `Polygon`.
+//! having to keep the original sequences around. Sequences converted
+//! from a slice or `&Vec` borrow heap-backed geometries like
+//! `LineString` and `Polygon` rather than cloning them, both for the
+//! index and for `big`, and `big` in each row borrows from your data
+//! too. The small geometry in each row borrows from the index, so a
+//! polygon that matches many big geometries is never cloned for them.
+//! `GeometryCow::into_owned` (or `Geometry::from`) turns a row's
+//! geometry into a plain `Geometry` if you need one.
 //!
 //! In a similar manner, [`SpatialIndex::proximity_map`](./struct.SpatialIndex.html#method.proximity_map) and
 //! [`SpatialIndex::proximity_map_with_geos`](./struct.SpatialIndex.html#method.proximity_map) offer
@@ -81,6 +161,82 @@
 //! return types. These differ from their `SJoin` counterparts only in
 //! the addition of a `distance` field.
 //!
+//! Rows come out in no particular order: grouped by geometry type for
+//! a `SpatialIndex`, and however the threads finish for a
+//! `ParSpatialIndex`. If you need them ordered by `big_index` and then
+//! `small_index`,
+//! [`spatial_join_sorted`](./struct.SpatialIndex.html#method.spatial_join_sorted)
+//! and [`proximity_map_sorted`](./struct.SpatialIndex.html#method.proximity_map_sorted)
+//! produce them that way while only sorting the rows for one big
+//! shape at a time. The parallel versions keep that order for
+//! `collect` and other order-preserving consumers.
+//!
+//! For a left join, where every element of `big` shows up whether it
+//! matched or not,
+//! [`spatial_join_left`](./struct.SpatialIndex.html#method.spatial_join_left)
+//! and [`proximity_map_left`](./struct.SpatialIndex.html#method.proximity_map_left)
+//! return [`SJoinLeftRow`](./struct.SJoinLeftRow.html) and
+//! [`ProxMapLeftRow`](./struct.ProxMapLeftRow.html) rows, whose
+//! `small_index` is `None` for a big shape that matched nothing.
+//!
+//! If your shapes come with IDs of their own, [`Config::serial_keyed`](./struct.Config.html#method.serial_keyed)
+//! and `Config::parallel_keyed` build an index from `(key, shape)`
+//! pairs. Joins against it take `(key, shape)` pairs for `big` too
+//! and return [`SJoinKeyRow`](./struct.SJoinKeyRow.html) and
+//! [`ProxMapKeyRow`](./struct.ProxMapKeyRow.html) rows carrying
+//! the keys, so you don't need to keep vectors of IDs around to
+//! decode positions. Their `spatial_join_stream` and
+//! `proximity_map_stream` pull `(key, shape)` pairs from an iterator
+//! one at a time, like the unkeyed ones.
+//!
+//! ## Aggregating
+//!
+//! If all you want from a join is a number per small shape, like how
+//! many points fall in each polygon,
+//! [`count_by_small`](./struct.SpatialIndex.html#method.count_by_small)
+//! returns a `Vec` indexed by `small_index` without handing you any
+//! rows, and
+//! [`fold_by_small`](./struct.SpatialIndex.html#method.fold_by_small)
+//! folds the rows into an accumulator of your choosing per small
+//! shape, for sums and the like. The `ParSpatialIndex` versions fold
+//! on every thread and merge the results.
+//!
+//! To find which elements of `big` match any small shape at all, or
+//! none, [`big_semi_join`](./struct.SpatialIndex.html#method.big_semi_join)
+//! and [`big_anti_join`](./struct.SpatialIndex.html#method.big_anti_join)
+//! return their indexes. They stop looking at a big shape once it
+//! has matched, so they can be much quicker than a full join against
+//! crowded small shapes.
+//! [`small_semi_join`](./struct.SpatialIndex.html#method.small_semi_join)
+//! does the same for the small shapes that match any element of
+//! `big`, skipping a small shape once it has matched.
+//!
+//! ## Looking Up One Shape
+//!
+//! When shapes arrive one at a time, say a point per request to a
+//! geofencing service,
+//! [`query`](./struct.SpatialIndex.html#method.query) takes a
+//! single `Geometry` and an `Interaction` and returns the matching
+//! `small_index`es, and
+//! [`within_distance`](./struct.SpatialIndex.html#method.within_distance)
+//! returns each small shape within a given distance along with how far
+//! away it is. Neither converts the shape into a `SplitGeoSeq` first,
+//! and outside of `Config::geographic` the R-tree search behind them
+//! doesn't allocate, so they're cheap enough to call per request.
+//! Checking the shapes it turns up can still allocate, for the
+//! interactions that build an intersection matrix and for some
+//! distances; [`query`](./struct.SpatialIndex.html#method.query) lists them.
+//!
+//! ## Updating an Index
+//!
+//! You don't have to rebuild a `SpatialIndex` when a few of the small
+//! shapes change: [`insert`](./struct.SpatialIndex.html#method.insert)
+//! adds a shape and returns the index results will use for it, and
+//! [`remove`](./struct.SpatialIndex.html#method.remove)
+//! takes one out. Every other shape keeps its index, and indexes are
+//! never reused. An index that has seen a lot of changes may answer
+//! queries a little slower than a freshly built one.
+//!
 //! ## Examples
 //!
 //! Here's the simplest thing: let's verify that a point intersects itself.
@@ -158,6 +314,35 @@
 //!     that [`serial`](./struct.Config.html#method.serial) returns
 //!     except that all the methods return `Result<impl
 //!     ParallelIterator>` instead of `Result<impl Iterator>`.
+//! - `serde`
+//!   - This adds a dependency on
+//!     [`serde`](https://crates.io/crates/serde) and derives
+//!     `Serialize` and `Deserialize` for the result rows (geometries
+//!     included), [`Config`](./struct.Config.html),
+//!     [`Interaction`](./enum.Interaction.html) and the other
+//!     public types except `Error`, so you can ship join results and
+//!     job configs around in whatever format you like. A
+//!     `RelatePattern` is written as its pattern string, and a
+//!     `SpatialIndex` is checked as it's deserialized, so bad data
+//!     fails to deserialize rather than panicking in a join later.
+//! - `persist`
+//!   - This enables `serde` and provides
+//!     `save`, `load`, `write_to`, `read_from` and `from_bytes` methods
+//!     on [`SpatialIndex`](./struct.SpatialIndex.html) and
+//!     [`ParSpatialIndex`](./struct.ParSpatialIndex.html), so you can
+//!     build an index once and load it later without bulk loading the
+//!     R-trees again. Saved indexes start with a format version, and
+//!     loading one written by an incompatible version of this crate
+//!     fails with `Error::BadIndexFile`, as does loading a damaged
+//!     file.
+//! - `mmap`
+//!   - This enables `persist` and adds a dependency on
+//!     [`memmap2`](https://crates.io/crates/memmap2) for a
+//!     `load_mmap` method that reads a saved index out of a
+//!     memory-mapped file. It's `unsafe`, since the file mustn't
+//!     change while it's mapped, and it only saves copying the file's
+//!     bytes: the R-trees and geometries are rebuilt in ordinary memory
+//!     as they're read, since the types they're made of own their memory.
 //!
 //! ## Geographic
 //!
@@ -185,6 +370,26 @@
 //! equator, you might be able to find a `max_distance` value that
 //! works, but that's pretty unlikely.
 //!
+//! Instead, proximity maps and nearest-neighbour joins can measure
+//! distances on the Earth: build the index with
+//! `Config::new().distance_metric(DistanceMetric::Geodesic)` and
+//! coordinates are read as longitude and latitude in degrees,
+//! `max_distance` is in meters, and `ProxMapRow::distance` reports
+//! meters on the WGS84 ellipsoid (computed with Vincenty's
+//! formula). Edges are treated as great-circle arcs when finding the
+//! closest points. Whether two shapes intersect (distance 0) is
+//! still decided in the plane.
+//!
+//! Setting `Config::geographic(true)` deals with the anti-meridian
+//! (longitude=±180 degrees), for spatial joins and proximity maps
+//! alike. Any geometry with an edge spanning more than 180 degrees of
+//! longitude is taken to cross the anti-meridian, and the index lines
+//! geometries up across it, so a point at 179.9 is close to one at
+//! -179.9. Geometries that cross get stored and returned with their
+//! western part shifted east by 360 degrees. Rows from the
+//! `_with_geos` methods carry small geometries as the index stores
+//! them, even when they matched after being moved a whole turn.
+//!
 //! ## Performance
 //!
 //! * You'll notice that our API specifies geometry sequences in terms
@@ -195,9 +400,12 @@
 //!   if you index the smaller sequence.
 //! * Because the spatial-join and proximity-map operations are
 //!   implemented as iterators, you can process very large data-sets
-//!   with low memory usage. But you do need to keep both the `small`
-//!   and `large` geometry sequence in memory, in addition to rtrees
-//!   for the `small` sequence. Note that in some cases, specifically
+//!   with low memory usage. The ordinary methods convert the whole
+//!   `large` sequence before producing any rows, but
+//!   `spatial_join_stream` and `proximity_map_stream` take any iterator
+//!   of geometries and convert them one at a time, so only the `small`
+//!   sequence and its rtrees need to fit in memory. Bad geometries
+//!   come out as `Err` rows in their place. Note that in some cases, specifically
 //!   whenever we're processing a heap-bound element of the `large`
 //!   sequence (i.e., Polygons or LineStrings), we will buffer all
 //!   matching result records for each such `large` geometry.
@@ -206,7 +414,8 @@
 //!   `max_distance` effectively buffers `small` geometries in the
 //!   r-trees. You'll still get the correct answer, but it might take
 //!   longer. The larger the `max_distance` value, the longer it will
-//!   take.
+//!   take. `Interaction::WithinDistance` is the exception: without
+//!   that buffer, it grows every big envelope instead.
 //!
 //! ## License
 //!
@@ -228,35 +437,100 @@
 
 use rstar::RTree;
 
+mod coord;
+pub use coord::CoordFloat;
+
 mod structs;
 pub use structs::*;
 
 mod validation;
 
+mod antimeridian;
+
 mod conv;
 
+mod de9im;
+pub use de9im::IntersectionMatrix;
+
+mod geodesic;
+
+mod knn;
+
 mod relates;
 
 mod rtrees;
 use rtrees::FakeRegion;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "serialization::SpatialIndexFields<'a, C>")
+)]
 #[derive(Debug)]
-pub struct SpatialIndex {
-    small: SplitGeoSeq,
-    point_tree: RTree<FakeRegion>,
-    line_tree: RTree<FakeRegion>,
-    poly_tree: RTree<FakeRegion>,
-    ls_tree: RTree<FakeRegion>,
-    rect_tree: RTree<FakeRegion>,
-    tri_tree: RTree<FakeRegion>,
+pub struct SpatialIndex<'a, C: CoordFloat = f64> {
+    small: SplitGeoSeq<'a, C>,
+    point_tree: RTree<FakeRegion<C>>,
+    line_tree: RTree<FakeRegion<C>>,
+    poly_tree: RTree<FakeRegion<C>>,
+    ls_tree: RTree<FakeRegion<C>>,
+    rect_tree: RTree<FakeRegion<C>>,
+    tri_tree: RTree<FakeRegion<C>>,
+    multi_point_tree: RTree<FakeRegion<C>>,
+    multi_ls_tree: RTree<FakeRegion<C>>,
+    multi_poly_tree: RTree<FakeRegion<C>>,
+    // Each small geometry's own `max_distance` by index, or empty when
+    // they all use the config's.
+    distances: Vec<f64>,
+    // The index `insert` hands out next; never reused, even after a
+    // `remove`.
+    next_index: usize,
     config: Config,
 }
 
 #[cfg(feature = "parallel")]
-pub struct ParSpatialIndex(SpatialIndex);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParSpatialIndex<'a, C: CoordFloat = f64>(SpatialIndex<'a, C>);
+
+/// A [`SpatialIndex`](struct.SpatialIndex.html) over `(key,
+/// geometry)` pairs, built with
+/// [`Config::serial_keyed`](struct.Config.html#method.serial_keyed).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "serialization::KeyedSpatialIndexFields<K>")
+)]
+pub struct KeyedSpatialIndex<K> {
+    index: SpatialIndex<'static>,
+    keys: Vec<K>,
+}
+
+#[cfg(feature = "parallel")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "serialization::ParKeyedSpatialIndexFields<K>")
+)]
+pub struct ParKeyedSpatialIndex<K> {
+    index: ParSpatialIndex<'static>,
+    keys: Vec<K>,
+}
 
 mod index;
 
+mod keyed;
+
+mod update;
+
+mod aggregate;
+
+#[cfg(feature = "persist")]
+mod binary;
+#[cfg(feature = "persist")]
+mod persist;
+
+#[cfg(feature = "serde")]
+mod serialization;
+
 #[cfg(test)]
 mod naive;
 
@@ -276,14 +550,31 @@ mod tests {
     use super::*;
     use index::*;
 
-    pub fn test_prox_map<Small, Big, E1, E2>(
+    // Geo rows only compare their indexes, but the expected rows still
+    // need geometries.
+    fn cow(geo: geo::Geometry<f64>) -> GeometryCow<'static> {
+        match geo {
+            geo::Geometry::Point(pt) => pt.into(),
+            geo::Geometry::Line(ln) => ln.into(),
+            geo::Geometry::Polygon(poly) => poly.into(),
+            geo::Geometry::LineString(ls) => ls.into(),
+            geo::Geometry::Rect(r) => r.into(),
+            geo::Geometry::Triangle(tri) => tri.into(),
+            geo::Geometry::MultiPoint(mp) => mp.into(),
+            geo::Geometry::MultiLineString(mls) => mls.into(),
+            geo::Geometry::MultiPolygon(mpoly) => mpoly.into(),
+            geo::Geometry::GeometryCollection(_) => unreachable!(),
+        }
+    }
+
+    pub fn test_prox_map<'a, Small, Big, E1, E2>(
         config: Config,
         small: Small,
         big: Big,
         expected: &Vec<ProxMapRow>,
     ) where
-        Small: TryInto<SplitGeoSeq, Error = E1> + Clone,
-        Big: TryInto<SplitGeoSeq, Error = E2> + Clone,
+        Small: TryInto<SplitGeoSeq<'a>, Error = E1> + Clone,
+        Big: TryInto<SplitGeoSeq<'a>, Error = E2> + Clone,
         E1: std::any::Any + std::fmt::Debug,
         E2: std::any::Any + std::fmt::Debug,
     {
@@ -291,21 +582,23 @@ mod tests {
         let small_geoms = sgs_try_into(small.clone())
             .expect("small conversion")
             .to_vec();
-        let big_geoms = sgs_try_into(big.clone()).expect("big conversion").to_vec();
+        // Converting up front lets the index borrow `big` for less
+        // than 'a.
+        let big = sgs_try_into(big).expect("big conversion");
+        let big_geoms = big.clone().to_vec();
         let expected_geoms: Vec<_> = expected
             .iter()
             .map(|pmr| ProxMapGeoRow {
                 big_index: pmr.big_index,
                 small_index: pmr.small_index,
                 distance: pmr.distance,
-                big: big_geoms[pmr.big_index].clone(),
-                small: small_geoms[pmr.small_index].clone(),
+                big: cow(big_geoms[pmr.big_index].clone()),
+                small: cow(small_geoms[pmr.small_index].clone()),
             })
             .collect();
         let _expected_geoms2 = expected_geoms.clone();
 
         let si = config
-            .clone()
             .serial(small.clone())
             .expect("construction succeeded");
         let mut actual = si.proximity_map(big.clone()).unwrap().collect::<Vec<_>>();
@@ -321,37 +614,35 @@ mod tests {
     }
 
     #[cfg(feature = "parallel")]
-    pub fn test_par_prox_map<Small, Big, E1, E2>(
+    pub fn test_par_prox_map<'a, Small, Big, E1, E2>(
         config: Config,
         small: Small,
         big: Big,
         expected: &Vec<ProxMapRow>,
     ) where
-        Small: TryInto<Par<SplitGeoSeq>, Error = E1> + Clone,
-        Big: TryInto<Par<SplitGeoSeq>, Error = E2> + Clone,
+        Small: TryInto<Par<SplitGeoSeq<'a>>, Error = E1> + Clone,
+        Big: TryInto<Par<SplitGeoSeq<'a>>, Error = E2> + Clone,
         E1: std::any::Any + std::fmt::Debug,
         E2: std::any::Any + std::fmt::Debug,
     {
         let small_geoms = par_sgs_try_into(small.clone())
             .expect("small conversion")
             .to_vec();
-        let big_geoms = par_sgs_try_into(big.clone())
-            .expect("big conversion")
-            .to_vec();
+        let big = par_sgs_try_into(big).expect("big conversion");
+        let big_geoms = big.clone().to_vec();
         let expected_geoms: Vec<_> = expected
             .iter()
             .map(|pmr| ProxMapGeoRow {
                 big_index: pmr.big_index,
                 small_index: pmr.small_index,
                 distance: pmr.distance,
-                big: big_geoms[pmr.big_index].clone(),
-                small: small_geoms[pmr.small_index].clone(),
+                big: cow(big_geoms[pmr.big_index].clone()),
+                small: cow(small_geoms[pmr.small_index].clone()),
             })
             .collect();
         let _expected_geoms2 = expected_geoms.clone();
 
         let si = config
-            .clone()
             .parallel(small.clone())
             .expect("construction succeeded");
         let mut actual = si.proximity_map(big.clone()).unwrap().collect::<Vec<_>>();
@@ -366,35 +657,37 @@ mod tests {
         assert_eq!(actual_geoms, expected_geoms);
     }
 
-    pub fn test_spatial_join<Small, Big, E1, E2>(
+    pub fn test_spatial_join<'a, Small, Big, E1, E2>(
         config: Config,
         small: Small,
         big: Big,
         interaction: Interaction,
         expected: &Vec<SJoinRow>,
     ) where
-        Small: TryInto<SplitGeoSeq, Error = E1> + Clone,
-        Big: TryInto<SplitGeoSeq, Error = E2> + Clone,
+        Small: TryInto<SplitGeoSeq<'a>, Error = E1> + Clone,
+        Big: TryInto<SplitGeoSeq<'a>, Error = E2> + Clone,
         E1: std::any::Any + std::fmt::Debug,
         E2: std::any::Any + std::fmt::Debug,
     {
         let small_geoms = sgs_try_into(small.clone())
             .expect("small conversion")
             .to_vec();
-        let big_geoms = sgs_try_into(big.clone()).expect("big conversion").to_vec();
+        // Converting up front lets the index borrow `big` for less
+        // than 'a.
+        let big = sgs_try_into(big).expect("big conversion");
+        let big_geoms = big.clone().to_vec();
         let expected_geoms: Vec<_> = expected
             .iter()
             .map(|sjr| SJoinGeoRow {
                 big_index: sjr.big_index,
                 small_index: sjr.small_index,
-                big: big_geoms[sjr.big_index].clone(),
-                small: small_geoms[sjr.small_index].clone(),
+                big: cow(big_geoms[sjr.big_index].clone()),
+                small: cow(small_geoms[sjr.small_index].clone()),
             })
             .collect();
         let _expected_geoms2 = expected_geoms.clone();
 
         let si = config
-            .clone()
             .serial(small.clone())
             .expect("construction succeeded");
         let mut actual = si
@@ -413,37 +706,35 @@ mod tests {
     }
 
     #[cfg(feature = "parallel")]
-    pub fn test_par_spatial_join<Small, Big, E1, E2>(
+    pub fn test_par_spatial_join<'a, Small, Big, E1, E2>(
         config: Config,
         small: Small,
         big: Big,
         interaction: Interaction,
         expected: &Vec<SJoinRow>,
     ) where
-        Small: TryInto<Par<SplitGeoSeq>, Error = E1> + Clone,
-        Big: TryInto<Par<SplitGeoSeq>, Error = E2> + Clone,
+        Small: TryInto<Par<SplitGeoSeq<'a>>, Error = E1> + Clone,
+        Big: TryInto<Par<SplitGeoSeq<'a>>, Error = E2> + Clone,
         E1: std::any::Any + std::fmt::Debug,
         E2: std::any::Any + std::fmt::Debug,
     {
         let small_geoms = par_sgs_try_into(small.clone())
             .expect("small conversion")
             .to_vec();
-        let big_geoms = par_sgs_try_into(big.clone())
-            .expect("big conversion")
-            .to_vec();
+        let big = par_sgs_try_into(big).expect("big conversion");
+        let big_geoms = big.clone().to_vec();
         let expected_geoms: Vec<_> = expected
             .iter()
             .map(|sjr| SJoinGeoRow {
                 big_index: sjr.big_index,
                 small_index: sjr.small_index,
-                big: big_geoms[sjr.big_index].clone(),
-                small: small_geoms[sjr.small_index].clone(),
+                big: cow(big_geoms[sjr.big_index].clone()),
+                small: cow(small_geoms[sjr.small_index].clone()),
             })
             .collect();
         let _expected_geoms2 = expected_geoms.clone();
 
         let si = config
-            .clone()
             .parallel(small.clone())
             .expect("construction succeeded");
         let mut actual = si
@@ -461,6 +752,73 @@ mod tests {
         assert_eq!(actual_geoms, expected_geoms);
     }
 
+    // Checks rows against `expected` and that each big geometry's
+    // rows come out nearest first.
+    fn check_nearest_neighbors(actual: Vec<ProxMapRow>, expected: &[ProxMapRow]) {
+        let mut by_big = std::collections::HashMap::new();
+        for row in actual.iter() {
+            if let Some(last) = by_big.insert(row.big_index, row.distance) {
+                assert!(last <= row.distance);
+            }
+        }
+        let mut actual = actual;
+        actual.sort();
+        assert_eq!(actual, expected);
+    }
+
+    pub fn test_nearest_neighbors<'a, Small, Big, E1, E2>(
+        config: Config,
+        small: Small,
+        big: Big,
+        k: usize,
+        max_distance: Option<f64>,
+        expected: &[ProxMapRow],
+    ) where
+        Small: TryInto<SplitGeoSeq<'a>, Error = E1> + Clone,
+        Big: TryInto<SplitGeoSeq<'a>, Error = E2> + Clone,
+        E1: std::any::Any + std::fmt::Debug,
+        E2: std::any::Any + std::fmt::Debug,
+    {
+        let si = config.serial(small).expect("construction succeeded");
+        let big = sgs_try_into(big).expect("big conversion");
+        let actual: Vec<_> = match max_distance {
+            Some(max_distance) => si
+                .nearest_neighbors_within(big, k, max_distance)
+                .unwrap()
+                .collect(),
+            None => si.nearest_neighbors(big, k).unwrap().collect(),
+        };
+        check_nearest_neighbors(actual, expected);
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn test_par_nearest_neighbors<'a, Small, Big, E1, E2>(
+        config: Config,
+        small: Small,
+        big: Big,
+        k: usize,
+        max_distance: Option<f64>,
+        expected: &[ProxMapRow],
+    ) where
+        Small: TryInto<Par<SplitGeoSeq<'a>>, Error = E1> + Clone,
+        Big: TryInto<Par<SplitGeoSeq<'a>>, Error = E2> + Clone,
+        E1: std::any::Any + std::fmt::Debug,
+        E2: std::any::Any + std::fmt::Debug,
+    {
+        let si = config.parallel(small).expect("construction succeeded");
+        let big = par_sgs_try_into(big).expect("big conversion");
+        // The parallel iterator interleaves big geometries, but each
+        // one's rows still arrive in order.
+        let actual: Vec<_> = match max_distance {
+            Some(max_distance) => si
+                .nearest_neighbors_within(big, k, max_distance)
+                .unwrap()
+                .collect(),
+            None => si.nearest_neighbors(big, k).unwrap().collect(),
+        };
+        check_nearest_neighbors(actual, expected);
+    }
+
     #[test]
     fn simple_index_self() {
         let config = Config::new().max_distance(4.);
@@ -523,5 +881,1395 @@ mod tests {
         #[cfg(feature = "parallel")]
         test_par_prox_map(config, small, big, &expected);
     }
+
+    #[test]
+    fn distance_band_prox_map() {
+        let config = Config::new().max_distance(3.).min_distance(1.);
+        let small = vec![
+            Point::new(1., 1.),
+            Point::new(4.5, 1.),
+            Point::new(6., 1.),
+            Point::new(9., 1.),
+        ];
+        let big = vec![geo::Rect::new((0., 0.), (4., 2.))];
+        // The first point is inside and the second too close; the
+        // last is too far.
+        let expected = vec![ProxMapRow {
+            big_index: 0,
+            small_index: 2,
+            distance: 2.,
+        }];
+        test_prox_map(config, small.clone(), big.clone(), &expected);
+        #[cfg(feature = "parallel")]
+        test_par_prox_map(config, small.clone(), big.clone(), &expected);
+
+        assert_eq!(
+            Config::new().min_distance(-1.).serial(&small).err(),
+            Some(Error::BadMinDistance(-1.))
+        );
+        assert_eq!(
+            Config::new().max_distance(1.).min_distance(2.).validate(),
+            Some(Error::BadMinDistance(2.))
+        );
+    }
+
+    #[test]
+    fn within_distance_join() {
+        let small = vec![Point::new(1., 1.), Point::new(6., 1.), Point::new(9., 1.)];
+        let big = vec![geo::Rect::new((0., 0.), (4., 2.))];
+        let row = |small_index| SJoinRow {
+            big_index: 0,
+            small_index,
+        };
+
+        // Within the R-tree buffer, beyond it, and nowhere at all.
+        let config = Config::new().max_distance(3.);
+        let interaction = Interaction::WithinDistance(2.);
+        test_spatial_join(config, &small, &big, interaction, &vec![row(0), row(1)]);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &big, interaction, &vec![row(0), row(1)]);
+        let interaction = Interaction::WithinDistance(5.);
+        let expected = vec![row(0), row(1), row(2)];
+        test_spatial_join(config, &small, &big, interaction, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &big, interaction, &expected);
+
+        let si = Config::new().serial(&small).unwrap();
+        let inside = Point::new(1., 1.).into();
+        let found: Vec<_> = si
+            .query(&inside, Interaction::WithinDistance(0.))
+            .unwrap()
+            .collect();
+        assert_eq!(found, vec![0]);
+
+        // A distance the R-trees can't search with is an error, where
+        // there's a Result to put it in.
+        let negative = Interaction::WithinDistance(-1.);
+        let bad = Some(Error::BadMaxDistance(-1.));
+        assert_eq!(si.spatial_join(&big, negative).err(), bad);
+        assert_eq!(si.query(&inside, negative).err(), bad);
+        let streamed: Vec<_> = si.spatial_join_stream(big.clone(), negative).collect();
+        assert_eq!(streamed, vec![Err(Error::BadMaxDistance(-1.))]);
+        assert_eq!(si.self_join(negative).count(), 0);
+        let nan = Interaction::WithinDistance(f64::NAN);
+        assert!(matches!(
+            si.spatial_join(&big, nan).err(),
+            Some(Error::BadMaxDistance(distance)) if distance.is_nan()
+        ));
+        #[cfg(feature = "parallel")]
+        {
+            let pi = Config::new().parallel(&small).unwrap();
+            assert_eq!(pi.spatial_join(&big, negative).err(), bad);
+            assert_eq!(pi.query(&inside, negative).err(), bad);
+        }
+
+        // Across the antimeridian, with and without an R-tree buffer.
+        let small: Vec<geo::Geometry<f64>> = vec![
+            Point::new(-179.9, 0.).into(),
+            geo::LineString::from(vec![(180., 5.), (180., 6.)]).into(),
+        ];
+        let big: Vec<geo::Geometry<f64>> = vec![
+            Point::new(179.9, 0.).into(),
+            geo::Line::new((179.9, 5.), (180.1, 5.)).into(),
+        ];
+        let expected = vec![
+            SJoinRow {
+                big_index: 0,
+                small_index: 0,
+            },
+            SJoinRow {
+                big_index: 1,
+                small_index: 1,
+            },
+        ];
+        for max_distance in &[0., 2.] {
+            let config = Config::new().max_distance(*max_distance).geographic(true);
+            let interaction = Interaction::WithinDistance(1.);
+            test_spatial_join(config, &small, &big, interaction, &expected);
+            #[cfg(feature = "parallel")]
+            test_par_spatial_join(config, &small, &big, interaction, &expected);
+        }
+    }
+
+    #[test]
+    fn overlap_join() {
+        let holed = geo::Polygon::new(
+            vec![(0., 0.), (0., 4.), (4., 4.), (4., 0.)].into(),
+            vec![vec![(1.5, 1.5), (2.5, 1.5), (2.5, 2.5), (1.5, 2.5)].into()],
+        );
+        let small: Vec<geo::Geometry<f64>> = vec![
+            geo::Rect::new((0., 0.), (2., 2.)).into(),
+            holed.into(),
+            geo::LineString::from(vec![(0., 2.), (4., 2.), (4., 5.)]).into(),
+            geo::Rect::new((3., 0.), (4., 1.)).into(),
+            Point::new(9., 9.).into(),
+        ];
+        let big = vec![geo::Rect::new((1., 1.), (3., 3.))];
+        let si = Config::new().serial(&small).unwrap();
+        let mut rows: Vec<_> = si.spatial_join_with_overlap(&big).unwrap().collect();
+        rows.sort();
+
+        let shares: Vec<_> = rows
+            .iter()
+            .map(|row| (row.small_index, row.area, row.length))
+            .collect();
+        // The last rect only touches `big` at a corner.
+        assert_eq!(
+            shares,
+            vec![(0, 1., 0.), (1, 3., 0.), (2, 0., 2.), (3, 0., 0.)]
+        );
+        let clipped = geo::MultiLineString(vec![vec![(1., 2.), (3., 2.)].into()]);
+        assert_eq!(rows[2].lines, clipped);
+        assert!(rows[0].lines.0.is_empty());
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().parallel(&small).unwrap();
+            let mut par_rows: Vec<_> = si.spatial_join_with_overlap(&big).unwrap().collect();
+            par_rows.sort();
+            assert_eq!(par_rows, rows);
+        }
+    }
+
+    #[test]
+    fn geodesic_prox_map() {
+        // One degree of longitude along the equator is 111,319.49 m on
+        // the WGS84 ellipsoid; one degree of latitude is less.
+        let config = Config::new()
+            .max_distance(111_000.)
+            .distance_metric(DistanceMetric::Geodesic);
+        let small = vec![Point::new(1., 0.), Point::new(0., 1.)];
+        let big = vec![Point::new(0., 0.)];
+        let si = config.serial(&small).unwrap();
+        let rows: Vec<_> = si.proximity_map(&big).unwrap().collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].small_index, 1);
+        assert!((rows[0].distance - 110_574.4).abs() < 1.);
+
+        let rows: Vec<_> = si.nearest_neighbors(&big, 2).unwrap().collect();
+        assert_eq!(rows.len(), 2);
+        assert!((rows[1].distance - 111_319.5).abs() < 1.);
+    }
+
+    #[test]
+    fn antimeridian() {
+        let small = vec![Point::new(179.75, 0.25), Point::new(-179.75, 0.75)];
+        let big = vec![
+            geo::Geometry::Line(geo::Line::new((179.5, 0.), (-179.5, 1.))),
+            geo::Geometry::Point(Point::new(179.75, 0.)),
+        ];
+        let config = Config::new().max_distance(1.).geographic(true);
+        let si = config.serial(&small).unwrap();
+
+        let mut rows: Vec<_> = si
+            .spatial_join(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        rows.sort();
+        let expected = vec![
+            SJoinRow {
+                big_index: 0,
+                small_index: 0,
+            },
+            SJoinRow {
+                big_index: 0,
+                small_index: 1,
+            },
+        ];
+        assert_eq!(rows, expected);
+
+        // The second point only meets the line a turn east, but rows
+        // report it as stored.
+        let mut rows: Vec<_> = si
+            .spatial_join_with_geos(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        rows.sort();
+        assert_eq!(rows[1].small, Point::new(-179.75, 0.75).into());
+
+        let mut rows: Vec<_> = si.proximity_map(&big).unwrap().collect();
+        rows.sort();
+        assert_eq!(rows.len(), 4);
+        assert_eq!((rows[3].big_index, rows[3].small_index), (1, 1));
+        assert!((rows[3].distance - 0.5f64.hypot(0.75)).abs() < 1e-9);
+
+        let rows: Vec<_> = si.nearest_neighbors(&big[1..], 2).unwrap().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].small_index, 1);
+        assert!((rows[1].distance - 0.5f64.hypot(0.75)).abs() < 1e-9);
+
+        // Without the flag the two sides of the Pacific are far apart.
+        let si = Config::new().max_distance(1.).serial(&small).unwrap();
+        assert_eq!(si.proximity_map(&big[1..]).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn self_join() {
+        let small = vec![
+            geo::Rect::new((0., 0.), (2., 2.)),
+            geo::Rect::new((1., 1.), (3., 3.)),
+            geo::Rect::new((1.5, 1.5), (1.75, 1.75)),
+            geo::Rect::new((10., 10.), (11., 11.)),
+        ];
+        let si = Config::new().max_distance(10.).serial(&small).unwrap();
+        let pairs = |rows: Vec<SJoinRow>| -> Vec<_> {
+            let mut pairs: Vec<_> = rows
+                .into_iter()
+                .map(|row| (row.small_index, row.big_index))
+                .collect();
+            pairs.sort();
+            pairs
+        };
+
+        let rows = si.self_join(Interaction::Intersects).collect();
+        assert_eq!(pairs(rows), vec![(0, 1), (0, 2), (1, 2)]);
+        // Order matters for Within, so both directions can show up.
+        let rows = si.self_join(Interaction::Within).collect();
+        assert_eq!(pairs(rows), vec![(2, 0), (2, 1)]);
+        let rows = si
+            .self_join(Interaction::Relate("T********".parse().unwrap()))
+            .collect();
+        assert_eq!(pairs(rows), vec![(0, 1), (0, 2), (1, 2)]);
+
+        let mut rows: Vec<_> = si.self_proximity_map().collect();
+        rows.sort();
+        let expected: Vec<_> = vec![(0, 1), (0, 2), (1, 2), (1, 3)];
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.small_index, row.big_index))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert!((rows[3].distance - 7f64.hypot(7.)).abs() < 1e-9);
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().max_distance(10.).parallel(&small).unwrap();
+            let rows = si.self_join(Interaction::Intersects).collect();
+            assert_eq!(pairs(rows), vec![(0, 1), (0, 2), (1, 2)]);
+            assert_eq!(si.self_proximity_map().count(), 4);
+        }
+    }
+
+    #[test]
+    fn semi_and_anti_joins() {
+        let small = vec![
+            geo::Rect::new((0., 0.), (2., 2.)),
+            geo::Rect::new((1., 1.), (3., 3.)),
+        ];
+        let big: Vec<geo::Geometry<f64>> = vec![
+            Point::new(1.5, 1.5).into(),
+            Point::new(20., 20.).into(),
+            geo::Polygon::new(vec![(0.5, 0.5), (0.6, 0.5), (0.5, 0.6)].into(), vec![]).into(),
+            geo::Line::new((10., 0.), (11., 0.)).into(),
+        ];
+        let sorted = |mut indexes: Vec<usize>| {
+            indexes.sort();
+            indexes
+        };
+
+        let si = Config::new().serial(&small).unwrap();
+        let semi = si.big_semi_join(&big, Interaction::Intersects).unwrap();
+        assert_eq!(sorted(semi.collect()), vec![0, 2]);
+        let anti = si.big_anti_join(&big, Interaction::Intersects).unwrap();
+        assert_eq!(sorted(anti.collect()), vec![1, 3]);
+        let semi = si.big_semi_join(&big, Interaction::Disjoint).unwrap();
+        assert_eq!(sorted(semi.collect()), vec![1, 2, 3]);
+        let anti = si.big_anti_join(&big, Interaction::Contains).unwrap();
+        assert_eq!(sorted(anti.collect()), vec![1, 3]);
+        let semi = si.small_semi_join(&big, Interaction::Intersects).unwrap();
+        assert_eq!(semi.collect::<Vec<_>>(), vec![0, 1]);
+        let semi = si
+            .small_semi_join(&big[2..], Interaction::Intersects)
+            .unwrap();
+        assert_eq!(semi.collect::<Vec<_>>(), vec![0]);
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().parallel(&small).unwrap();
+            let semi = si.big_semi_join(&big, Interaction::Intersects).unwrap();
+            assert_eq!(sorted(semi.collect()), vec![0, 2]);
+            let anti = si.big_anti_join(&big, Interaction::Intersects).unwrap();
+            assert_eq!(sorted(anti.collect()), vec![1, 3]);
+            let semi = si
+                .small_semi_join(&big[2..], Interaction::Intersects)
+                .unwrap();
+            assert_eq!(semi.collect::<Vec<_>>(), vec![0]);
+        }
+    }
+
+    #[test]
+    fn sorted_joins() {
+        let small = vec![
+            geo::Rect::new((0., 0.), (2., 2.)).into(),
+            Point::new(1., 1.).into(),
+            geo::Geometry::Rect(geo::Rect::new((1., 1.), (3., 3.))),
+        ];
+        let big: Vec<geo::Geometry<f64>> = vec![
+            geo::Polygon::new(vec![(0.5, 0.5), (1.5, 0.5), (0.5, 1.5)].into(), vec![]).into(),
+            Point::new(1.5, 1.5).into(),
+            geo::Line::new((0., 1.), (2., 1.)).into(),
+        ];
+        let expected: Vec<_> = [
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (1, 0),
+            (1, 2),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+        ]
+        .iter()
+        .map(|&(big_index, small_index)| SJoinRow {
+            big_index,
+            small_index,
+        })
+        .collect();
+
+        let si = Config::new().max_distance(0.5).serial(&small).unwrap();
+        let rows: Vec<_> = si
+            .spatial_join_sorted(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        assert_eq!(rows, expected);
+        let rows: Vec<_> = si.proximity_map_sorted(&big).unwrap().collect();
+        let mut unsorted: Vec<_> = si.proximity_map(&big).unwrap().collect();
+        unsorted.sort();
+        assert_eq!(rows, unsorted);
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().max_distance(0.5).parallel(&small).unwrap();
+            let rows: Vec<_> = si
+                .spatial_join_sorted(&big, Interaction::Intersects)
+                .unwrap()
+                .collect();
+            assert_eq!(rows, expected);
+            let rows: Vec<_> = si.proximity_map_sorted(&big).unwrap().collect();
+            assert_eq!(rows, unsorted);
+        }
+    }
+
+    #[test]
+    fn single_lookups() {
+        let small: Vec<geo::Geometry<f64>> = vec![
+            geo::Rect::new((0., 0.), (2., 2.)).into(),
+            geo::Rect::new((1., 1.), (3., 3.)).into(),
+            Point::new(5., 5.).into(),
+        ];
+        let si = Config::new().max_distance(1.).serial(&small).unwrap();
+
+        let inside = Point::new(1.5, 1.5).into();
+        let mut found: Vec<_> = si.query(&inside, Interaction::Contains).unwrap().collect();
+        found.sort();
+        assert_eq!(found, vec![0, 1]);
+        let found: Vec<_> = si.query(&inside, Interaction::Disjoint).unwrap().collect();
+        assert_eq!(found, vec![2]);
+
+        let line = geo::Line::new((0.5, 0.5), (0.5, 1.5)).into();
+        let found: Vec<_> = si.query(&line, Interaction::Within).unwrap().collect();
+        assert_eq!(found, Vec::<usize>::new());
+        let found: Vec<_> = si.query(&line, Interaction::CoveredBy).unwrap().collect();
+        assert_eq!(found, Vec::<usize>::new());
+        let found: Vec<_> = si.query(&line, Interaction::Intersects).unwrap().collect();
+        assert_eq!(found, vec![0]);
+
+        // Further than `max_distance`, and nearer.
+        let far = Point::new(5., 3.).into();
+        let mut found: Vec<_> = si.within_distance(&far, 2.).unwrap().collect();
+        found.sort_by_key(|(index, _)| *index);
+        assert_eq!(found, vec![(1, 2.), (2, 2.)]);
+        let found: Vec<_> = si.within_distance(&far, 0.5).unwrap().collect();
+        assert_eq!(found, vec![]);
+        assert_eq!(
+            si.within_distance(&far, -1.).err(),
+            Some(Error::BadMaxDistance(-1.))
+        );
+        assert!(matches!(
+            si.within_distance(&far, f64::NAN).err(),
+            Some(Error::BadMaxDistance(distance)) if distance.is_nan()
+        ));
+
+        let poly = geo::Polygon::new(vec![(4., 4.), (6., 4.), (5., 6.)].into(), vec![]).into();
+        let found: Vec<_> = si.query(&poly, Interaction::Contains).unwrap().collect();
+        assert_eq!(found, Vec::<usize>::new());
+        let found: Vec<_> = si.query(&poly, Interaction::Within).unwrap().collect();
+        assert_eq!(found, vec![2]);
+
+        let bad = Point::new(f64::NAN, 0.).into();
+        match si.query(&bad, Interaction::Intersects) {
+            Err(Error::BadCoordinateValue(0, geo::Geometry::Point(_))) => {}
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        let collection = geo::Geometry::GeometryCollection(geo::GeometryCollection(vec![inside]));
+        assert_eq!(
+            si.within_distance(&collection, 1.).err(),
+            Some(Error::UnsupportedGeometry(0, "GeometryCollection"))
+        );
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().max_distance(1.).parallel(&small).unwrap();
+            let found: Vec<_> = si.query(&poly, Interaction::Within).unwrap().collect();
+            assert_eq!(found, vec![2]);
+            let mut found: Vec<_> = si.within_distance(&far, 2.).unwrap().collect();
+            found.sort_by_key(|(index, _)| *index);
+            assert_eq!(found, vec![(1, 2.), (2, 2.)]);
+        }
+    }
+
+    #[test]
+    fn left_joins() {
+        let small = vec![
+            geo::Rect::new((0., 0.), (2., 2.)),
+            geo::Rect::new((1., 1.), (3., 3.)),
+        ];
+        let big: Vec<geo::Geometry<f64>> = vec![
+            Point::new(1.5, 1.5).into(),
+            Point::new(20., 20.).into(),
+            geo::Polygon::new(vec![(0.5, 0.5), (0.6, 0.5), (0.5, 0.6)].into(), vec![]).into(),
+            geo::Line::new((3.5, 1.5), (4., 1.5)).into(),
+        ];
+        let expected = vec![
+            SJoinLeftRow {
+                big_index: 0,
+                small_index: Some(0),
+            },
+            SJoinLeftRow {
+                big_index: 0,
+                small_index: Some(1),
+            },
+            SJoinLeftRow {
+                big_index: 1,
+                small_index: None,
+            },
+            SJoinLeftRow {
+                big_index: 2,
+                small_index: Some(0),
+            },
+            SJoinLeftRow {
+                big_index: 3,
+                small_index: None,
+            },
+        ];
+
+        let si = Config::new().max_distance(1.).serial(&small).unwrap();
+        let mut rows: Vec<_> = si
+            .spatial_join_left(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        rows.sort();
+        assert_eq!(rows, expected);
+
+        let mut rows: Vec<_> = si.proximity_map_left(&big).unwrap().collect();
+        rows.sort();
+        let expected_distances = vec![
+            (0, Some(0), Some(0.)),
+            (0, Some(1), Some(0.)),
+            (1, None, None),
+            (2, Some(0), Some(0.)),
+            (2, Some(1), Some(0.45f64.hypot(0.45))),
+            (3, Some(1), Some(0.5)),
+        ];
+        assert_eq!(rows.len(), expected_distances.len());
+        for (row, (big_index, small_index, distance)) in rows.iter().zip(expected_distances) {
+            assert_eq!((row.big_index, row.small_index), (big_index, small_index));
+            match (row.distance, distance) {
+                (Some(a), Some(b)) => assert!((a - b).abs() < 1e-9),
+                (a, b) => assert_eq!(a, b),
+            }
+        }
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().max_distance(1.).parallel(&small).unwrap();
+            let mut rows: Vec<_> = si
+                .spatial_join_left(&big, Interaction::Intersects)
+                .unwrap()
+                .collect();
+            rows.sort();
+            assert_eq!(rows, expected);
+            assert_eq!(si.proximity_map_left(&big).unwrap().count(), 6);
+        }
+    }
+
+    #[test]
+    fn aggregates() {
+        let small = vec![
+            geo::Rect::new((0., 0.), (2., 2.)),
+            geo::Rect::new((1., 1.), (3., 3.)),
+            geo::Rect::new((10., 10.), (11., 11.)),
+        ];
+        let big = vec![
+            Point::new(0.5, 0.5),
+            Point::new(1.5, 1.5),
+            Point::new(2.5, 2.5),
+            Point::new(20., 20.),
+        ];
+        let weights = [1., 10., 100., 1000.];
+
+        let mut si = Config::new().serial(&small).unwrap();
+        let counts = si.count_by_small(&big, Interaction::Intersects).unwrap();
+        assert_eq!(counts, vec![2, 2, 0]);
+        let sums = si
+            .fold_by_small(&big, Interaction::Intersects, 0., |sum, row| {
+                *sum += weights[row.big_index]
+            })
+            .unwrap();
+        assert_eq!(sums, vec![11., 110., 0.]);
+
+        // Removed geometries keep their slot.
+        si.remove(1);
+        let counts = si.count_by_small(&big, Interaction::Intersects).unwrap();
+        assert_eq!(counts, vec![2, 0, 0]);
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().parallel(&small).unwrap();
+            let counts = si.count_by_small(&big, Interaction::Intersects).unwrap();
+            assert_eq!(counts, vec![2, 2, 0]);
+            let sums = si
+                .fold_by_small(
+                    &big,
+                    Interaction::Intersects,
+                    0.,
+                    |sum, row| *sum += weights[row.big_index],
+                    |sum, other| *sum += other,
+                )
+                .unwrap();
+            assert_eq!(sums, vec![11., 110., 0.]);
+            let empty: Vec<Point<f64>> = vec![];
+            let counts = si.count_by_small(&empty, Interaction::Intersects).unwrap();
+            assert_eq!(counts, vec![0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn keyed_joins() {
+        let small = vec![
+            ("home", geo::Rect::new((0., 0.), (2., 2.))),
+            ("work", geo::Rect::new((10., 10.), (12., 12.))),
+        ];
+        let big = vec![(17u32, Point::new(1., 1.)), (42, Point::new(11., 14.))];
+        let si = Config::new()
+            .max_distance(3.)
+            .serial_keyed(small.clone())
+            .unwrap();
+
+        let rows: Vec<_> = si
+            .spatial_join(big.clone(), Interaction::Intersects)
+            .unwrap()
+            .collect();
+        assert_eq!(
+            rows,
+            vec![SJoinKeyRow {
+                big_key: 17,
+                small_key: "home"
+            }]
+        );
+
+        let mut rows: Vec<_> = si.proximity_map(big.clone()).unwrap().collect();
+        rows.sort_by_key(|row| row.big_key);
+        assert_eq!(
+            rows,
+            vec![
+                ProxMapKeyRow {
+                    big_key: 17,
+                    small_key: "home",
+                    distance: 0.
+                },
+                ProxMapKeyRow {
+                    big_key: 42,
+                    small_key: "work",
+                    distance: 2.
+                }
+            ]
+        );
+
+        let stream = big
+            .iter()
+            .map(|&(key, pt)| (key, pt))
+            .chain(std::iter::once((7, geo::Point::new(f64::NAN, 0.))));
+        let rows: Vec<_> = si
+            .proximity_map_stream(stream)
+            .map(|row| row.map(|row| (row.big_key, row.small_key)))
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(&rows[..2], &[Ok((17, "home")), Ok((42, "work"))]);
+        assert_eq!(rows[2].as_ref().err().and_then(Error::index), Some(2));
+
+        let rows: Vec<_> = si
+            .spatial_join_stream(big.clone(), Interaction::Intersects)
+            .collect();
+        assert_eq!(
+            rows,
+            vec![Ok(SJoinKeyRow {
+                big_key: 17,
+                small_key: "home"
+            })]
+        );
+
+        let bad = vec![("empty", geo::LineString::<f64>(vec![]))];
+        assert_eq!(
+            Config::new().serial_keyed(bad).err(),
+            Some(Error::LineStringTooSmall(0))
+        );
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new()
+                .max_distance(3.)
+                .parallel_keyed(small)
+                .unwrap();
+            let mut keys: Vec<_> = si
+                .proximity_map(big.clone())
+                .unwrap()
+                .map(|row| (row.big_key, row.small_key))
+                .collect();
+            keys.sort();
+            assert_eq!(keys, vec![(17, "home"), (42, "work")]);
+
+            let keys: Vec<_> = si
+                .spatial_join_stream(big, Interaction::Intersects)
+                .map(|row| row.map(|row| (row.big_key, row.small_key)))
+                .collect();
+            assert_eq!(keys, vec![Ok((17, "home"))]);
+        }
+    }
+
+    #[test]
+    fn streaming_joins() {
+        let small = vec![Point::new(1., 1.), Point::new(5., 5.)];
+        let si = Config::new().max_distance(1.5).serial(&small).unwrap();
+        // Nothing here ever gets collected into a sequence.
+        let big = || {
+            (0..4).map(|i| match i {
+                2 => geo::Geometry::LineString(vec![(0., 0.)].into()),
+                _ => geo::Geometry::Rect(geo::Rect::new(
+                    (i as f64, i as f64),
+                    (i as f64 + 1., i as f64 + 1.),
+                )),
+            })
+        };
+
+        let rows: Vec<_> = si
+            .spatial_join_stream(big(), Interaction::Intersects)
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                Ok(SJoinRow {
+                    big_index: 0,
+                    small_index: 0
+                }),
+                Ok(SJoinRow {
+                    big_index: 1,
+                    small_index: 0
+                }),
+                Err(Error::LineStringTooSmall(2)),
+            ]
+        );
+
+        let rows: Vec<_> = si.proximity_map_stream(big()).collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[2], Err(Error::LineStringTooSmall(2)));
+        assert_eq!(
+            rows[3].as_ref().map(|row| (row.big_index, row.small_index)),
+            Ok((3, 1))
+        );
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new().max_distance(1.5).parallel(&small).unwrap();
+            let mut rows: Vec<_> = si
+                .spatial_join_stream(big(), Interaction::Intersects)
+                .filter_map(Result::ok)
+                .collect();
+            rows.sort();
+            assert_eq!(rows.len(), 2);
+            assert_eq!(
+                si.proximity_map_stream(big())
+                    .filter(Result::is_err)
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let small = vec![Point::new(179.75, 0.25), Point::new(0., 0.)];
+        let big = vec![geo::Rect::new((179.5, 0.), (180., 1.))];
+        let config = Config::new().geographic(true);
+        let mut si = config.serial(&small).unwrap();
+        let pairs = |si: &SpatialIndex| -> Vec<_> {
+            let mut rows: Vec<_> = si
+                .spatial_join(&big, Interaction::Intersects)
+                .unwrap()
+                .map(|row| row.small_index)
+                .collect();
+            rows.sort();
+            rows
+        };
+        assert_eq!(pairs(&si), vec![0]);
+
+        let crossing = geo::Line::new((179., 0.5), (-179., 0.5));
+        assert_eq!(si.insert(crossing), Ok(2));
+        assert_eq!(si.insert(Point::new(179.9, 0.9)), Ok(3));
+        assert_eq!(pairs(&si), vec![0, 2, 3]);
+
+        // Removing index 0 moves index 3 into its slot.
+        assert_eq!(
+            si.remove(0),
+            Some(geo::Geometry::Point(Point::new(179.75, 0.25)))
+        );
+        assert_eq!(si.remove(0), None);
+        assert_eq!(pairs(&si), vec![2, 3]);
+        assert_eq!(
+            si.remove(2),
+            Some(geo::Geometry::Line(geo::Line::new(
+                (179., 0.5),
+                (181., 0.5)
+            )))
+        );
+        assert_eq!(pairs(&si), vec![3]);
+
+        // Indexes are never handed out twice.
+        assert_eq!(si.insert(Point::new(179.6, 0.1)), Ok(4));
+        assert_eq!(pairs(&si), vec![3, 4]);
+        assert_eq!(
+            si.insert(geo::LineString(vec![(0., 0.).into()])),
+            Err(Error::LineStringTooSmall(0))
+        );
+
+        #[cfg(feature = "parallel")]
+        {
+            let mut si = config.parallel(&small).unwrap();
+            assert_eq!(si.insert(Point::new(179.6, 0.1)), Ok(2));
+            assert!(si.remove(0).is_some());
+            let mut rows: Vec<_> = si
+                .spatial_join(&big, Interaction::Intersects)
+                .unwrap()
+                .collect();
+            rows.sort();
+            assert_eq!(
+                rows,
+                vec![SJoinRow {
+                    big_index: 0,
+                    small_index: 2
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn per_geometry_distances() {
+        let small = vec![Point::new(0., 0.), Point::new(10., 0.), Point::new(20., 0.)];
+        let radii = [1., 5., 0.5];
+        let big = vec![
+            Point::new(0.5, 0.),
+            Point::new(14., 0.),
+            Point::new(20.8, 0.),
+        ];
+        let rows = |si: &SpatialIndex, big: &[Point<f64>]| -> Vec<_> {
+            let mut rows: Vec<_> = si.proximity_map(big).unwrap().collect();
+            rows.sort();
+            rows
+        };
+        let mut si = Config::new()
+            .serial_with_distances(&small, |i| radii[i])
+            .unwrap();
+        let expected = vec![
+            ProxMapRow {
+                big_index: 0,
+                small_index: 0,
+                distance: 0.5,
+            },
+            ProxMapRow {
+                big_index: 1,
+                small_index: 1,
+                distance: 4.,
+            },
+        ];
+        assert_eq!(rows(&si, &big), expected);
+        let found: Vec<_> = si
+            .within_distance(&Point::new(3., 0.).into(), 3.)
+            .unwrap()
+            .collect();
+        assert_eq!(found, vec![(0, 3.)]);
+
+        // Removing index 1 moves the last geometry, and its own
+        // distance, into its slot.
+        assert_eq!(si.insert_with_distance(Point::new(30., 0.), 2.), Ok(3));
+        assert_eq!(si.insert(Point::new(40., 0.)), Ok(4));
+        assert!(si.remove(1).is_some());
+        let big = vec![
+            Point::new(31.5, 0.),
+            Point::new(10., 0.),
+            Point::new(40., 0.),
+        ];
+        let expected = vec![
+            ProxMapRow {
+                big_index: 0,
+                small_index: 3,
+                distance: 1.5,
+            },
+            ProxMapRow {
+                big_index: 2,
+                small_index: 4,
+                distance: 0.,
+            },
+        ];
+        assert_eq!(rows(&si, &big), expected);
+
+        // An index built with one distance keeps it for the old
+        // geometries once a new one brings its own.
+        let mut si = Config::new().max_distance(1.).serial(&small).unwrap();
+        assert_eq!(si.insert_with_distance(Point::new(30., 0.), 3.), Ok(3));
+        let big = vec![Point::new(0., 2.), Point::new(30., 2.)];
+        assert_eq!(
+            rows(&si, &big),
+            vec![ProxMapRow {
+                big_index: 1,
+                small_index: 3,
+                distance: 2.,
+            }]
+        );
+
+        assert_eq!(
+            Config::new().serial_with_distances(&small, |_| -1.).err(),
+            Some(Error::BadMaxDistance(-1.))
+        );
+        match si.insert_with_distance(Point::new(0., 0.), f64::NAN) {
+            Err(Error::BadMaxDistance(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = Config::new()
+                .parallel_with_distances(&small, |i| radii[i])
+                .unwrap();
+            let big = vec![Point::new(14., 0.), Point::new(20.8, 0.)];
+            let rows: Vec<_> = si.proximity_map(&big).unwrap().collect();
+            assert_eq!(
+                rows,
+                vec![ProxMapRow {
+                    big_index: 0,
+                    small_index: 1,
+                    distance: 4.,
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn multi_polygon_spatial_join() {
+        let config = Config::new();
+        let square = |x: f64| {
+            geo::Polygon::new(
+                vec![(x, 0.), (x + 1., 0.), (x + 1., 1.), (x, 1.), (x, 0.)].into(),
+                vec![],
+            )
+        };
+        let small = vec![
+            geo::Geometry::MultiPolygon(geo::MultiPolygon(vec![square(0.), square(10.)])),
+            geo::Geometry::Polygon(square(5.)),
+        ];
+        let big = vec![
+            geo::Geometry::Point(Point::new(10.5, 0.5)),
+            geo::Geometry::MultiPoint(geo::MultiPoint(vec![
+                Point::new(0.5, 0.5),
+                Point::new(5.5, 0.5),
+            ])),
+        ];
+        let expected = vec![
+            SJoinRow {
+                big_index: 0,
+                small_index: 0,
+            },
+            SJoinRow {
+                big_index: 1,
+                small_index: 0,
+            },
+            SJoinRow {
+                big_index: 1,
+                small_index: 1,
+            },
+        ];
+        test_spatial_join(config, &small, &big, Interaction::Intersects, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &big, Interaction::Intersects, &expected);
+
+        // Neither small geometry covers both members of the multi-point.
+        let expected = vec![SJoinRow {
+            big_index: 0,
+            small_index: 0,
+        }];
+        test_spatial_join(config, &small, &big, Interaction::Contains, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &big, Interaction::Contains, &expected);
+    }
+
+    #[test]
+    fn points_on_polygon_boundary() {
+        let config = Config::new();
+        let triangle = vec![geo::Polygon::new(
+            vec![(0.5, 0.5), (1.5, 0.5), (0.5, 1.5)].into(),
+            vec![],
+        )];
+        // On an edge, on a vertex, inside, and outside.
+        let points = vec![
+            Point::new(1., 1.),
+            Point::new(0.5, 0.5),
+            Point::new(0.7, 0.7),
+            Point::new(5., 5.),
+        ];
+        let rows = |pairs: &[(usize, usize)]| -> Vec<SJoinRow> {
+            pairs
+                .iter()
+                .map(|&(big_index, small_index)| SJoinRow {
+                    big_index,
+                    small_index,
+                })
+                .collect()
+        };
+
+        for (interaction, big_indexes) in &[
+            (Interaction::Intersects, vec![0, 1, 2]),
+            (Interaction::Touches, vec![0, 1]),
+            (Interaction::Covers, vec![0, 1, 2]),
+            (Interaction::Contains, vec![2]),
+            (Interaction::Disjoint, vec![3]),
+        ] {
+            let pairs: Vec<_> = big_indexes.iter().map(|&i| (i, 0)).collect();
+            test_spatial_join(config, &triangle, &points, *interaction, &rows(&pairs));
+            #[cfg(feature = "parallel")]
+            test_par_spatial_join(config, &triangle, &points, *interaction, &rows(&pairs));
+        }
+
+        for (interaction, small_indexes) in &[
+            (Interaction::Intersects, vec![0, 1, 2]),
+            (Interaction::Touches, vec![0, 1]),
+            (Interaction::CoveredBy, vec![0, 1, 2]),
+            (Interaction::Within, vec![2]),
+            (Interaction::Disjoint, vec![3]),
+        ] {
+            let pairs: Vec<_> = small_indexes.iter().map(|&i| (0, i)).collect();
+            test_spatial_join(config, &points, &triangle, *interaction, &rows(&pairs));
+            #[cfg(feature = "parallel")]
+            test_par_spatial_join(config, &points, &triangle, *interaction, &rows(&pairs));
+        }
+    }
+
+    #[test]
+    fn shapes_along_boundaries() {
+        let config = Config::new();
+        let small = vec![
+            geo::Geometry::Triangle(geo::Triangle::from([(0., 0.), (2., 0.), (0., 2.)])),
+            geo::Geometry::Polygon(geo::Rect::new((0., 0.), (2., 2.)).to_polygon()),
+        ];
+        let big = vec![
+            // On the line through an edge, but past its end.
+            geo::Geometry::Point(Point::new(3., 0.)),
+            geo::Geometry::Point(Point::new(1., 0.)),
+            // Along an edge.
+            geo::Geometry::LineString(vec![(0., 0.), (1., 0.)].into()),
+            // From a corner, through the inside, out to an edge.
+            geo::Geometry::LineString(vec![(0., 0.), (1., 1.), (2., 1.)].into()),
+            geo::Geometry::Line(geo::Line::new((0., 0.), (0.5, 0.5))),
+            geo::Geometry::LineString(vec![(3., 0.), (4., 0.)].into()),
+        ];
+        let rows = |pairs: &[(usize, usize)]| -> Vec<SJoinRow> {
+            pairs
+                .iter()
+                .map(|&(big_index, small_index)| SJoinRow {
+                    big_index,
+                    small_index,
+                })
+                .collect()
+        };
+
+        for (interaction, pairs) in &[
+            (
+                Interaction::Intersects,
+                vec![
+                    (1, 0),
+                    (1, 1),
+                    (2, 0),
+                    (2, 1),
+                    (3, 0),
+                    (3, 1),
+                    (4, 0),
+                    (4, 1),
+                ],
+            ),
+            (Interaction::Contains, vec![(3, 1), (4, 0), (4, 1)]),
+            (Interaction::Disjoint, vec![(0, 0), (0, 1), (5, 0), (5, 1)]),
+        ] {
+            test_spatial_join(config, &small, &big, *interaction, &rows(pairs));
+            #[cfg(feature = "parallel")]
+            test_par_spatial_join(config, &small, &big, *interaction, &rows(pairs));
+        }
+    }
+
+    #[test]
+    fn multi_geometry_on_boundary() {
+        let config = Config::new();
+        let small = vec![
+            geo::Geometry::Rect(geo::Rect::new((0., 0.), (4., 4.))),
+            geo::Geometry::MultiPolygon(geo::MultiPolygon(vec![
+                geo::Rect::new((10., 0.), (11., 1.)).to_polygon(),
+                geo::Rect::new((11., 0.), (12., 1.)).to_polygon(),
+            ])),
+        ];
+        let big = vec![
+            // One member inside, one on the edge.
+            geo::Geometry::MultiPoint(geo::MultiPoint(vec![
+                Point::new(2., 2.),
+                Point::new(4., 2.),
+            ])),
+            // Every member on the edge.
+            geo::Geometry::MultiPoint(geo::MultiPoint(vec![
+                Point::new(0., 0.),
+                Point::new(4., 2.),
+            ])),
+            // Straddles the two touching squares.
+            geo::Geometry::Line(geo::Line::new((10.5, 0.5), (11.5, 0.5))),
+        ];
+        let expected = vec![
+            SJoinRow {
+                big_index: 0,
+                small_index: 0,
+            },
+            SJoinRow {
+                big_index: 2,
+                small_index: 1,
+            },
+        ];
+        test_spatial_join(config, &small, &big, Interaction::Contains, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &big, Interaction::Contains, &expected);
+        let contains = Interaction::Relate(RelatePattern::new("T*****FF*").unwrap());
+        test_spatial_join(config, &small, &big, contains, &expected);
+
+        // And the other way around.
+        let expected = vec![SJoinRow {
+            big_index: 0,
+            small_index: 0,
+        }];
+        test_spatial_join(
+            config,
+            &big[..1],
+            &small[..1],
+            Interaction::Within,
+            &expected,
+        );
+
+        // A point on a member's edge meets the multi-polygon but isn't
+        // inside it.
+        let on_edge = vec![Point::new(10., 0.5)];
+        let expected = vec![SJoinRow {
+            big_index: 0,
+            small_index: 1,
+        }];
+        test_spatial_join(config, &small, &on_edge, Interaction::Intersects, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &on_edge, Interaction::Intersects, &expected);
+        test_spatial_join(config, &small, &on_edge, Interaction::Contains, &vec![]);
+    }
+
+    #[test]
+    fn relate_pattern_spatial_join() {
+        let config = Config::new();
+        let small = vec![
+            geo::Geometry::Rect(geo::Rect::new((0., 0.), (1., 1.))),
+            geo::Geometry::Rect(geo::Rect::new((10., 10.), (11., 11.))),
+        ];
+        let big = vec![geo::Geometry::Rect(geo::Rect::new((1., 0.), (2., 1.)))];
+
+        // Edge to edge, so interiors are apart but boundaries share a line.
+        let touching_edges = Interaction::Relate("F***1****".parse().unwrap());
+        let expected = vec![SJoinRow {
+            big_index: 0,
+            small_index: 0,
+        }];
+        test_spatial_join(config, &small, &big, touching_edges, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &big, touching_edges, &expected);
+
+        // The R-trees never pair the far away rectangle with big, but
+        // a disjoint pattern still has to find it.
+        let disjoint = Interaction::Relate(RelatePattern::new("ff*ff****").unwrap());
+        let expected = vec![SJoinRow {
+            big_index: 0,
+            small_index: 1,
+        }];
+        test_spatial_join(config, &small, &big, disjoint, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &small, &big, disjoint, &expected);
+
+        // A point on the polygon's edge meets its boundary and nothing
+        // else, so it isn't disjoint from it.
+        let on_edge = vec![Point::new(1.5, 0.)];
+        let polygon = vec![geo::Rect::new((1., 0.), (2., 1.)).to_polygon()];
+        let point_on_boundary = Interaction::Relate("F0FFFF212".parse().unwrap());
+        let expected = vec![SJoinRow {
+            big_index: 0,
+            small_index: 0,
+        }];
+        test_spatial_join(config, &on_edge, &polygon, point_on_boundary, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &on_edge, &polygon, point_on_boundary, &expected);
+        test_spatial_join(config, &on_edge, &polygon, disjoint, &vec![]);
+        #[cfg(feature = "parallel")]
+        test_par_spatial_join(config, &on_edge, &polygon, disjoint, &vec![]);
+
+        assert_eq!(
+            RelatePattern::new("T*F**F**"),
+            Err(Error::BadRelatePattern("T*F**F**".to_string()))
+        );
+        assert!("T*F**F**X".parse::<RelatePattern>().is_err());
+    }
+
+    #[test]
+    fn f32_coordinates() {
+        use geo::algorithm::map_coords::MapCoords;
+
+        let small: Vec<geo::Geometry<f32>> = vec![
+            geo::Rect::new((0., 0.), (2., 2.)).into(),
+            geo::Polygon::new(vec![(4., 4.), (6., 4.), (5., 6.)].into(), vec![]).into(),
+            Point::new(10., 10.).into(),
+        ];
+        let big: Vec<geo::Geometry<f32>> = vec![
+            Point::new(1., 1.).into(),
+            geo::Line::new((3., 5.), (7., 5.)).into(),
+            Point::new(10., 13.).into(),
+        ];
+        let wide = |geos: &[geo::Geometry<f32>]| -> Vec<geo::Geometry<f64>> {
+            geos.iter()
+                .map(|geo| geo.map_coords(|&(x, y)| (x.into(), y.into())))
+                .collect()
+        };
+        let (small64, big64) = (wide(&small), wide(&big));
+
+        // The f32 index has to agree with one built from the same
+        // geometries widened to f64.
+        let config = Config::new().max_distance(4.);
+        let si = config.serial(&small).unwrap();
+        let si64 = config.serial(&small64).unwrap();
+        for &interaction in &[
+            Interaction::Intersects,
+            Interaction::Contains,
+            Interaction::Within,
+            Interaction::Crosses,
+            Interaction::Disjoint,
+        ] {
+            let mut rows: Vec<_> = si.spatial_join(&big, interaction).unwrap().collect();
+            rows.sort();
+            let mut expected: Vec<_> = si64.spatial_join(&big64, interaction).unwrap().collect();
+            expected.sort();
+            assert_eq!(rows, expected, "{:?}", interaction);
+        }
+
+        let mut rows: Vec<_> = si.proximity_map(&big).unwrap().collect();
+        rows.sort();
+        let mut expected: Vec<_> = si64.proximity_map(&big64).unwrap().collect();
+        expected.sort();
+        assert_eq!(rows, expected);
+        assert!(rows.iter().any(|row| row.distance == 3.));
+
+        let bad: Vec<geo::Geometry<f32>> = vec![Point::new(f32::NAN, 0.).into()];
+        match si.spatial_join(&bad, Interaction::Intersects) {
+            Err(Error::BadCoordinateValue(0, geo::Geometry::Point(_))) => {}
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+
+        let rows: Vec<_> = si
+            .spatial_join_with_geos(&big, Interaction::Contains)
+            .unwrap()
+            .collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].big, GeometryCow::Point(Point::new(1., 1.)));
+
+        #[cfg(feature = "parallel")]
+        {
+            let si = config.parallel(&small).unwrap();
+            let mut rows: Vec<_> = si.proximity_map(&big).unwrap().collect();
+            rows.sort();
+            assert_eq!(rows, expected);
+            let rows: Vec<_> = si
+                .spatial_join_with_geos(&big, Interaction::Contains)
+                .unwrap()
+                .collect();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].big, GeometryCow::Point(Point::new(1., 1.)));
+        }
+    }
+
+    #[test]
+    fn nearest_neighbors() {
+        let small = vec![
+            geo::Geometry::Point(Point::new(0., 0.)),
+            geo::Geometry::Rect(geo::Rect::new((10., 0.), (11., 1.))),
+            geo::Geometry::Point(Point::new(100., 0.)),
+        ];
+        let big = vec![Point::new(8., 0.), Point::new(60., 0.)];
+        let expected = vec![
+            ProxMapRow {
+                big_index: 0,
+                small_index: 0,
+                distance: 8.,
+            },
+            ProxMapRow {
+                big_index: 0,
+                small_index: 1,
+                distance: 2.,
+            },
+            ProxMapRow {
+                big_index: 1,
+                small_index: 1,
+                distance: 49.,
+            },
+            ProxMapRow {
+                big_index: 1,
+                small_index: 2,
+                distance: 40.,
+            },
+        ];
+        test_nearest_neighbors(Config::new(), &small, &big, 2, None, &expected);
+        #[cfg(feature = "parallel")]
+        test_par_nearest_neighbors(Config::new(), &small, &big, 2, None, &expected);
+
+        let expected = vec![ProxMapRow {
+            big_index: 0,
+            small_index: 1,
+            distance: 2.,
+        }];
+        test_nearest_neighbors(Config::new(), &small, &big, 2, Some(5.), &expected);
+        #[cfg(feature = "parallel")]
+        test_par_nearest_neighbors(Config::new(), &small, &big, 2, Some(5.), &expected);
+
+        let si = Config::new().serial(&small).unwrap();
+        assert_eq!(
+            si.nearest_neighbors_within(&big, 1, -1.).err(),
+            Some(Error::BadMaxDistance(-1.))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips() {
+        let config = Config::new()
+            .max_distance(2.5)
+            .distance_metric(DistanceMetric::Geodesic)
+            .geographic(true);
+        assert_eq!(round_trip(&config), config);
+
+        let interactions = vec![
+            Interaction::Intersects,
+            Interaction::Disjoint,
+            Interaction::Relate("T*F**F***".parse().unwrap()),
+        ];
+        assert_eq!(round_trip(&interactions), interactions);
+        assert_eq!(
+            serde_json::to_string(&Interaction::Relate("t*f**f***".parse().unwrap())).unwrap(),
+            r#"{"Relate":"T*F**F***"}"#
+        );
+        assert!(serde_json::from_str::<Interaction>(r#"{"Relate":"T*F"}"#).is_err());
+
+        let small = vec![Point::new(1., 1.), Point::new(5., 5.)];
+        let big = vec![geo::Rect::new((0., 0.), (2., 2.))];
+        let si = Config::new().max_distance(10.).serial(&small).unwrap();
+
+        let rows: Vec<_> = si
+            .spatial_join(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        assert_eq!(round_trip(&rows), rows);
+
+        let rows: Vec<_> = si.proximity_map(&big).unwrap().collect();
+        let back = round_trip(&rows);
+        assert_eq!(back, rows);
+        assert!(back
+            .iter()
+            .zip(rows.iter())
+            .all(|(a, b)| a.distance == b.distance));
+
+        // The geo rows only compare indexes, so check the geometries
+        // survive too.
+        let rows: Vec<_> = si
+            .spatial_join_with_geos(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        let back = round_trip(&rows);
+        assert_eq!(back, rows);
+        assert!(back
+            .iter()
+            .zip(rows.iter())
+            .all(|(a, b)| (&a.big, &a.small) == (&b.big, &b.small)));
+
+        let rows: Vec<_> = si.proximity_map_with_geos(&big).unwrap().collect();
+        let back = round_trip(&rows);
+        assert_eq!(back, rows);
+        assert!(back
+            .iter()
+            .zip(rows.iter())
+            .all(|(a, b)| (&a.big, &a.small, a.distance) == (&b.big, &b.small, b.distance)));
+
+        let sgs: SplitGeoSeq = (&small).try_into().unwrap();
+        assert_eq!(round_trip(&sgs), sgs);
+    }
+
+    #[test]
+    fn borrows_heap_geometries() {
+        use std::borrow::Cow;
+
+        let poly = geo::Polygon::new(vec![(0., 0.), (2., 0.), (2., 2.), (0., 0.)].into(), vec![]);
+        let small = vec![poly.clone()];
+        let big = vec![poly];
+
+        let si = Config::new().serial(&small).unwrap();
+        let rows: Vec<_> = si
+            .spatial_join_with_geos(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        assert_eq!(rows.len(), 1);
+        match &rows[0].big {
+            GeometryCow::Polygon(Cow::Borrowed(p)) => assert!(std::ptr::eq(*p, &big[0])),
+            other => panic!("expected a borrowed polygon, got {:?}", other),
+        }
+
+        // Owned input stays owned.
+        let rows: Vec<_> = si
+            .spatial_join_with_geos(big.clone(), Interaction::Intersects)
+            .unwrap()
+            .collect();
+        assert!(matches!(rows[0].big, GeometryCow::Polygon(Cow::Owned(_))));
+
+        // Small geometries borrow from the index, even one that owns
+        // them.
+        let si = Config::new().serial(small.clone()).unwrap();
+        let rows: Vec<_> = si.proximity_map_with_geos(big.clone()).unwrap().collect();
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(
+            rows[0].small,
+            GeometryCow::Polygon(Cow::Borrowed(_))
+        ));
+        assert_eq!(
+            geo::Geometry::from(rows[0].small.clone()),
+            small[0].clone().into()
+        );
+    }
+
     // for all pairs of types, verift that prox map finds and doesn't find depending on max_distance
 }
@@ -1,8 +1,9 @@
 use geo::{Geometry, Point};
 
-use crate::{relates::Relates, Interaction, ProxMapRow, SJoinRow, SplitGeoSeq};
+use crate::structs::Stored;
+use crate::{relates::Relates, DistanceMetric, Interaction, ProxMapRow, SJoinRow, SplitGeoSeq};
 
-impl SplitGeoSeq {
+impl SplitGeoSeq<'_> {
     pub fn to_vec(&self) -> Vec<Geometry<f64>> {
         let mut result = vec![
             Geometry::Point(Point::new(0., 0.));
@@ -12,6 +13,9 @@ impl SplitGeoSeq {
                 + self.geos.line_strings.len()
                 + self.geos.rects.len()
                 + self.geos.tris.len()
+                + self.geos.multi_points.len()
+                + self.geos.multi_line_strings.len()
+                + self.geos.multi_polys.len()
         ];
         for (i, g) in self
             .indexes
@@ -19,7 +23,7 @@ impl SplitGeoSeq {
             .iter()
             .zip(self.geos.points.iter().cloned())
         {
-            result[i] = g.into();
+            result[i] = g.into_geometry();
         }
         for (i, g) in self
             .indexes
@@ -27,7 +31,7 @@ impl SplitGeoSeq {
             .iter()
             .zip(self.geos.lines.iter().cloned())
         {
-            result[i] = g.into();
+            result[i] = g.into_geometry();
         }
         for (i, g) in self
             .indexes
@@ -35,7 +39,7 @@ impl SplitGeoSeq {
             .iter()
             .zip(self.geos.polys.iter().cloned())
         {
-            result[i] = g.into();
+            result[i] = g.into_geometry();
         }
         for (i, g) in self
             .indexes
@@ -43,7 +47,7 @@ impl SplitGeoSeq {
             .iter()
             .zip(self.geos.line_strings.iter().cloned())
         {
-            result[i] = g.into();
+            result[i] = g.into_geometry();
         }
         for (i, g) in self
             .indexes
@@ -51,10 +55,34 @@ impl SplitGeoSeq {
             .iter()
             .zip(self.geos.rects.iter().cloned())
         {
-            result[i] = g.into();
+            result[i] = g.into_geometry();
         }
         for (i, g) in self.indexes.tris.iter().zip(self.geos.tris.iter().cloned()) {
-            result[i] = g.into();
+            result[i] = g.into_geometry();
+        }
+        for (i, g) in self
+            .indexes
+            .multi_points
+            .iter()
+            .zip(self.geos.multi_points.iter().cloned())
+        {
+            result[i] = g.into_geometry();
+        }
+        for (i, g) in self
+            .indexes
+            .multi_line_strings
+            .iter()
+            .zip(self.geos.multi_line_strings.iter().cloned())
+        {
+            result[i] = g.into_geometry();
+        }
+        for (i, g) in self
+            .indexes
+            .multi_polys
+            .iter()
+            .zip(self.geos.multi_polys.iter().cloned())
+        {
+            result[i] = g.into_geometry();
         }
         result
     }
@@ -71,51 +99,106 @@ macro_rules! enum_dispatch {
             (Geometry::Line($a), Geometry::LineString($b)) => $expr,
             (Geometry::Line($a), Geometry::Rect($b)) => $expr,
             (Geometry::Line($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::Line($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::Line($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::Line($a), Geometry::MultiPolygon($b)) => $expr,
             (Geometry::Point($a), Geometry::Line($b)) => $expr,
             (Geometry::Point($a), Geometry::Point($b)) => $expr,
             (Geometry::Point($a), Geometry::Polygon($b)) => $expr,
             (Geometry::Point($a), Geometry::LineString($b)) => $expr,
             (Geometry::Point($a), Geometry::Rect($b)) => $expr,
             (Geometry::Point($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::Point($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::Point($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::Point($a), Geometry::MultiPolygon($b)) => $expr,
             (Geometry::Polygon($a), Geometry::Line($b)) => $expr,
             (Geometry::Polygon($a), Geometry::Point($b)) => $expr,
             (Geometry::Polygon($a), Geometry::Polygon($b)) => $expr,
             (Geometry::Polygon($a), Geometry::LineString($b)) => $expr,
             (Geometry::Polygon($a), Geometry::Rect($b)) => $expr,
             (Geometry::Polygon($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::Polygon($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::Polygon($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::Polygon($a), Geometry::MultiPolygon($b)) => $expr,
             (Geometry::LineString($a), Geometry::Line($b)) => $expr,
             (Geometry::LineString($a), Geometry::Point($b)) => $expr,
             (Geometry::LineString($a), Geometry::Polygon($b)) => $expr,
             (Geometry::LineString($a), Geometry::LineString($b)) => $expr,
             (Geometry::LineString($a), Geometry::Rect($b)) => $expr,
             (Geometry::LineString($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::LineString($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::LineString($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::LineString($a), Geometry::MultiPolygon($b)) => $expr,
             (Geometry::Rect($a), Geometry::Line($b)) => $expr,
             (Geometry::Rect($a), Geometry::Point($b)) => $expr,
             (Geometry::Rect($a), Geometry::Polygon($b)) => $expr,
             (Geometry::Rect($a), Geometry::LineString($b)) => $expr,
             (Geometry::Rect($a), Geometry::Rect($b)) => $expr,
             (Geometry::Rect($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::Rect($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::Rect($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::Rect($a), Geometry::MultiPolygon($b)) => $expr,
             (Geometry::Triangle($a), Geometry::Line($b)) => $expr,
             (Geometry::Triangle($a), Geometry::Point($b)) => $expr,
             (Geometry::Triangle($a), Geometry::Polygon($b)) => $expr,
             (Geometry::Triangle($a), Geometry::LineString($b)) => $expr,
             (Geometry::Triangle($a), Geometry::Rect($b)) => $expr,
             (Geometry::Triangle($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::Triangle($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::Triangle($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::Triangle($a), Geometry::MultiPolygon($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::Line($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::Point($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::Polygon($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::LineString($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::Rect($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::MultiPoint($a), Geometry::MultiPolygon($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::Line($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::Point($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::Polygon($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::LineString($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::Rect($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::MultiLineString($a), Geometry::MultiPolygon($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::Line($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::Point($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::Polygon($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::LineString($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::Rect($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::Triangle($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::MultiPoint($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::MultiLineString($b)) => $expr,
+            (Geometry::MultiPolygon($a), Geometry::MultiPolygon($b)) => $expr,
             _ => panic!("match failure in enum_dispatch!"),
         }
     };
 }
 
+// Measured from `big` to `small`, the same way round as the index
+// does, so the floating point results match exactly.
+fn slow_distance(big: &Geometry<f64>, small: &Geometry<f64>, metric: DistanceMetric) -> f64 {
+    match metric {
+        DistanceMetric::Euclidean => enum_dispatch!(big, small, big.EuclideanDistance(small)),
+        DistanceMetric::Geodesic => enum_dispatch!(big, small, big.GeodesicDistance(small)),
+    }
+}
+
 pub(crate) fn slow_prox_map(
     small: &SplitGeoSeq,
     big: &SplitGeoSeq,
     max_distance: f64,
+    metric: DistanceMetric,
 ) -> Vec<ProxMapRow> {
     let mut result = Vec::new();
 
     for (ai, a) in small.to_vec().iter().enumerate() {
         for (bi, b) in big.to_vec().iter().enumerate() {
-            let distance = enum_dispatch!(a, b, a.EuclideanDistance(b));
+            let distance = slow_distance(b, a, metric);
             if distance <= max_distance {
                 result.push(ProxMapRow {
                     big_index: bi,
@@ -130,6 +213,40 @@ pub(crate) fn slow_prox_map(
     result
 }
 
+pub(crate) fn slow_nearest_neighbors(
+    small: &SplitGeoSeq,
+    big: &SplitGeoSeq,
+    k: usize,
+    max_distance: f64,
+    metric: DistanceMetric,
+) -> Vec<ProxMapRow> {
+    let mut result = Vec::new();
+
+    for (bi, b) in big.to_vec().iter().enumerate() {
+        let mut rows: Vec<ProxMapRow> = small
+            .to_vec()
+            .iter()
+            .enumerate()
+            .map(|(ai, a)| ProxMapRow {
+                big_index: bi,
+                small_index: ai,
+                distance: slow_distance(b, a, metric),
+            })
+            .filter(|row| row.distance <= max_distance)
+            .collect();
+        rows.sort_by(|x, y| {
+            x.distance
+                .partial_cmp(&y.distance)
+                .unwrap()
+                .then(x.small_index.cmp(&y.small_index))
+        });
+        result.extend(rows.into_iter().take(k));
+    }
+
+    result.sort();
+    result
+}
+
 pub(crate) fn slow_spatial_join(
     small: &SplitGeoSeq,
     big: &SplitGeoSeq,
@@ -143,6 +260,17 @@ pub(crate) fn slow_spatial_join(
                 Interaction::Intersects => enum_dispatch!(a, b, a.Intersects(b)),
                 Interaction::Contains => enum_dispatch!(a, b, a.Contains(b)),
                 Interaction::Within => enum_dispatch!(a, b, b.Contains(a)),
+                Interaction::Touches => enum_dispatch!(a, b, a.Touches(b)),
+                Interaction::Crosses => enum_dispatch!(a, b, a.Crosses(b)),
+                Interaction::Overlaps => enum_dispatch!(a, b, a.Overlaps(b)),
+                Interaction::Covers => enum_dispatch!(a, b, a.Covers(b)),
+                Interaction::CoveredBy => enum_dispatch!(a, b, a.CoveredBy(b)),
+                Interaction::Disjoint => enum_dispatch!(a, b, a.Disjoint(b)),
+                Interaction::Relate(pattern) => enum_dispatch!(a, b, a.Relate(b, &pattern)),
+                Interaction::WithinDistance(distance) => {
+                    enum_dispatch!(b, a, b.Intersects(a))
+                        || slow_distance(b, a, DistanceMetric::Euclidean) <= distance
+                }
             };
             if include {
                 result.push(SJoinRow {
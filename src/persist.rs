@@ -0,0 +1,296 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::binary::{self, BinaryError};
+#[cfg(feature = "parallel")]
+use crate::ParSpatialIndex;
+use crate::{Error, SpatialIndex};
+
+// A saved index is these eight bytes, the format version as a
+// little-endian u32, and then the index itself in our binary format
+// (see binary.rs): the small geometries, their external indexes, the
+// R-trees node by node, any per-geometry distances and the config.
+// Bump FORMAT_VERSION whenever the layout of any of those changes.
+//
+// With the `mmap` feature, `load_mmap` reads a saved index out of a
+// memory-mapped file instead of through a buffer. That saves a copy
+// of the file's bytes, but no more: rstar's R-trees and geo's shapes
+// own their memory, so every tree and geometry is still rebuilt as
+// it's read. Either way the result is checked like any other
+// deserialized index, since nothing stops a file from being damaged
+// or made up.
+const MAGIC: &[u8; 8] = b"spjoinix";
+const FORMAT_VERSION: u32 = 6;
+const HEADER_LEN: usize = 12;
+
+impl SpatialIndex<'_> {
+    /// Saves the index to `path`; `SpatialIndex::load` reads it back
+    /// without rebuilding any R-trees.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+        self.write_to(&mut writer)?;
+        writer.flush().map_err(io_error)
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_all(MAGIC).map_err(io_error)?;
+        writer
+            .write_all(&FORMAT_VERSION.to_le_bytes())
+            .map_err(io_error)?;
+        binary::to_writer(writer, self).map_err(binary_error)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SpatialIndex<'static>, Error> {
+        SpatialIndex::read_from(BufReader::new(File::open(path).map_err(io_error)?))
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> Result<SpatialIndex<'static>, Error> {
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header).map_err(io_error)?;
+        check_header(&header)?;
+        binary::from_reader(reader).map_err(binary_error)
+    }
+
+    /// Loads an index saved with `save` or `write_to` out of memory.
+    /// The index is a copy: nothing in it borrows from `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SpatialIndex<'static>, Error> {
+        if bytes.len() < HEADER_LEN {
+            return Err(Error::BadIndexFile("too short".to_string()));
+        }
+        check_header(&bytes[..HEADER_LEN])?;
+        binary::from_reader(&bytes[HEADER_LEN..]).map_err(binary_error)
+    }
+
+    /// Like `load`, but memory-maps `path` and reads the index out of
+    /// the mapping, so the file is paged in by the OS rather than
+    /// copied through a buffer. The index is still a copy, and the
+    /// mapping is gone by the time this returns.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may write to or truncate the file, in this process or
+    /// any other, until this returns. The mapping hands the file's
+    /// pages to the loader as an ordinary byte slice, so a change made
+    /// underneath it is undefined behavior rather than a load error.
+    /// Loading a file nothing else is touching is as safe as `load`.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn load_mmap<P: AsRef<Path>>(path: P) -> Result<SpatialIndex<'static>, Error> {
+        let file = File::open(path).map_err(io_error)?;
+        // Safety: the mapping only lives as long as this call, and our
+        // caller promises the file holds still until then.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+        SpatialIndex::from_bytes(&map)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl ParSpatialIndex<'_> {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.0.save(path)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        self.0.write_to(writer)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ParSpatialIndex<'static>, Error> {
+        SpatialIndex::load(path).map(ParSpatialIndex)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> Result<ParSpatialIndex<'static>, Error> {
+        SpatialIndex::read_from(reader).map(ParSpatialIndex)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<ParSpatialIndex<'static>, Error> {
+        SpatialIndex::from_bytes(bytes).map(ParSpatialIndex)
+    }
+
+    /// # Safety
+    ///
+    /// As for [`SpatialIndex::load_mmap`](struct.SpatialIndex.html#method.load_mmap).
+    #[cfg(feature = "mmap")]
+    pub unsafe fn load_mmap<P: AsRef<Path>>(path: P) -> Result<ParSpatialIndex<'static>, Error> {
+        unsafe { SpatialIndex::load_mmap(path) }.map(ParSpatialIndex)
+    }
+}
+
+fn check_header(header: &[u8]) -> Result<(), Error> {
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(Error::BadIndexFile("missing header".to_string()));
+    }
+    let mut version = [0; 4];
+    version.copy_from_slice(&header[MAGIC.len()..HEADER_LEN]);
+    let version = u32::from_le_bytes(version);
+    if version != FORMAT_VERSION {
+        return Err(Error::BadIndexFile(format!(
+            "format version {} but we only read version {}",
+            version, FORMAT_VERSION
+        )));
+    }
+    Ok(())
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => Error::BadIndexFile("too short".to_string()),
+        _ => Error::Io(e.to_string()),
+    }
+}
+
+fn binary_error(e: BinaryError) -> Error {
+    match e {
+        BinaryError::Io(e) => io_error(e),
+        BinaryError::Invalid(message) => Error::BadIndexFile(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, DistanceMetric, Interaction};
+    use geo::{Geometry, Line, LineString, Point, Polygon, Rect};
+
+    fn sample() -> Vec<Geometry<f64>> {
+        vec![
+            Geometry::Point(Point::new(1., 1.)),
+            Geometry::Rect(Rect::new((0., 0.), (2., 2.))),
+            Geometry::Line(Line::new((5., 5.), (6., 7.))),
+            Geometry::Polygon(Polygon::new(
+                LineString::from(vec![(10., 10.), (12., 10.), (11., 12.)]),
+                vec![],
+            )),
+        ]
+    }
+
+    fn assert_same_joins(a: &SpatialIndex, b: &SpatialIndex) {
+        let big = vec![
+            Geometry::Point(Point::new(1., 1.)),
+            Geometry::Rect(Rect::new((4., 4.), (11., 11.))),
+        ];
+        let mut a_rows: Vec<_> = a
+            .spatial_join(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        let mut b_rows: Vec<_> = b
+            .spatial_join(&big, Interaction::Intersects)
+            .unwrap()
+            .collect();
+        a_rows.sort();
+        b_rows.sort();
+        assert_eq!(a_rows, b_rows);
+        assert!(!a_rows.is_empty());
+
+        let mut a_rows: Vec<_> = a.proximity_map(&big).unwrap().collect();
+        let mut b_rows: Vec<_> = b.proximity_map(&big).unwrap().collect();
+        a_rows.sort();
+        b_rows.sort();
+        assert_eq!(a_rows, b_rows);
+    }
+
+    #[test]
+    fn round_trip() {
+        let config = Config::new()
+            .max_distance(3.)
+            .distance_metric(DistanceMetric::Geodesic);
+        let si = config.serial(sample()).unwrap();
+        let mut bytes = vec![];
+        si.write_to(&mut bytes).unwrap();
+        assert_eq!(&bytes[..8], b"spjoinix");
+
+        let loaded = SpatialIndex::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.config, si.config);
+        assert_eq!(loaded.small, si.small);
+        assert_same_joins(&si, &loaded);
+
+        let loaded = SpatialIndex::read_from(&bytes[..]).unwrap();
+        assert_same_joins(&si, &loaded);
+
+        let path = std::env::temp_dir().join(format!("spatial-join-{}.idx", std::process::id()));
+        si.save(&path).unwrap();
+        let loaded = SpatialIndex::load(&path);
+        #[cfg(feature = "mmap")]
+        // Safety: nothing else knows about the file.
+        let mapped = unsafe { SpatialIndex::load_mmap(&path) };
+        std::fs::remove_file(&path).unwrap();
+        assert_same_joins(&si, &loaded.unwrap());
+        #[cfg(feature = "mmap")]
+        assert_same_joins(&si, &mapped.unwrap());
+    }
+
+    #[test]
+    fn bad_files() {
+        let si = Config::new().serial(sample()).unwrap();
+        let mut bytes = vec![];
+        si.write_to(&mut bytes).unwrap();
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[8] += 1;
+        assert!(matches!(
+            SpatialIndex::from_bytes(&wrong_version),
+            Err(Error::BadIndexFile(_))
+        ));
+        assert!(matches!(
+            SpatialIndex::from_bytes(b"not an index at all"),
+            Err(Error::BadIndexFile(_))
+        ));
+        assert!(matches!(
+            SpatialIndex::from_bytes(&bytes[..bytes.len() / 2]),
+            Err(Error::BadIndexFile(_))
+        ));
+        assert!(matches!(
+            SpatialIndex::read_from(&bytes[..5]),
+            Err(Error::BadIndexFile(_))
+        ));
+        assert!(matches!(
+            SpatialIndex::load("/nonexistent/spatial-join.idx"),
+            Err(Error::Io(_))
+        ));
+        #[cfg(feature = "mmap")]
+        assert!(matches!(
+            unsafe { SpatialIndex::load_mmap("/nonexistent/spatial-join.idx") },
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn damaged_files() {
+        fn reload(si: &SpatialIndex) -> Result<SpatialIndex<'static>, Error> {
+            let mut bytes = vec![];
+            si.write_to(&mut bytes).unwrap();
+            SpatialIndex::from_bytes(&bytes)
+        }
+
+        let good = || Config::new().serial(sample()).unwrap();
+        assert!(reload(&good()).is_ok());
+
+        let mut si = good();
+        si.small.geos.points.push(Point::new(3., 3.));
+        assert!(matches!(reload(&si), Err(Error::BadIndexFile(_))));
+
+        let mut si = good();
+        si.small.geos.points[0] = Point::new(f64::NAN, 3.);
+        assert!(matches!(reload(&si), Err(Error::BadIndexFile(_))));
+
+        let mut si = good();
+        si.distances = vec![1.; si.next_index - 1];
+        assert!(matches!(reload(&si), Err(Error::BadIndexFile(_))));
+
+        let mut si = good();
+        si.next_index -= 1;
+        assert!(matches!(reload(&si), Err(Error::BadIndexFile(_))));
+
+        let mut si = good();
+        let bbox = si.rect_tree.iter().next().unwrap().bbox;
+        si.rect_tree.insert(crate::rtrees::FakeRegion {
+            id: 1,
+            bbox,
+            shift: 0,
+        });
+        assert!(matches!(reload(&si), Err(Error::BadIndexFile(_))));
+
+        let mut si = good();
+        si.rect_tree = rstar::RTree::new();
+        assert!(matches!(reload(&si), Err(Error::BadIndexFile(_))));
+    }
+}
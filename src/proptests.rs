@@ -1,16 +1,24 @@
 use std::convert::TryInto;
 
-use geo::{Coordinate, Geometry, Line, LineString, Point, Polygon, Rect, Triangle};
+use geo::algorithm::map_coords::MapCoords;
+use geo::{
+    Coordinate, Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point,
+    Polygon, Rect, Triangle,
+};
 use proptest::prelude::*;
 
-use super::naive::{slow_prox_map, slow_spatial_join};
+use super::naive::{slow_nearest_neighbors, slow_prox_map, slow_spatial_join};
 use crate::{
-    relates::Relates, tests::test_prox_map, tests::test_spatial_join, Config, Error, Interaction,
-    SplitGeoSeq,
+    relates::Relates, tests::test_nearest_neighbors, tests::test_prox_map,
+    tests::test_spatial_join, Config, DistanceMetric, Indexes, Interaction, ProxMapRow,
+    RelatePattern, SplitGeoSeq,
 };
 
 #[cfg(feature = "parallel")]
-use crate::{tests::test_par_prox_map, tests::test_par_spatial_join, Par};
+use crate::{
+    tests::test_par_nearest_neighbors, tests::test_par_prox_map, tests::test_par_spatial_join,
+    Error, Par,
+};
 
 #[rustfmt::skip]
 prop_compose! {
@@ -47,22 +55,26 @@ prop_compose! {
     }
 }
 
+fn regular_polygon(center: Point<f64>, exterior_points: usize, radius: f64) -> Polygon<f64> {
+    let angles = (0..exterior_points)
+        .map(|idx| 2.0 * std::f64::consts::PI * (idx as f64) / (exterior_points as f64));
+    let points: Vec<geo::Coordinate<f64>> = angles
+        .map(|angle_rad| angle_rad.sin_cos())
+        .map(|(sin, cos)| geo::Coordinate {
+            x: center.x() + radius * cos,
+            y: center.y() + radius * sin,
+        })
+        .collect();
+
+    Polygon::new(geo::LineString(points), vec![])
+}
+
 #[rustfmt::skip]
 prop_compose! {
     fn arb_poly()(center in arb_point(),
-		  exterior_points in 3..17,
+		  exterior_points in 3..17usize,
 		  radius in 0.000001..0.5) -> Polygon<f64> {
-	let angles = (0..exterior_points)
-	    .map(|idx| 2.0 * std::f64::consts::PI * (idx as f64) / (exterior_points as f64));
-	let points: Vec<geo::Coordinate<f64>> = angles
-	    .map(|angle_rad| angle_rad.sin_cos())
-	    .map(|(sin, cos)| geo::Coordinate {
-		x: center.x() + radius * cos,
-		y: center.y() + radius * sin,
-	    })
-	    .collect();
-
-	Polygon::new(geo::LineString(points), vec![])
+	regular_polygon(center, exterior_points, radius)
     }
 }
 
@@ -75,6 +87,42 @@ prop_compose! {
     }
 }
 
+#[rustfmt::skip]
+prop_compose! {
+    fn arb_multipoint()(points in prop::collection::vec(arb_point(), 1..5)) -> MultiPoint<f64> {
+	MultiPoint(points)
+    }
+}
+
+#[rustfmt::skip]
+prop_compose! {
+    fn arb_multilinestring()(
+	line_strings in prop::collection::vec(arb_linestring(), 1..4)) -> MultiLineString<f64> {
+	MultiLineString(line_strings)
+    }
+}
+
+// libgeos only promises sensible answers for valid MultiPolygons, so
+// we drop each member into its own cell of a 4x4 grid over our
+// [-1, 1] square and keep it small enough that members never touch.
+#[rustfmt::skip]
+prop_compose! {
+    fn arb_multipoly()(
+	cells in prop::sample::subsequence((0..16).collect::<Vec<usize>>(), 1..4),
+	shapes in prop::collection::vec((3..17usize, 0.000001..0.2), 4)) -> MultiPolygon<f64> {
+	MultiPolygon(cells
+		     .iter()
+		     .zip(shapes.iter())
+		     .map(|(cell, (exterior_points, radius))| {
+			 let center = Point::new(
+			     -0.75 + 0.5 * ((cell % 4) as f64),
+			     -0.75 + 0.5 * ((cell / 4) as f64));
+			 regular_polygon(center, *exterior_points, *radius)
+		     })
+		     .collect())
+    }
+}
+
 fn geo_strat() -> impl Strategy<Value = Geometry<f64>> {
     prop_oneof![
         arb_point().prop_map(Geometry::Point),
@@ -82,10 +130,67 @@ fn geo_strat() -> impl Strategy<Value = Geometry<f64>> {
         arb_poly().prop_map(Geometry::Polygon),
         arb_linestring().prop_map(Geometry::LineString),
         arb_rect().prop_map(Geometry::Rect),
-        arb_triangle().prop_map(Geometry::Triangle)
+        arb_triangle().prop_map(Geometry::Triangle),
+        arb_multipoint().prop_map(Geometry::MultiPoint),
+        arb_multilinestring().prop_map(Geometry::MultiLineString),
+        arb_multipoly().prop_map(Geometry::MultiPolygon)
+    ]
+}
+
+// Uniform coordinates almost never put one shape on another's
+// boundary, so touching and covering hardly ever come up. For the
+// predicates that hinge on boundaries we also build shapes out of the
+// points of a coarse grid, where shared vertices and edges are common.
+#[rustfmt::skip]
+prop_compose! {
+    fn grid_point()(x in 0..5i32, y in 0..5i32) -> Point<f64> {
+	Point::new(f64::from(x) / 2. - 1., f64::from(y) / 2. - 1.)
+    }
+}
+
+// Twice the signed area of the triangle abc.
+fn area2(a: Point<f64>, b: Point<f64>, c: Point<f64>) -> f64 {
+    (b.x() - a.x()) * (c.y() - a.y()) - (c.x() - a.x()) * (b.y() - a.y())
+}
+
+fn no_repeats(points: &[Point<f64>]) -> bool {
+    points.windows(2).all(|pair| pair[0] != pair[1])
+}
+
+fn grid_rect() -> impl Strategy<Value = Rect<f64>> {
+    (grid_point(), grid_point())
+        .prop_filter("degenerate rect", |(a, b)| a.x() != b.x() && a.y() != b.y())
+        .prop_map(|(a, b)| Rect::new(a.0, b.0))
+}
+
+fn grid_triangle() -> impl Strategy<Value = Triangle<f64>> {
+    (grid_point(), grid_point(), grid_point())
+        .prop_filter("degenerate triangle", |&(a, b, c)| area2(a, b, c) != 0.)
+        .prop_map(|(a, b, c)| Triangle(a.0, b.0, c.0))
+}
+
+fn grid_geo_strat() -> impl Strategy<Value = Geometry<f64>> {
+    prop_oneof![
+        grid_point().prop_map(Geometry::Point),
+        (grid_point(), grid_point())
+            .prop_filter("degenerate line", |(a, b)| a != b)
+            .prop_map(|(a, b)| Geometry::Line(Line::new(a, b))),
+        prop::collection::vec(grid_point(), 2..6)
+            .prop_filter("repeated point", |points| no_repeats(points))
+            .prop_map(|points| Geometry::LineString(points.into())),
+        grid_rect().prop_map(Geometry::Rect),
+        grid_rect().prop_map(|r| Geometry::Polygon(r.to_polygon())),
+        grid_triangle().prop_map(Geometry::Triangle),
+        grid_triangle().prop_map(|t| Geometry::Polygon(t.to_polygon())),
+        prop::collection::vec(grid_point(), 1..4)
+            .prop_map(|points| Geometry::MultiPoint(MultiPoint(points))),
     ]
 }
 
+fn relate_strat() -> impl Strategy<Value = Geometry<f64>> {
+    prop_oneof![geo_strat(), grid_geo_strat()]
+}
+
 #[cfg(feature = "parallel")]
 #[rustfmt::skip]
 proptest! {
@@ -95,14 +200,18 @@ proptest! {
 	let serial: Result<SplitGeoSeq, Error> = (&geos).try_into();
 	let parallel: Result<Par<SplitGeoSeq>, Error> = (&geos).try_into();
 	assert_eq!(serial, parallel.map(|p| p.0));
+
+	let serial = SplitGeoSeq::lenient_from(&geos);
+	let parallel = Par::<SplitGeoSeq>::lenient_from(&geos);
+	assert_eq!(serial, (parallel.0.0, parallel.1));
     }
 }
 
 #[rustfmt::skip]
 prop_compose! {
     fn arb_splitgeoseq(n: usize)(
-	geos in prop::collection::vec(geo_strat(), 0..n)) -> SplitGeoSeq {
-	(&geos).try_into().unwrap()
+	geos in prop::collection::vec(geo_strat(), 0..n)) -> SplitGeoSeq<'static> {
+	geos.try_into().unwrap()
     }
 }
 
@@ -113,12 +222,67 @@ proptest! {
 	  small in arb_splitgeoseq(20),
 	  big in arb_splitgeoseq(20),
 	  max_distance in 0.0..4.0) {
-	let expected = slow_prox_map(&small, &big, max_distance);
+	let expected = slow_prox_map(&small, &big, max_distance, DistanceMetric::Euclidean);
 	let config = Config::new().max_distance(max_distance);
 	#[cfg(feature = "parallel")]
 	test_par_prox_map(config, small.clone(), big.clone(), &expected);
 	test_prox_map(config, small, big, &expected);
     }
+
+    #[test]
+    fn banded_prox_map_vs_slow(
+	  small in arb_splitgeoseq(20),
+	  big in arb_splitgeoseq(20),
+	  min_distance in 0.0..2.0,
+	  width in 0.0..2.0) {
+	let max_distance = min_distance + width;
+	let expected: Vec<_> = slow_prox_map(&small, &big, max_distance, DistanceMetric::Euclidean)
+	    .into_iter()
+	    .filter(|row| row.distance >= min_distance)
+	    .collect();
+	let config = Config::new().max_distance(max_distance).min_distance(min_distance);
+	#[cfg(feature = "parallel")]
+	test_par_prox_map(config, small.clone(), big.clone(), &expected);
+	test_prox_map(config, small, big, &expected);
+    }
+
+    #[test]
+    fn own_distances_prox_map_vs_slow(
+	  small in arb_splitgeoseq(20),
+	  big in arb_splitgeoseq(20),
+	  radii in prop::collection::vec(0.0..2.0, 20)) {
+	let expected: Vec<_> = slow_prox_map(&small, &big, 2.0, DistanceMetric::Euclidean)
+	    .into_iter()
+	    .filter(|row| row.distance <= radii[row.small_index])
+	    .collect();
+	#[cfg(feature = "parallel")]
+	{
+	    use rayon::prelude::*;
+	    let si = Config::new().parallel_with_distances(small.clone(), |i| radii[i]).unwrap();
+	    let mut actual: Vec<_> = si.proximity_map(big.clone()).unwrap().collect();
+	    actual.sort();
+	    prop_assert_eq!(&actual, &expected);
+	}
+	let si = Config::new().serial_with_distances(small, |i| radii[i]).unwrap();
+	let mut actual: Vec<_> = si.proximity_map(big).unwrap().collect();
+	actual.sort();
+	prop_assert_eq!(actual, expected);
+    }
+}
+
+fn pattern_strat() -> impl Strategy<Value = RelatePattern> {
+    prop_oneof![
+        prop::sample::select(vec![
+            "T*F**F***",
+            "FF*FF****",
+            "T*****FF*",
+            "F***1****",
+            "212101212"
+        ])
+        .prop_map(String::from),
+        "[TF*012]{9}",
+    ]
+    .prop_map(|pattern| RelatePattern::new(&pattern).unwrap())
 }
 
 fn interaction_strat() -> impl Strategy<Value = Interaction> {
@@ -126,6 +290,14 @@ fn interaction_strat() -> impl Strategy<Value = Interaction> {
         Just(Interaction::Intersects),
         Just(Interaction::Within),
         Just(Interaction::Contains),
+        Just(Interaction::Touches),
+        Just(Interaction::Crosses),
+        Just(Interaction::Overlaps),
+        Just(Interaction::Covers),
+        Just(Interaction::CoveredBy),
+        Just(Interaction::Disjoint),
+        pattern_strat().prop_map(Interaction::Relate),
+        (0.0..2.0).prop_map(Interaction::WithinDistance),
     ]
 }
 
@@ -141,28 +313,341 @@ proptest! {
 	test_par_spatial_join(Config::new(), small.clone(), big.clone(), interaction, &expected);
 	test_spatial_join(Config::new(), small, big, interaction, &expected);
     }
+
+    // Whether the distance fits inside the R-tree buffer or not.
+    #[test]
+    fn within_distance_join_vs_slow(
+	  small in arb_splitgeoseq(20),
+	  big in arb_splitgeoseq(20),
+	  max_distance in 0.0..2.0,
+	  distance in 0.0..2.0) {
+	let interaction = Interaction::WithinDistance(distance);
+	let expected = slow_spatial_join(&small, &big, interaction);
+	let config = Config::new().max_distance(max_distance);
+	#[cfg(feature = "parallel")]
+	test_par_spatial_join(config, small.clone(), big.clone(), interaction, &expected);
+	test_spatial_join(config, small, big, interaction, &expected);
+    }
 }
 
+// Indexes that are never inserted or that get removed twice should
+// come back `None` without disturbing anything.
 #[rustfmt::skip]
 proptest! {
     #[test]
-    fn compare_relates_to_libgeos(
-  	  a in geo_strat(),
-	  b in geo_strat()) {
+    fn insert_remove_vs_slow(
+	  small in prop::collection::vec(geo_strat(), 0..20),
+	  extra in prop::collection::vec(geo_strat(), 0..10),
+	  removed in prop::collection::vec(0..32usize, 0..15),
+	  big in prop::collection::vec(geo_strat(), 0..20),
+	  interaction in interaction_strat(),
+	  max_distance in 0.0..1.0) {
+	let all: Vec<_> = small.iter().chain(extra.iter()).cloned().collect();
+	let (all_sgs, big_sgs): (SplitGeoSeq, SplitGeoSeq) =
+	    ((&all).try_into().unwrap(), (&big).try_into().unwrap());
 
-	fn wkt_str(x: &Geometry<f64>) -> String {
-	    use wkt::ToWkt;
-	    let w = x.to_wkt();
-	    assert_eq!(w.items.len(), 1);
-	    w.items[0].to_string()
+	let mut si = Config::new().max_distance(max_distance).serial(&small).unwrap();
+	for (i, geo) in extra.into_iter().enumerate() {
+	    prop_assert_eq!(si.insert(geo).unwrap(), small.len() + i);
 	}
+	let mut gone = vec![false; all.len()];
+	for index in removed {
+	    let expected = all.get(index).filter(|_| !gone[index]).cloned();
+	    prop_assert_eq!(si.remove(index), expected.clone());
+	    if expected.is_some() {
+		gone[index] = true;
+	    }
+	}
+
+	let expected: Vec<_> = slow_spatial_join(&all_sgs, &big_sgs, interaction)
+	    .into_iter()
+	    .filter(|row| !gone[row.small_index])
+	    .collect();
+	let mut actual: Vec<_> = si.spatial_join(&big, interaction).unwrap().collect();
+	actual.sort();
+	prop_assert_eq!(actual, expected);
 
-	// FIXME: find a way to use lifetime annotations to convince
-	// rustc that the return value doesn't use the borrow at all
-	fn convert(x: &Geometry<f64>) -> geos::Geometry {
-	    geos::Geometry::new_from_wkt(&wkt_str(x)).unwrap()
+	let expected: Vec<_> = slow_prox_map(&all_sgs, &big_sgs, max_distance, DistanceMetric::Euclidean)
+	    .into_iter()
+	    .filter(|row| !gone[row.small_index])
+	    .collect();
+	let mut actual: Vec<_> = si.proximity_map(&big).unwrap().collect();
+	actual.sort();
+	prop_assert_eq!(actual.len(), expected.len());
+	for (a, e) in actual.iter().zip(expected.iter()) {
+	    prop_assert_eq!(a, e);
+	    prop_assert!((a.distance - e.distance).abs() < 1e-10);
 	}
+    }
+}
 
+// `Indexes` should behave just like the Vec it stands for, whatever
+// mix of insertions and removals it sees. `(true, n)` removes the
+// geometry at position n (mod the length) and `(false, n)` pushes a
+// new index n % 3 past the last one handed out.
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn indexes_vs_vec(
+	  start in 0..5usize,
+	  len in 0..20usize,
+	  ops in prop::collection::vec((any::<bool>(), 0..20usize), 0..40)) {
+	let mut indexes = Indexes::Range(start..start + len);
+	let mut expected: Vec<usize> = (start..start + len).collect();
+	let mut next = start + len;
+	for (remove, n) in ops {
+	    if remove && !expected.is_empty() {
+		let position = n % expected.len();
+		prop_assert_eq!(indexes.swap_remove(position), expected.swap_remove(position));
+	    } else {
+		next += n % 3;
+		indexes.push_sparse(next);
+		expected.push(next);
+		next += 1;
+	    }
+	    prop_assert_eq!(indexes.iter().collect::<Vec<_>>(), expected.clone());
+	    for index in 0..next + 1 {
+		prop_assert_eq!(indexes.position(index), expected.iter().position(|&i| i == index));
+	    }
+	}
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn stream_vs_slow(
+	  small in arb_splitgeoseq(50),
+	  big in prop::collection::vec(geo_strat(), 0..20),
+	  interaction in interaction_strat(),
+	  max_distance in 0.0..1.0) {
+	let big_sgs: SplitGeoSeq = (&big).try_into().unwrap();
+	let si = Config::new().max_distance(max_distance).serial(small.clone()).unwrap();
+
+	let mut actual: Vec<_> = si
+	    .spatial_join_stream(big.iter().cloned(), interaction)
+	    .collect::<Result<_, _>>()
+	    .unwrap();
+	actual.sort();
+	prop_assert_eq!(actual, slow_spatial_join(&small, &big_sgs, interaction));
+
+	let mut actual: Vec<_> = si
+	    .proximity_map_stream(big.iter().cloned())
+	    .collect::<Result<_, _>>()
+	    .unwrap();
+	actual.sort();
+	prop_assert_eq!(actual, slow_prox_map(&small, &big_sgs, max_distance, DistanceMetric::Euclidean));
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn self_join_vs_slow(
+	  small in arb_splitgeoseq(50),
+	  interaction in interaction_strat(),
+	  max_distance in 0.0..1.0) {
+	let symmetric = interaction.is_symmetric();
+	let expected: Vec<_> = slow_spatial_join(&small, &small, interaction)
+	    .into_iter()
+	    .filter(|row| if symmetric {
+		row.small_index < row.big_index
+	    } else {
+		row.small_index != row.big_index
+	    })
+	    .collect();
+	let si = Config::new().max_distance(max_distance).serial(small.clone()).unwrap();
+	let mut actual: Vec<_> = si.self_join(interaction).collect();
+	actual.sort();
+	prop_assert_eq!(&actual, &expected);
+
+	let expected: Vec<_> = slow_prox_map(&small, &small, max_distance, DistanceMetric::Euclidean)
+	    .into_iter()
+	    .filter(|row| row.small_index < row.big_index)
+	    .collect();
+	let mut actual_prox: Vec<_> = si.self_proximity_map().collect();
+	actual_prox.sort();
+	prop_assert_eq!(&actual_prox, &expected);
+
+	#[cfg(feature = "parallel")]
+	{
+	    use rayon::prelude::*;
+	    let si = Config::new().max_distance(max_distance).parallel(small).unwrap();
+	    let mut par_actual: Vec<_> = si.self_join(interaction).collect();
+	    par_actual.sort();
+	    prop_assert_eq!(par_actual, actual);
+	    let mut par_actual: Vec<_> = si.self_proximity_map().collect();
+	    par_actual.sort();
+	    prop_assert_eq!(par_actual, actual_prox);
+	}
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn nearest_neighbors_vs_slow(
+	  small in arb_splitgeoseq(100),
+	  big in arb_splitgeoseq(20),
+	  k in 0..5usize,
+	  max_distance in prop::option::of(0.0..1.0)) {
+	let expected = slow_nearest_neighbors(
+	    &small, &big, k, max_distance.unwrap_or(f64::INFINITY), DistanceMetric::Euclidean);
+	#[cfg(feature = "parallel")]
+	test_par_nearest_neighbors(Config::new(), small.clone(), big.clone(), k, max_distance, &expected);
+	test_nearest_neighbors(Config::new(), small, big, k, max_distance, &expected);
+    }
+}
+
+// Our [-1, 1] square is a fine patch of the Earth in degrees, about
+// 220 km across.
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn geodesic_vs_slow(
+	  small in arb_splitgeoseq(20),
+	  big in arb_splitgeoseq(20),
+	  k in 0..5usize,
+	  max_distance in 0.0..400_000.0) {
+	let config = Config::new()
+	    .max_distance(max_distance)
+	    .distance_metric(DistanceMetric::Geodesic);
+	let expected = slow_prox_map(&small, &big, max_distance, DistanceMetric::Geodesic);
+	#[cfg(feature = "parallel")]
+	test_par_prox_map(config, small.clone(), big.clone(), &expected);
+	test_prox_map(config, small.clone(), big.clone(), &expected);
+
+	let expected = slow_nearest_neighbors(
+	    &small, &big, k, max_distance, DistanceMetric::Geodesic);
+	#[cfg(feature = "parallel")]
+	test_par_nearest_neighbors(config, small.clone(), big.clone(), k, Some(max_distance), &expected);
+	test_nearest_neighbors(config, small, big, k, Some(max_distance), &expected);
+    }
+}
+
+// Moving everything in our [-1, 1] square 180 degrees east, so it
+// straddles the antimeridian, shouldn't change any results.
+fn across_antimeridian(geos: &[Geometry<f64>]) -> Vec<Geometry<f64>> {
+    geos.iter()
+        .map(|geo| {
+            geo.map_coords(|&(x, y)| {
+                let x = x + 180.;
+                (if x > 180. { x - 360. } else { x }, y)
+            })
+        })
+        .collect()
+}
+
+fn geographic_interaction_strat() -> impl Strategy<Value = Interaction> {
+    // The intersection matrix predicates are touchier about rounding,
+    // which moving the coordinates introduces.
+    prop_oneof![
+        Just(Interaction::Intersects),
+        Just(Interaction::Within),
+        Just(Interaction::Contains),
+        Just(Interaction::Disjoint),
+    ]
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn geographic_vs_slow(
+	  small in prop::collection::vec(geo_strat(), 0..20),
+	  big in prop::collection::vec(geo_strat(), 0..20),
+	  interaction in geographic_interaction_strat(),
+	  max_distance in 0.0..1.0) {
+	let config = Config::new().max_distance(max_distance).geographic(true);
+	let (small_sgs, big_sgs): (SplitGeoSeq, SplitGeoSeq) =
+	    ((&small).try_into().unwrap(), (&big).try_into().unwrap());
+	let (small, big) = (across_antimeridian(&small), across_antimeridian(&big));
+
+	let expected = slow_spatial_join(&small_sgs, &big_sgs, interaction);
+	let si = config.serial(&small).unwrap();
+	let mut actual: Vec<_> = si.spatial_join(&big, interaction).unwrap().collect();
+	actual.sort();
+	prop_assert_eq!(actual, expected);
+
+	let pairs = |rows: Vec<ProxMapRow>| -> Vec<_> {
+	    rows.into_iter().map(|row| (row.big_index, row.small_index)).collect()
+	};
+	let expected = pairs(slow_prox_map(&small_sgs, &big_sgs, max_distance, DistanceMetric::Euclidean));
+	let mut actual: Vec<_> = si.proximity_map(&big).unwrap().collect();
+	actual.sort();
+	prop_assert_eq!(pairs(actual), expected);
+
+	#[cfg(feature = "parallel")]
+	{
+	    use rayon::prelude::*;
+	    let si = config.parallel(&small).unwrap();
+	    let mut actual: Vec<_> = si.spatial_join(&big, interaction).unwrap().collect();
+	    actual.sort();
+	    prop_assert_eq!(actual, slow_spatial_join(&small_sgs, &big_sgs, interaction));
+	}
+    }
+}
+
+// These hold no matter how the intersection matrix gets computed, so
+// they're a cheap check that doesn't need libgeos.
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn de9im_predicates_agree(
+	a in relate_strat(),
+	b in relate_strat()) {
+	// enum_dispatch! consumes its arguments, hence all the clones.
+	macro_rules! relation {
+	    ($x:ident . $method:ident ( $y:ident )) => {{
+		let (x, y) = ($x.clone(), $y.clone());
+		crate::enum_dispatch!(x, y, x.$method(&y))
+	    }};
+	}
+	prop_assert_eq!(relation!(a.Covers(b)), relation!(b.CoveredBy(a)));
+	prop_assert_eq!(relation!(a.Touches(b)), relation!(b.Touches(a)));
+	prop_assert_eq!(relation!(a.Crosses(b)), relation!(b.Crosses(a)));
+	prop_assert_eq!(relation!(a.Overlaps(b)), relation!(b.Overlaps(a)));
+	prop_assert_eq!(relation!(a.Disjoint(b)), !relation!(a.Intersects(b)));
+	let disjoint = RelatePattern::new("FF*FF****").unwrap();
+	prop_assert_eq!(relation!(a.Disjoint(b)), {
+	    let (x, y) = (a.clone(), b.clone());
+	    crate::enum_dispatch!(x, y, x.relate_matrix(&y).matches(&disjoint))
+	});
+	let covers = ["T*****FF*", "*T****FF*", "***T**FF*", "****T*FF*"]
+	    .iter()
+	    .map(|p| RelatePattern::new(p).unwrap())
+	    .any(|p| {
+		let (x, y) = (a.clone(), b.clone());
+		crate::enum_dispatch!(x, y, x.Relate(&y, &p))
+	    });
+	prop_assert_eq!(relation!(a.Covers(b)), covers);
+	let contains = RelatePattern::new("T*****FF*").unwrap();
+	prop_assert_eq!(relation!(a.Contains(b)), {
+	    let (x, y) = (a.clone(), b.clone());
+	    crate::enum_dispatch!(x, y, x.relate_matrix(&y).matches(&contains))
+	});
+	if relation!(a.Touches(b)) {
+	    prop_assert!(!relation!(a.Overlaps(b)) && !relation!(a.Crosses(b)));
+	}
+    }
+}
+
+fn wkt_str(x: &Geometry<f64>) -> String {
+    use wkt::ToWkt;
+    let w = x.to_wkt();
+    assert_eq!(w.items.len(), 1);
+    w.items[0].to_string()
+}
+
+fn convert(x: &Geometry<f64>) -> geos::Geometry<'static> {
+    geos::Geometry::new_from_wkt(&wkt_str(x)).unwrap()
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn compare_relates_to_libgeos(
+  	  a in relate_strat(),
+	  b in relate_strat()) {
 	let geos_a = convert(&a);
 	let geos_b = convert(&b);
 
@@ -178,10 +663,69 @@ proptest! {
 	    crate::enum_dispatch!(a2, b2, a2.Contains(&b2)),
 	    geos_a.contains(&geos_b).unwrap());
 
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Touches(&b2)),
+	    geos_a.touches(&geos_b).unwrap());
+
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Crosses(&b2)),
+	    geos_a.crosses(&geos_b).unwrap());
+
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Overlaps(&b2)),
+	    geos_a.overlaps(&geos_b).unwrap());
+
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Covers(&b2)),
+	    geos_a.covers(&geos_b).unwrap());
+
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.CoveredBy(&b2)),
+	    geos_a.covered_by(&geos_b).unwrap());
+
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Disjoint(&b2)),
+	    geos_a.disjoint(&geos_b).unwrap());
+
+	// The geos crate doesn't expose GEOSRelate, so we check our
+	// matrices through the patterns behind its named predicates.
+	let within = RelatePattern::new("T*F**F***").unwrap();
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Relate(&b2, &within)),
+	    geos_a.within(&geos_b).unwrap());
+
+	let contains = RelatePattern::new("T*****FF*").unwrap();
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Relate(&b2, &contains)),
+	    geos_a.contains(&geos_b).unwrap());
+
+	let equals = RelatePattern::new("T*F**FFF*").unwrap();
+	let a2 = a.clone();
+	let b2 = b.clone();
+	assert_eq!(
+	    crate::enum_dispatch!(a2, b2, a2.Relate(&b2, &equals)),
+	    geos_a.equals(&geos_b).unwrap());
+
 	// I don't want to test distance checks for stuff that we
 	// didn't write because I keep finding issues that I don't
 	// feel like writing up.
-	if match &a {Geometry::Rect(_) | Geometry::Triangle(_) => true, _ => false} {
+	if matches!(&a, Geometry::Rect(_) | Geometry::Triangle(_)) {
 	    let a2 = a.clone();
 	    let b2 = b.clone();
 	    let relates_dist = crate::enum_dispatch!(a2, b2, a2.EuclideanDistance(&b2));
@@ -197,3 +741,180 @@ proptest! {
 	}
     }
 }
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn compare_overlap_to_libgeos(
+	  small in geo_strat(),
+	  big in geo_strat()) {
+	let si = Config::new().serial(vec![small.clone()]).unwrap();
+	let rows: Vec<_> = si.spatial_join_with_overlap(vec![big.clone()]).unwrap().collect();
+	let (geos_small, geos_big) = (convert(&small), convert(&big));
+	if !geos_small.intersects(&geos_big).unwrap() {
+	    prop_assert!(rows.is_empty());
+	    return Ok(());
+	}
+	prop_assert_eq!(rows.len(), 1);
+	let shared = geos_small.intersection(&geos_big).unwrap();
+	prop_assert!(approx::relative_eq!(rows[0].area, shared.area().unwrap(),
+					  epsilon = 1e-9, max_relative = 1e-6),
+		     "area {} != {}", rows[0].area, shared.area().unwrap());
+	// libgeos measures the perimeter of an areal intersection.
+	let areal = |geo: &Geometry<f64>| matches!(
+	    geo,
+	    Geometry::Polygon(_) | Geometry::Rect(_) | Geometry::Triangle(_) | Geometry::MultiPolygon(_));
+	if !(areal(&small) && areal(&big)) {
+	    prop_assert!(approx::relative_eq!(rows[0].length, shared.length().unwrap(),
+					      epsilon = 1e-9, max_relative = 1e-6),
+			 "length {} != {}", rows[0].length, shared.length().unwrap());
+	}
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn counts_vs_join(
+	  small in prop::collection::vec(geo_strat(), 0..30),
+	  big in prop::collection::vec(geo_strat(), 0..30),
+	  interaction in interaction_strat()) {
+	let si = Config::new().serial(&small).unwrap();
+	let mut expected = vec![0; small.len()];
+	for row in si.spatial_join(&big, interaction).unwrap() {
+	    expected[row.small_index] += 1;
+	}
+	prop_assert_eq!(si.count_by_small(&big, interaction).unwrap(), expected.clone());
+
+	#[cfg(feature = "parallel")]
+	{
+	    let si = Config::new().parallel(&small).unwrap();
+	    prop_assert_eq!(si.count_by_small(&big, interaction).unwrap(), expected);
+	}
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn semi_anti_vs_join(
+	  small in prop::collection::vec(geo_strat(), 0..30),
+	  big in prop::collection::vec(geo_strat(), 0..30),
+	  interaction in interaction_strat()) {
+	let si = Config::new().serial(&small).unwrap();
+	let mut matched: Vec<_> = si.spatial_join(&big, interaction).unwrap().map(|row| row.big_index).collect();
+	matched.sort();
+	matched.dedup();
+	let unmatched: Vec<_> = (0..big.len()).filter(|i| matched.binary_search(i).is_err()).collect();
+
+	let mut semi: Vec<_> = si.big_semi_join(&big, interaction).unwrap().collect();
+	semi.sort();
+	prop_assert_eq!(&semi, &matched);
+	let mut anti: Vec<_> = si.big_anti_join(&big, interaction).unwrap().collect();
+	anti.sort();
+	prop_assert_eq!(&anti, &unmatched);
+	let mut small_matched: Vec<_> = si.spatial_join(&big, interaction).unwrap().map(|row| row.small_index).collect();
+	small_matched.sort();
+	small_matched.dedup();
+	prop_assert_eq!(si.small_semi_join(&big, interaction).unwrap().collect::<Vec<_>>(), small_matched.clone());
+
+	#[cfg(feature = "parallel")]
+	{
+	    use rayon::prelude::*;
+	    let si = Config::new().parallel(&small).unwrap();
+	    let mut semi: Vec<_> = si.big_semi_join(&big, interaction).unwrap().collect();
+	    semi.sort();
+	    prop_assert_eq!(semi, matched);
+	    let mut anti: Vec<_> = si.big_anti_join(&big, interaction).unwrap().collect();
+	    anti.sort();
+	    prop_assert_eq!(anti, unmatched);
+	    prop_assert_eq!(si.small_semi_join(&big, interaction).unwrap().collect::<Vec<_>>(), small_matched);
+	}
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn left_vs_join(
+	  small in prop::collection::vec(geo_strat(), 0..30),
+	  big in prop::collection::vec(geo_strat(), 0..30),
+	  interaction in interaction_strat(),
+	  max_distance in 0.0..1.0) {
+	let si = Config::new().max_distance(max_distance).serial(&small).unwrap();
+	let left = |pairs: Vec<(usize, usize)>| -> Vec<(usize, Option<usize>)> {
+	    (0..big.len()).flat_map(|i| {
+		let matches: Vec<_> = pairs.iter().filter(|p| p.0 == i).map(|p| Some(p.1)).collect();
+		if matches.is_empty() { vec![(i, None)] } else { matches.into_iter().map(|m| (i, m)).collect() }
+	    }).collect()
+	};
+
+	let mut pairs: Vec<_> = si.spatial_join(&big, interaction).unwrap().map(|row| (row.big_index, row.small_index)).collect();
+	pairs.sort();
+	let mut actual: Vec<_> = si.spatial_join_left(&big, interaction).unwrap().map(|row| (row.big_index, row.small_index)).collect();
+	actual.sort();
+	prop_assert_eq!(actual, left(pairs));
+
+	let mut pairs: Vec<_> = si.proximity_map(&big).unwrap().map(|row| (row.big_index, row.small_index)).collect();
+	pairs.sort();
+	let mut actual: Vec<_> = si.proximity_map_left(&big).unwrap().map(|row| (row.big_index, row.small_index)).collect();
+	actual.sort();
+	prop_assert_eq!(actual, left(pairs));
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn sorted_vs_join(
+	  small in prop::collection::vec(geo_strat(), 0..30),
+	  big in prop::collection::vec(geo_strat(), 0..30),
+	  interaction in interaction_strat(),
+	  max_distance in 0.0..1.0) {
+	let si = Config::new().max_distance(max_distance).serial(&small).unwrap();
+	let mut expected: Vec<_> = si.spatial_join(&big, interaction).unwrap().collect();
+	expected.sort();
+	let actual: Vec<_> = si.spatial_join_sorted(&big, interaction).unwrap().collect();
+	prop_assert_eq!(&actual, &expected);
+
+	let mut expected_prox: Vec<_> = si.proximity_map(&big).unwrap().collect();
+	expected_prox.sort();
+	let actual: Vec<_> = si.proximity_map_sorted(&big).unwrap().collect();
+	prop_assert_eq!(&actual, &expected_prox);
+
+	#[cfg(feature = "parallel")]
+	{
+	    use rayon::prelude::*;
+	    let si = Config::new().max_distance(max_distance).parallel(&small).unwrap();
+	    let actual: Vec<_> = si.spatial_join_sorted(&big, interaction).unwrap().collect();
+	    prop_assert_eq!(actual, expected);
+	    let actual: Vec<_> = si.proximity_map_sorted(&big).unwrap().collect();
+	    prop_assert_eq!(actual, expected_prox);
+	}
+    }
+}
+
+#[rustfmt::skip]
+proptest! {
+    #[test]
+    fn lookups_vs_join(
+	  small in prop::collection::vec(geo_strat(), 0..30),
+	  big in prop::collection::vec(geo_strat(), 0..10),
+	  interaction in interaction_strat(),
+	  max_distance in 0.0..1.0) {
+	let si = Config::new().max_distance(max_distance).serial(&small).unwrap();
+	let rows: Vec<_> = si.spatial_join_sorted(&big, interaction).unwrap().collect();
+	let prox: Vec<_> = si.proximity_map_sorted(&big).unwrap().collect();
+	for (i, geo) in big.iter().enumerate() {
+	    let expected: Vec<_> = rows.iter().filter(|row| row.big_index == i).map(|row| row.small_index).collect();
+	    let mut actual: Vec<_> = si.query(geo, interaction).unwrap().collect();
+	    actual.sort();
+	    prop_assert_eq!(actual, expected);
+
+	    let expected: Vec<_> = prox.iter().filter(|row| row.big_index == i).map(|row| (row.small_index, row.distance)).collect();
+	    let mut actual: Vec<_> = si.within_distance(geo, max_distance).unwrap().collect();
+	    actual.sort_by_key(|(index, _)| *index);
+	    prop_assert_eq!(actual, expected);
+	}
+    }
+}
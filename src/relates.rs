@@ -1,7 +1,12 @@
+use crate::de9im::{disjoint_matrix, relate, IntersectionMatrix, Topology};
+use crate::{geodesic, CoordFloat, RelatePattern};
 use geo::algorithm::contains::Contains;
 use geo::algorithm::euclidean_distance::EuclideanDistance;
 use geo::algorithm::intersects::Intersects;
-use geo::{Coordinate, Line, LineString, Point, Polygon, Rect, Triangle};
+use geo::{
+    Coordinate, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Rect,
+    Triangle,
+};
 
 // Generated with gen.py; too bad rust macros aren't powerful enough
 // to handle this sort of thing without making another package for a
@@ -9,15 +14,69 @@ use geo::{Coordinate, Line, LineString, Point, Polygon, Rect, Triangle};
 
 // rename Interaction to Relation and put it along with this trait +impls into a relations module
 #[allow(non_snake_case)]
-pub trait Relates<T> {
+pub trait Relates<T: Topology>: Topology {
     // FIXME: explain why we use CamelCase
     fn Contains(&self, other: &T) -> bool;
     fn Intersects(&self, other: &T) -> bool;
     fn EuclideanDistance(&self, other: &T) -> f64;
+
+    // The rest of the DE-9IM predicates don't have hand-tuned
+    // versions; they all go through the intersection matrix. We
+    // check Intersects first since shapes that don't meet can't
+    // touch, cross, overlap or cover each other, and that spares us
+    // the matrix for most of the candidates the R-trees hand us.
+    fn Touches(&self, other: &T) -> bool {
+        self.Intersects(other) && relate(&self.parts(), &other.parts()).is_touches()
+    }
+    fn Crosses(&self, other: &T) -> bool {
+        if !self.Intersects(other) {
+            return false;
+        }
+        let (a, b) = (self.parts(), other.parts());
+        relate(&a, &b).is_crosses(a.dimension(), b.dimension())
+    }
+    fn Overlaps(&self, other: &T) -> bool {
+        if !self.Intersects(other) {
+            return false;
+        }
+        let (a, b) = (self.parts(), other.parts());
+        relate(&a, &b).is_overlaps(a.dimension(), b.dimension())
+    }
+    fn Covers(&self, other: &T) -> bool {
+        self.Intersects(other) && relate(&self.parts(), &other.parts()).is_covers()
+    }
+    fn CoveredBy(&self, other: &T) -> bool {
+        self.Intersects(other) && relate(&self.parts(), &other.parts()).is_covered_by()
+    }
+    fn Disjoint(&self, other: &T) -> bool {
+        !self.Intersects(other)
+    }
+    fn Relate(&self, other: &T, pattern: &RelatePattern) -> bool {
+        if !self.Intersects(other) {
+            return !pattern.needs_intersection() && self.relate_disjoint(other, pattern);
+        }
+        self.relate_matrix(other).matches(pattern)
+    }
+    // Relate for shapes we already know don't meet.
+    fn relate_disjoint(&self, other: &T, pattern: &RelatePattern) -> bool {
+        disjoint_matrix(&self.parts(), &other.parts()).matches(pattern)
+    }
+    fn relate_matrix(&self, other: &T) -> IntersectionMatrix {
+        relate(&self.parts(), &other.parts())
+    }
+    // Meters on the WGS84 ellipsoid, reading coordinates as
+    // longitude and latitude.
+    fn GeodesicDistance(&self, other: &T) -> f64 {
+        if self.Intersects(other) {
+            0.
+        } else {
+            geodesic::distance(&self.parts(), &other.parts())
+        }
+    }
 }
 
 #[allow(clippy::many_single_char_names)]
-fn rect_points(r: &Rect<f64>) -> [Coordinate<f64>; 4] {
+fn rect_points<C: CoordFloat>(r: &Rect<C>) -> [Coordinate<C>; 4] {
     // These points are arranged in clockwise order:
     // b              c
     //  +------------+
@@ -30,13 +89,13 @@ fn rect_points(r: &Rect<f64>) -> [Coordinate<f64>; 4] {
     // a              d
     let a = r.min();
     let c = r.max();
-    let b: Coordinate<f64> = (a.x, c.y).into();
-    let d: Coordinate<f64> = (c.x, a.y).into();
+    let b: Coordinate<C> = (a.x, c.y).into();
+    let d: Coordinate<C> = (c.x, a.y).into();
     [a, b, c, d]
 }
 
 #[allow(clippy::many_single_char_names)]
-fn rect_lines(r: &Rect<f64>) -> [Line<f64>; 4] {
+pub(crate) fn rect_lines<C: CoordFloat>(r: &Rect<C>) -> [Line<C>; 4] {
     let [a, b, c, d] = rect_points(r);
     [
         Line::new(a, b),
@@ -46,4 +105,156 @@ fn rect_lines(r: &Rect<f64>) -> [Line<f64>; 4] {
     ]
 }
 
+// Which side of `line` `c` is on: positive to the left, negative to
+// the right, and zero when it's on the line through `line`.
+fn side<C: CoordFloat>(line: &Line<C>, c: Coordinate<C>) -> C {
+    line.dx() * (c.y - line.start.y) - line.dy() * (c.x - line.start.x)
+}
+
+// Rects and triangles are convex, so with their edges in hand we can
+// place a coordinate by which sides of the edges it falls on. This
+// also sidesteps geo's Triangle::contains, which accepts points on
+// the extension of an edge.
+fn convex_covers<C: CoordFloat>(edges: &[Line<C>], c: Coordinate<C>) -> bool {
+    let (mut left, mut right) = (false, false);
+    for edge in edges {
+        let s = side(edge, c);
+        left |= s > C::zero();
+        right |= s < C::zero();
+    }
+    match (left, right) {
+        (true, true) => false,
+        // Only a flat shape has every edge on one line.
+        (false, false) => edges.iter().any(|edge| edge.intersects(&Point(c))),
+        _ => true,
+    }
+}
+
+fn convex_contains<C: CoordFloat>(edges: &[Line<C>], c: Coordinate<C>) -> bool {
+    edges.iter().all(|edge| side(edge, c) > C::zero())
+        || edges.iter().all(|edge| side(edge, c) < C::zero())
+}
+
+// A segment that's inside a convex shape only misses the interior when
+// it runs along one of the edges.
+fn along_edge<C: CoordFloat>(edges: &[Line<C>], line: &Line<C>) -> bool {
+    edges.iter().any(|edge| {
+        edge.start != edge.end
+            && side(edge, line.start) == C::zero()
+            && side(edge, line.end) == C::zero()
+    })
+}
+
 include!("relates_impl.rs");
+
+// Multi-geometries don't need the hand-tuned bodies that gen.py
+// produces: they relate to everything through their members. A
+// multi-geometry intersects something if any member does, and its
+// distance is the smallest member distance. Containment mostly splits
+// up too: a shape that one member contains, the whole multi-geometry
+// contains, and a shape only one member meets is contained exactly
+// when that member contains it. What's left is a shape straddling
+// members that touch, and that goes to the intersection matrix.
+fn members_contain<M, T, O>(multi: &M, members: &[T], other: &O) -> bool
+where
+    M: Relates<O>,
+    T: Relates<O>,
+    O: Topology,
+{
+    if members.iter().any(|member| member.Contains(other)) {
+        return true;
+    }
+    let mut meeting = members.iter().filter(|member| member.Intersects(other));
+    meeting.next().is_some() && meeting.next().is_some() && multi.relate_matrix(other).is_contains()
+}
+
+// The other way around, the container holds the multi-geometry when it
+// contains every member, and not when a member misses it. A member that
+// meets the container without being inside it might just sit on the
+// boundary, so that's left to the matrix.
+fn contains_members<S, M, T>(container: &S, multi: &M, members: &[T]) -> bool
+where
+    S: Relates<T> + Relates<M>,
+    M: Topology,
+    T: Topology,
+{
+    let mut on_boundary = false;
+    for member in members {
+        if !container.Contains(member) {
+            if !container.Intersects(member) {
+                return false;
+            }
+            on_boundary = true;
+        }
+    }
+    !members.is_empty() && (!on_boundary || container.relate_matrix(multi).is_contains())
+}
+
+macro_rules! multi_relates {
+    ($Multi:ident; $($Other:ident),+) => {
+        $(
+            impl<C: CoordFloat> Relates<$Other<C>> for $Multi<C> {
+                fn Contains(&self, other: &$Other<C>) -> bool {
+                    members_contain(self, &self.0, other)
+                }
+                fn EuclideanDistance(&self, other: &$Other<C>) -> f64 {
+                    self.0
+                        .iter()
+                        .map(|member| member.EuclideanDistance(other))
+                        .fold(f64::INFINITY, f64::min)
+                }
+                fn Intersects(&self, other: &$Other<C>) -> bool {
+                    self.0.iter().any(|member| member.Intersects(other))
+                }
+            }
+
+            impl<C: CoordFloat> Relates<$Multi<C>> for $Other<C> {
+                fn Contains(&self, other: &$Multi<C>) -> bool {
+                    contains_members(self, other, &other.0)
+                }
+                fn EuclideanDistance(&self, other: &$Multi<C>) -> f64 {
+                    other
+                        .0
+                        .iter()
+                        .map(|member| self.EuclideanDistance(member))
+                        .fold(f64::INFINITY, f64::min)
+                }
+                fn Intersects(&self, other: &$Multi<C>) -> bool {
+                    other.0.iter().any(|member| self.Intersects(member))
+                }
+            }
+        )+
+    };
+}
+
+multi_relates!(MultiPoint; Point, Line, LineString, Polygon, Rect, Triangle);
+multi_relates!(MultiLineString; Point, Line, LineString, Polygon, Rect, Triangle);
+multi_relates!(MultiPolygon; Point, Line, LineString, Polygon, Rect, Triangle);
+
+// Between two multi-geometries, every member on the contained side
+// still has to meet the container, which rules most pairs out cheaply.
+macro_rules! multi_multi_relates {
+    ($Multi:ident; $($Other:ident),+) => {
+        $(
+            impl<C: CoordFloat> Relates<$Other<C>> for $Multi<C> {
+                fn Contains(&self, other: &$Other<C>) -> bool {
+                    other.0.iter().all(|member| self.Intersects(member))
+                        && members_contain(self, &self.0, other)
+                }
+                fn EuclideanDistance(&self, other: &$Other<C>) -> f64 {
+                    self.0
+                        .iter()
+                        .map(|member| member.EuclideanDistance(other))
+                        .fold(f64::INFINITY, f64::min)
+                }
+                fn Intersects(&self, other: &$Other<C>) -> bool {
+                    self.0.iter().any(|member| member.Intersects(other))
+                }
+            }
+        )+
+    };
+}
+
+multi_multi_relates!(MultiPoint; MultiPoint, MultiLineString, MultiPolygon);
+multi_multi_relates!(MultiLineString; MultiPoint, MultiLineString, MultiPolygon);
+multi_multi_relates!(MultiPolygon; MultiPoint, MultiLineString, MultiPolygon);
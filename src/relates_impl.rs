@@ -1,185 +1,237 @@
-impl Relates<Line<f64>> for Point<f64> {
-    fn Contains(&self, _other: &Line<f64>) -> bool {
+impl<C: CoordFloat> Relates<Line<C>> for Point<C> {
+    fn Contains(&self, _other: &Line<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Line<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Line<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Line<f64>) -> bool {
+    fn Intersects(&self, other: &Line<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<LineString<f64>> for Point<f64> {
-    fn Contains(&self, _other: &LineString<f64>) -> bool {
+impl<C: CoordFloat> Relates<LineString<C>> for Point<C> {
+    fn Contains(&self, _other: &LineString<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &LineString<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &LineString<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &LineString<f64>) -> bool {
+    fn Intersects(&self, other: &LineString<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Polygon<f64>> for Point<f64> {
-    fn Contains(&self, _other: &Polygon<f64>) -> bool {
+impl<C: CoordFloat> Relates<Polygon<C>> for Point<C> {
+    fn Contains(&self, _other: &Polygon<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Polygon<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Polygon<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Polygon<f64>) -> bool {
+    fn Intersects(&self, other: &Polygon<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Rect<f64>> for Point<f64> {
-    fn Contains(&self, _other: &Rect<f64>) -> bool {
+impl<C: CoordFloat> Relates<Rect<C>> for Point<C> {
+    fn Contains(&self, _other: &Rect<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Rect<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Rect<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Rect<f64>) -> bool {
+    fn Intersects(&self, other: &Rect<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Triangle<f64>> for Point<f64> {
-    fn Contains(&self, _other: &Triangle<f64>) -> bool {
+impl<C: CoordFloat> Relates<Triangle<C>> for Point<C> {
+    fn Contains(&self, _other: &Triangle<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Triangle<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Triangle<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Triangle<f64>) -> bool {
+    fn Intersects(&self, other: &Triangle<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Polygon<f64>> for Line<f64> {
-    fn Contains(&self, _other: &Polygon<f64>) -> bool {
+impl<C: CoordFloat> Relates<Polygon<C>> for Line<C> {
+    fn Contains(&self, _other: &Polygon<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Polygon<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Polygon<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Polygon<f64>) -> bool {
+    fn Intersects(&self, other: &Polygon<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<Rect<f64>> for Line<f64> {
-    fn Contains(&self, _other: &Rect<f64>) -> bool {
+impl<C: CoordFloat> Relates<Rect<C>> for Line<C> {
+    fn Contains(&self, _other: &Rect<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Rect<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Rect<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Rect<f64>) -> bool {
+    fn Intersects(&self, other: &Rect<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Triangle<f64>> for Line<f64> {
-    fn Contains(&self, _other: &Triangle<f64>) -> bool {
+impl<C: CoordFloat> Relates<Triangle<C>> for Line<C> {
+    fn Contains(&self, _other: &Triangle<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Triangle<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Triangle<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Triangle<f64>) -> bool {
+    fn Intersects(&self, other: &Triangle<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Polygon<f64>> for LineString<f64> {
-    fn Contains(&self, _other: &Polygon<f64>) -> bool {
+impl<C: CoordFloat> Relates<Polygon<C>> for LineString<C> {
+    fn Contains(&self, _other: &Polygon<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Polygon<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Polygon<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Polygon<f64>) -> bool {
-        self.intersects(other)
+    fn Intersects(&self, other: &Polygon<C>) -> bool {
+        other.Intersects(self)
     }
 }
 
-impl Relates<Rect<f64>> for LineString<f64> {
-    fn Contains(&self, _other: &Rect<f64>) -> bool {
+impl<C: CoordFloat> Relates<Rect<C>> for LineString<C> {
+    fn Contains(&self, _other: &Rect<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Rect<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Rect<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Rect<f64>) -> bool {
+    fn Intersects(&self, other: &Rect<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Triangle<f64>> for LineString<f64> {
-    fn Contains(&self, _other: &Triangle<f64>) -> bool {
+impl<C: CoordFloat> Relates<Triangle<C>> for LineString<C> {
+    fn Contains(&self, _other: &Triangle<C>) -> bool {
         false
     }
-    fn EuclideanDistance(&self, other: &Triangle<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Triangle<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Triangle<f64>) -> bool {
+    fn Intersects(&self, other: &Triangle<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<LineString<f64>> for LineString<f64> {
-    fn Contains(&self, other: &LineString<f64>) -> bool {
+impl<C: CoordFloat> Relates<LineString<C>> for LineString<C> {
+    fn Contains(&self, other: &LineString<C>) -> bool {
         other
             .lines()
             .all(|oline| self.lines().any(|sline| sline.Contains(&oline)))
     }
-    fn EuclideanDistance(&self, other: &LineString<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &LineString<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
+    }
+    fn Intersects(&self, other: &LineString<C>) -> bool {
+        self.lines()
+            .any(|sline| other.lines().any(|oline| sline.intersects(&oline)))
+    }
+}
+
+impl<C: CoordFloat> Relates<Point<C>> for Line<C> {
+    fn Contains(&self, other: &Point<C>) -> bool {
+        self.intersects(other) && other.0 != self.start && other.0 != self.end
+    }
+    fn EuclideanDistance(&self, other: &Point<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &LineString<f64>) -> bool {
+    fn Intersects(&self, other: &Point<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<Line<f64>> for Rect<f64> {
-    fn Contains(&self, other: &Line<f64>) -> bool {
-        self.contains(&other.start_point()) && self.contains(&other.end_point())
+impl<C: CoordFloat> Relates<Point<C>> for LineString<C> {
+    fn Contains(&self, other: &Point<C>) -> bool {
+        self.contains(other)
+            && (self.is_closed()
+                || (self.0.first() != Some(&other.0) && self.0.last() != Some(&other.0)))
+    }
+    fn EuclideanDistance(&self, other: &Point<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
+    }
+    fn Intersects(&self, other: &Point<C>) -> bool {
+        self.contains(other)
+    }
+}
+
+impl<C: CoordFloat> Relates<Point<C>> for Rect<C> {
+    fn Contains(&self, other: &Point<C>) -> bool {
+        convex_contains(&rect_lines(self), other.0)
+    }
+    fn EuclideanDistance(&self, other: &Point<C>) -> f64 {
+        if self.Intersects(other) {
+            0.0
+        } else {
+            rect_lines(self)
+                .iter()
+                .map(|sline| sline.euclidean_distance(other).as_f64())
+                .min_by(|a, b| a.partial_cmp(b).unwrap())
+                .unwrap()
+        }
+    }
+    fn Intersects(&self, other: &Point<C>) -> bool {
+        self.contains(other)
+    }
+}
+
+impl<C: CoordFloat> Relates<Line<C>> for Rect<C> {
+    fn Contains(&self, other: &Line<C>) -> bool {
+        let edges = rect_lines(self);
+        convex_covers(&edges, other.start)
+            && convex_covers(&edges, other.end)
+            && !along_edge(&edges, other)
     }
-    fn EuclideanDistance(&self, other: &Line<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Line<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
             rect_lines(self)
                 .iter()
-                .map(|sline| sline.euclidean_distance(other))
+                .map(|sline| sline.euclidean_distance(other).as_f64())
                 .min_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Line<f64>) -> bool {
+    fn Intersects(&self, other: &Line<C>) -> bool {
         self.Contains(other) || rect_lines(self).iter().any(|sline| sline.intersects(other))
     }
 }
 
-impl Relates<LineString<f64>> for Rect<f64> {
-    fn Contains(&self, other: &LineString<f64>) -> bool {
-        other.points_iter().all(|pt| self.Contains(&pt))
+impl<C: CoordFloat> Relates<LineString<C>> for Rect<C> {
+    fn Contains(&self, other: &LineString<C>) -> bool {
+        let edges = rect_lines(self);
+        other.0.iter().all(|c| convex_covers(&edges, *c))
+            && other.lines().any(|oline| !along_edge(&edges, &oline))
     }
-    fn EuclideanDistance(&self, other: &LineString<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &LineString<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
             rect_lines(self)
                 .iter()
-                .map(|sline| sline.euclidean_distance(other))
+                .map(|sline| sline.euclidean_distance(other).as_f64())
                 .min_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &LineString<f64>) -> bool {
+    fn Intersects(&self, other: &LineString<C>) -> bool {
         self.Contains(other)
             || rect_lines(self)
                 .iter()
@@ -187,13 +239,13 @@ impl Relates<LineString<f64>> for Rect<f64> {
     }
 }
 
-impl Relates<Triangle<f64>> for Rect<f64> {
-    fn Contains(&self, other: &Triangle<f64>) -> bool {
+impl<C: CoordFloat> Relates<Triangle<C>> for Rect<C> {
+    fn Contains(&self, other: &Triangle<C>) -> bool {
         self.contains(&Point(other.0))
             && self.contains(&Point(other.1))
             && self.contains(&Point(other.2))
     }
-    fn EuclideanDistance(&self, other: &Triangle<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Triangle<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
@@ -204,7 +256,7 @@ impl Relates<Triangle<f64>> for Rect<f64> {
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Triangle<f64>) -> bool {
+    fn Intersects(&self, other: &Triangle<C>) -> bool {
         self.Contains(other)
             || rect_lines(self)
                 .iter()
@@ -213,11 +265,11 @@ impl Relates<Triangle<f64>> for Rect<f64> {
     }
 }
 
-impl Relates<Polygon<f64>> for Rect<f64> {
-    fn Contains(&self, other: &Polygon<f64>) -> bool {
-        other.exterior().points_iter().all(|pt| self.Contains(&pt))
+impl<C: CoordFloat> Relates<Polygon<C>> for Rect<C> {
+    fn Contains(&self, other: &Polygon<C>) -> bool {
+        other.exterior().points_iter().all(|pt| self.contains(&pt))
     }
-    fn EuclideanDistance(&self, other: &Polygon<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Polygon<C>) -> f64 {
         if self.Intersects(other.exterior()) {
             0.0
         } else {
@@ -228,60 +280,65 @@ impl Relates<Polygon<f64>> for Rect<f64> {
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Polygon<f64>) -> bool {
+    fn Intersects(&self, other: &Polygon<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<Point<f64>> for Triangle<f64> {
-    fn Contains(&self, other: &Point<f64>) -> bool {
+impl<C: CoordFloat> Relates<Point<C>> for Triangle<C> {
+    fn Contains(&self, other: &Point<C>) -> bool {
         if self.0 == self.1 && self.1 == self.2 {
             self.0 == other.0
         } else {
-            self.contains(other)
+            convex_contains(&self.to_lines(), other.0)
         }
     }
-    fn EuclideanDistance(&self, other: &Point<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Point<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
             self.to_lines()
                 .iter()
-                .map(|sline| sline.euclidean_distance(other))
+                .map(|sline| sline.euclidean_distance(other).as_f64())
                 .min_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Point<f64>) -> bool {
-        self.Contains(other)
+    fn Intersects(&self, other: &Point<C>) -> bool {
+        convex_covers(&self.to_lines(), other.0)
     }
 }
 
-impl Relates<Line<f64>> for Triangle<f64> {
-    fn Contains(&self, other: &Line<f64>) -> bool {
-        self.Contains(&other.start_point()) && self.Contains(&other.end_point())
+impl<C: CoordFloat> Relates<Line<C>> for Triangle<C> {
+    fn Contains(&self, other: &Line<C>) -> bool {
+        let edges = self.to_lines();
+        convex_covers(&edges, other.start)
+            && convex_covers(&edges, other.end)
+            && !along_edge(&edges, other)
     }
-    fn EuclideanDistance(&self, other: &Line<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Line<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
             self.to_lines()
                 .iter()
-                .map(|sline| sline.euclidean_distance(other))
+                .map(|sline| sline.euclidean_distance(other).as_f64())
                 .min_by(|a, b| a.partial_cmp(b).unwrap())
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Line<f64>) -> bool {
+    fn Intersects(&self, other: &Line<C>) -> bool {
         self.Contains(other) || self.to_lines().iter().any(|sline| sline.intersects(other))
     }
 }
 
-impl Relates<LineString<f64>> for Triangle<f64> {
-    fn Contains(&self, other: &LineString<f64>) -> bool {
-        other.lines().all(|line| self.Contains(&line))
+impl<C: CoordFloat> Relates<LineString<C>> for Triangle<C> {
+    fn Contains(&self, other: &LineString<C>) -> bool {
+        let edges = self.to_lines();
+        other.0.iter().all(|c| convex_covers(&edges, *c))
+            && other.lines().any(|oline| !along_edge(&edges, &oline))
     }
-    fn EuclideanDistance(&self, other: &LineString<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &LineString<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
@@ -290,7 +347,7 @@ impl Relates<LineString<f64>> for Triangle<f64> {
                 .map(|sline| {
                     other
                         .lines()
-                        .map(|oline| oline.euclidean_distance(sline))
+                        .map(|oline| oline.euclidean_distance(sline).as_f64())
                         .min_by(|a, b| a.partial_cmp(b).unwrap())
                 })
                 .min_by(|a, b| a.partial_cmp(b).unwrap())
@@ -298,7 +355,7 @@ impl Relates<LineString<f64>> for Triangle<f64> {
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &LineString<f64>) -> bool {
+    fn Intersects(&self, other: &LineString<C>) -> bool {
         self.Contains(other)
             || self
                 .to_lines()
@@ -307,13 +364,12 @@ impl Relates<LineString<f64>> for Triangle<f64> {
     }
 }
 
-impl Relates<Triangle<f64>> for Triangle<f64> {
-    fn Contains(&self, other: &Triangle<f64>) -> bool {
-        self.Contains(&Point(other.0))
-            && self.Contains(&Point(other.1))
-            && self.Contains(&Point(other.2))
+impl<C: CoordFloat> Relates<Triangle<C>> for Triangle<C> {
+    fn Contains(&self, other: &Triangle<C>) -> bool {
+        let edges = self.to_lines();
+        other.to_array().iter().all(|c| convex_covers(&edges, *c))
     }
-    fn EuclideanDistance(&self, other: &Triangle<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Triangle<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
@@ -323,7 +379,7 @@ impl Relates<Triangle<f64>> for Triangle<f64> {
                 .map(|oline| {
                     self.to_lines()
                         .iter()
-                        .map(|sline| sline.euclidean_distance(oline))
+                        .map(|sline| sline.euclidean_distance(oline).as_f64())
                         .min_by(|a, b| a.partial_cmp(b).unwrap())
                 })
                 .min_by(|a, b| a.partial_cmp(b).unwrap())
@@ -331,7 +387,7 @@ impl Relates<Triangle<f64>> for Triangle<f64> {
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Triangle<f64>) -> bool {
+    fn Intersects(&self, other: &Triangle<C>) -> bool {
         self.Contains(other)
             || self
                 .to_lines()
@@ -341,11 +397,12 @@ impl Relates<Triangle<f64>> for Triangle<f64> {
     }
 }
 
-impl Relates<Polygon<f64>> for Triangle<f64> {
-    fn Contains(&self, other: &Polygon<f64>) -> bool {
-        other.exterior().points_iter().all(|pt| self.Contains(&pt))
+impl<C: CoordFloat> Relates<Polygon<C>> for Triangle<C> {
+    fn Contains(&self, other: &Polygon<C>) -> bool {
+        let edges = self.to_lines();
+        other.exterior().0.iter().all(|c| convex_covers(&edges, *c))
     }
-    fn EuclideanDistance(&self, other: &Polygon<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Polygon<C>) -> f64 {
         if self.Intersects(other.exterior()) {
             0.0
         } else {
@@ -356,208 +413,175 @@ impl Relates<Polygon<f64>> for Triangle<f64> {
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Polygon<f64>) -> bool {
-        self.Intersects(other.exterior())
-            || (other.exterior().Contains(self)
-                || if other.interiors().is_empty() {
-                    false
-                } else {
-                    other.interiors().iter().all(|hole| !hole.Contains(self))
-                })
+    fn Intersects(&self, other: &Polygon<C>) -> bool {
+        self.to_polygon().intersects(other)
     }
 }
 
-impl Relates<Rect<f64>> for Triangle<f64> {
-    fn Contains(&self, other: &Rect<f64>) -> bool {
-        rect_lines(other).iter().all(|line| self.Contains(line))
+impl<C: CoordFloat> Relates<Rect<C>> for Triangle<C> {
+    fn Contains(&self, other: &Rect<C>) -> bool {
+        let edges = self.to_lines();
+        rect_points(other).iter().all(|c| convex_covers(&edges, *c))
     }
-    fn EuclideanDistance(&self, other: &Rect<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Rect<C>) -> f64 {
         other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Rect<f64>) -> bool {
+    fn Intersects(&self, other: &Rect<C>) -> bool {
         other.Intersects(self)
     }
 }
 
-impl Relates<Rect<f64>> for Polygon<f64> {
-    fn Contains(&self, other: &Rect<f64>) -> bool {
-        rect_lines(other).iter().all(|line| self.contains(line))
+impl<C: CoordFloat> Relates<Line<C>> for Polygon<C> {
+    fn Contains(&self, other: &Line<C>) -> bool {
+        self.contains(other)
+            || (self.Intersects(&other.start_point())
+                && self.Intersects(&other.end_point())
+                && self.relate_matrix(other).is_contains())
     }
-    fn EuclideanDistance(&self, other: &Rect<f64>) -> f64 {
-        other.EuclideanDistance(self)
+    fn EuclideanDistance(&self, other: &Line<C>) -> f64 {
+        if self.intersects(other) {
+            0.0
+        } else {
+            self.exterior()
+                .lines()
+                .map(|sline| sline.euclidean_distance(other).as_f64())
+                .min_by(|a, b| a.partial_cmp(b).unwrap())
+                .unwrap()
+        }
     }
-    fn Intersects(&self, other: &Rect<f64>) -> bool {
+    fn Intersects(&self, other: &Line<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<Triangle<f64>> for Polygon<f64> {
-    fn Contains(&self, other: &Triangle<f64>) -> bool {
-        self.contains(&Point(other.0))
-            && self.contains(&Point(other.1))
-            && self.contains(&Point(other.2))
-    }
-    fn EuclideanDistance(&self, other: &Triangle<f64>) -> f64 {
-        other.EuclideanDistance(self)
-    }
-    fn Intersects(&self, other: &Triangle<f64>) -> bool {
-        other.Intersects(self)
-    }
-}
-
-impl Relates<Point<f64>> for Point<f64> {
-    fn Contains(&self, other: &Point<f64>) -> bool {
+impl<C: CoordFloat> Relates<LineString<C>> for Polygon<C> {
+    fn Contains(&self, other: &LineString<C>) -> bool {
         self.contains(other)
+            || (other.points_iter().all(|pt| self.Intersects(&pt))
+                && self.relate_matrix(other).is_contains())
     }
-    fn EuclideanDistance(&self, other: &Point<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &LineString<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Point<f64>) -> bool {
-        self == other
+    fn Intersects(&self, other: &LineString<C>) -> bool {
+        other.lines().any(|oline| self.intersects(&oline))
     }
 }
 
-impl Relates<Point<f64>> for Line<f64> {
-    fn Contains(&self, other: &Point<f64>) -> bool {
+impl<C: CoordFloat> Relates<Polygon<C>> for Polygon<C> {
+    fn Contains(&self, other: &Polygon<C>) -> bool {
         self.contains(other)
+            || (other
+                .exterior()
+                .points_iter()
+                .all(|pt| self.Intersects(&pt))
+                && self.relate_matrix(other).is_contains())
     }
-    fn EuclideanDistance(&self, other: &Point<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Polygon<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Point<f64>) -> bool {
+    fn Intersects(&self, other: &Polygon<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<Line<f64>> for Line<f64> {
-    fn Contains(&self, other: &Line<f64>) -> bool {
-        self.contains(other)
+impl<C: CoordFloat> Relates<Rect<C>> for Polygon<C> {
+    fn Contains(&self, other: &Rect<C>) -> bool {
+        rect_lines(other).iter().all(|line| self.contains(line))
+            || (rect_points(other)
+                .iter()
+                .all(|c| self.Intersects(&Point(*c)))
+                && self.relate_matrix(other).is_contains())
     }
-    fn EuclideanDistance(&self, other: &Line<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Rect<C>) -> f64 {
+        other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &Line<f64>) -> bool {
+    fn Intersects(&self, other: &Rect<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<LineString<f64>> for Line<f64> {
-    fn Contains(&self, other: &LineString<f64>) -> bool {
-        self.contains(other)
+impl<C: CoordFloat> Relates<Triangle<C>> for Polygon<C> {
+    fn Contains(&self, other: &Triangle<C>) -> bool {
+        other.to_lines().iter().all(|line| self.contains(line))
+            || (other.to_array().iter().all(|c| self.Intersects(&Point(*c)))
+                && self.relate_matrix(other).is_contains())
     }
-    fn EuclideanDistance(&self, other: &LineString<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Triangle<C>) -> f64 {
+        other.EuclideanDistance(self)
     }
-    fn Intersects(&self, other: &LineString<f64>) -> bool {
-        self.intersects(other)
+    fn Intersects(&self, other: &Triangle<C>) -> bool {
+        other.Intersects(self)
     }
 }
 
-impl Relates<Point<f64>> for LineString<f64> {
-    fn Contains(&self, other: &Point<f64>) -> bool {
+impl<C: CoordFloat> Relates<Point<C>> for Point<C> {
+    fn Contains(&self, other: &Point<C>) -> bool {
         self.contains(other)
     }
-    fn EuclideanDistance(&self, other: &Point<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Point<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Point<f64>) -> bool {
-        self.contains(other)
+    fn Intersects(&self, other: &Point<C>) -> bool {
+        self == other
     }
 }
 
-impl Relates<Line<f64>> for LineString<f64> {
-    fn Contains(&self, other: &Line<f64>) -> bool {
+impl<C: CoordFloat> Relates<Line<C>> for Line<C> {
+    fn Contains(&self, other: &Line<C>) -> bool {
         self.contains(other)
     }
-    fn EuclideanDistance(&self, other: &Line<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Line<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Line<f64>) -> bool {
+    fn Intersects(&self, other: &Line<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<Point<f64>> for Polygon<f64> {
-    fn Contains(&self, other: &Point<f64>) -> bool {
-        self.contains(other)
-    }
-    fn EuclideanDistance(&self, other: &Point<f64>) -> f64 {
-        self.euclidean_distance(other)
-    }
-    fn Intersects(&self, other: &Point<f64>) -> bool {
-        self.contains(other)
-    }
-}
-
-impl Relates<Line<f64>> for Polygon<f64> {
-    fn Contains(&self, other: &Line<f64>) -> bool {
+impl<C: CoordFloat> Relates<LineString<C>> for Line<C> {
+    fn Contains(&self, other: &LineString<C>) -> bool {
         self.contains(other)
     }
-    fn EuclideanDistance(&self, other: &Line<f64>) -> f64 {
-        if self.intersects(other) {
-            0.0
-        } else {
-            self.exterior()
-                .lines()
-                .map(|sline| sline.euclidean_distance(other))
-                .min_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap()
-        }
+    fn EuclideanDistance(&self, other: &LineString<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &Line<f64>) -> bool {
+    fn Intersects(&self, other: &LineString<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<LineString<f64>> for Polygon<f64> {
-    fn Contains(&self, other: &LineString<f64>) -> bool {
+impl<C: CoordFloat> Relates<Line<C>> for LineString<C> {
+    fn Contains(&self, other: &Line<C>) -> bool {
         self.contains(other)
     }
-    fn EuclideanDistance(&self, other: &LineString<f64>) -> f64 {
-        self.euclidean_distance(other)
+    fn EuclideanDistance(&self, other: &Line<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-    fn Intersects(&self, other: &LineString<f64>) -> bool {
+    fn Intersects(&self, other: &Line<C>) -> bool {
         self.intersects(other)
     }
 }
 
-impl Relates<Polygon<f64>> for Polygon<f64> {
-    fn Contains(&self, other: &Polygon<f64>) -> bool {
+impl<C: CoordFloat> Relates<Point<C>> for Polygon<C> {
+    fn Contains(&self, other: &Point<C>) -> bool {
         self.contains(other)
     }
-    fn EuclideanDistance(&self, other: &Polygon<f64>) -> f64 {
-        self.euclidean_distance(other)
-    }
-    fn Intersects(&self, other: &Polygon<f64>) -> bool {
-        self.intersects(other)
+    fn EuclideanDistance(&self, other: &Point<C>) -> f64 {
+        self.euclidean_distance(other).as_f64()
     }
-}
-
-impl Relates<Point<f64>> for Rect<f64> {
-    fn Contains(&self, other: &Point<f64>) -> bool {
+    fn Intersects(&self, other: &Point<C>) -> bool {
         self.contains(other)
-    }
-    fn EuclideanDistance(&self, other: &Point<f64>) -> f64 {
-        if self.Intersects(other) {
-            0.0
-        } else {
-            rect_lines(self)
-                .iter()
-                .map(|sline| sline.euclidean_distance(other))
-                .min_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap()
-        }
-    }
-    fn Intersects(&self, other: &Point<f64>) -> bool {
-        self.Contains(other)
+            || self.exterior().contains(other)
+            || self.interiors().iter().any(|hole| hole.contains(other))
     }
 }
 
-impl Relates<Rect<f64>> for Rect<f64> {
-    fn Contains(&self, other: &Rect<f64>) -> bool {
+impl<C: CoordFloat> Relates<Rect<C>> for Rect<C> {
+    fn Contains(&self, other: &Rect<C>) -> bool {
         self.contains(other)
     }
-    fn EuclideanDistance(&self, other: &Rect<f64>) -> f64 {
+    fn EuclideanDistance(&self, other: &Rect<C>) -> f64 {
         if self.Intersects(other) {
             0.0
         } else {
@@ -568,7 +592,7 @@ impl Relates<Rect<f64>> for Rect<f64> {
                 .unwrap()
         }
     }
-    fn Intersects(&self, other: &Rect<f64>) -> bool {
+    fn Intersects(&self, other: &Rect<C>) -> bool {
         self.intersects(other)
     }
 }
@@ -1,113 +1,201 @@
 use geo::algorithm::bounding_rect::BoundingRect;
-use geo::{Line, LineString, Point, Polygon, Rect, Triangle};
+use geo::{
+    Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Rect, Triangle,
+};
 use rstar::RTree;
 
-use crate::SplitGeoSeq;
+use crate::antimeridian::{copies, Candidates};
+use crate::structs::{Indexes, Stored};
+use crate::{geodesic, Config, CoordFloat, DistanceMetric, SplitGeoSeq};
 
-type RTreeEnvelope = rstar::AABB<[f64; 2]>;
+pub(crate) type RTreeEnvelope<C = f64> = rstar::AABB<[C; 2]>;
 
-#[derive(Debug)]
-pub struct FakeRegion {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq)]
+pub struct FakeRegion<C: CoordFloat = f64> {
     pub id: usize,
-    pub bbox: RTreeEnvelope,
+    pub bbox: RTreeEnvelope<C>,
+    // How many turns east we moved the geometry to get `bbox`; always
+    // 0 unless `Config::geographic` is set.
+    pub shift: i32,
 }
 
-impl rstar::RTreeObject for FakeRegion {
-    type Envelope = RTreeEnvelope;
+impl<C: CoordFloat> rstar::RTreeObject for FakeRegion<C> {
+    type Envelope = RTreeEnvelope<C>;
 
     fn envelope(&self) -> Self::Envelope {
         self.bbox
     }
 }
 
-impl SplitGeoSeq {
-    pub fn to_rtrees(&self, max_distance: f64) -> [RTree<FakeRegion>; 6] {
-        // Why duplicate? Because bounding_rect isn't defined for
-        // Point and for the geos it is defined for, it sometimes
-        // gives you a Rect and sometimes Option<Rect>
+impl<C: CoordFloat> SplitGeoSeq<'_, C> {
+    pub fn to_rtrees(&self, config: &Config) -> [RTree<FakeRegion<C>>; 9] {
+        self.to_buffered_rtrees(config, &[])
+    }
+
+    pub(crate) fn to_buffered_rtrees(
+        &self,
+        config: &Config,
+        distances: &[f64],
+    ) -> [RTree<FakeRegion<C>>; 9] {
+        let (geos, indexes) = (&self.geos, &self.indexes);
         [
-            RTree::bulk_load(
-                self.geos
-                    .points
-                    .iter()
-                    .enumerate()
-                    .map(|(index, pt)| FakeRegion {
-                        id: index,
-                        bbox: cheap_buffer(pt.to_env(), max_distance),
-                    })
-                    .collect(),
-            ),
-            RTree::bulk_load(
-                self.geos
-                    .lines
-                    .iter()
-                    .enumerate()
-                    .map(|(index, ln)| FakeRegion {
-                        id: index,
-                        bbox: cheap_buffer(ln.to_env(), max_distance),
-                    })
-                    .collect(),
-            ),
-            RTree::bulk_load(
-                self.geos
-                    .polys
-                    .iter()
-                    .enumerate()
-                    .map(|(index, poly)| FakeRegion {
-                        id: index,
-                        bbox: cheap_buffer(poly.to_env(), max_distance),
-                    })
-                    .collect(),
-            ),
-            RTree::bulk_load(
-                self.geos
-                    .line_strings
-                    .iter()
-                    .enumerate()
-                    .map(|(index, ls)| FakeRegion {
-                        id: index,
-                        bbox: cheap_buffer(ls.to_env(), max_distance),
-                    })
-                    .collect(),
-            ),
-            RTree::bulk_load(
-                self.geos
-                    .rects
-                    .iter()
-                    .enumerate()
-                    .map(|(index, rect)| FakeRegion {
-                        id: index,
-                        bbox: cheap_buffer(rect.to_env(), max_distance),
-                    })
-                    .collect(),
-            ),
-            RTree::bulk_load(
-                self.geos
-                    .tris
-                    .iter()
-                    .enumerate()
-                    .map(|(index, tri)| FakeRegion {
-                        id: index,
-                        bbox: cheap_buffer(tri.to_env(), max_distance),
-                    })
-                    .collect(),
+            bulk_load(&geos.points, &indexes.points, config, distances),
+            bulk_load(&geos.lines, &indexes.lines, config, distances),
+            bulk_load(&geos.polys, &indexes.polys, config, distances),
+            bulk_load(&geos.line_strings, &indexes.line_strings, config, distances),
+            bulk_load(&geos.rects, &indexes.rects, config, distances),
+            bulk_load(&geos.tris, &indexes.tris, config, distances),
+            bulk_load(&geos.multi_points, &indexes.multi_points, config, distances),
+            bulk_load(
+                &geos.multi_line_strings,
+                &indexes.multi_line_strings,
+                config,
+                distances,
             ),
+            bulk_load(&geos.multi_polys, &indexes.multi_polys, config, distances),
         ]
     }
 }
 
-pub trait Envelope {
-    fn to_env(&self) -> RTreeEnvelope;
+/// How far to buffer the small geometry with index `index`.
+/// `distances` holds each geometry's own `max_distance`, or is empty
+/// when they all use the config's.
+pub(crate) fn own_distance(config: &Config, distances: &[f64], index: usize) -> f64 {
+    distances.get(index).copied().unwrap_or(config.max_distance)
+}
+
+// We go through Envelope rather than bounding_rect because
+// bounding_rect isn't defined for Point and for the geos it is
+// defined for, it sometimes gives you a Rect and sometimes
+// Option<Rect>.
+fn bulk_load<'a, S>(
+    geos: &[S],
+    indexes: &Indexes,
+    config: &Config,
+    distances: &[f64],
+) -> RTree<FakeRegion<S::Coord>>
+where
+    S: Stored<'a>,
+    S::Geo: Envelope<S::Coord>,
+{
+    let bbox = |index: usize, geo: &S| {
+        let distance = own_distance(config, distances, indexes.get(index));
+        buffer(geo.geo().to_env(), distance, config)
+    };
+    if config.geographic {
+        return RTree::bulk_load(
+            geos.iter()
+                .enumerate()
+                .flat_map(|(index, geo)| {
+                    copies(bbox(index, geo))
+                        .into_iter()
+                        .map(move |(bbox, shift)| FakeRegion {
+                            id: index,
+                            bbox,
+                            shift,
+                        })
+                })
+                .collect(),
+        );
+    }
+    RTree::bulk_load(
+        geos.iter()
+            .enumerate()
+            .map(|(index, geo)| FakeRegion {
+                id: index,
+                bbox: bbox(index, geo),
+                shift: 0,
+            })
+            .collect(),
+    )
+}
+
+// The entries `bulk_load` would make for the geometry at `id`,
+// buffered by `distance`, so we can add and remove them one geometry
+// at a time.
+fn regions<C: CoordFloat, G: Envelope<C>>(
+    id: usize,
+    geo: &G,
+    distance: f64,
+    config: &Config,
+) -> impl Iterator<Item = FakeRegion<C>> {
+    let bbox = buffer(geo.to_env(), distance, config);
+    let copies = if config.geographic {
+        copies(bbox)
+    } else {
+        smallvec::smallvec![(bbox, 0)]
+    };
+    copies
+        .into_iter()
+        .map(move |(bbox, shift)| FakeRegion { id, bbox, shift })
+}
+
+pub(crate) fn insert<C: CoordFloat, G: Envelope<C>>(
+    tree: &mut RTree<FakeRegion<C>>,
+    id: usize,
+    geo: &G,
+    distance: f64,
+    config: &Config,
+) {
+    for fake in regions(id, geo, distance, config) {
+        tree.insert(fake);
+    }
+}
+
+pub(crate) fn remove<C: CoordFloat, G: Envelope<C>>(
+    tree: &mut RTree<FakeRegion<C>>,
+    id: usize,
+    geo: &G,
+    distance: f64,
+    config: &Config,
+) {
+    for fake in regions(id, geo, distance, config) {
+        tree.remove(&fake)
+            .expect("R-tree out of step with the small geometries");
+    }
+}
+
+/// The small geometries whose envelopes in `tree` meet `env`, along
+/// with how many turns east to shift each one to line it up with
+/// `env`. With `Config::geographic` a geometry may show up more than
+/// once; hits then come out sorted.
+pub(crate) fn candidates<'a, C: CoordFloat>(
+    tree: &'a RTree<FakeRegion<C>>,
+    env: &RTreeEnvelope<C>,
+    config: &Config,
+) -> impl Iterator<Item = (usize, i32)> + 'a {
+    if config.geographic {
+        let mut hits = Vec::new();
+        for (query, turns) in copies(*env) {
+            hits.extend(
+                tree.locate_in_envelope_intersecting(&query)
+                    .map(|fake| (fake.id, fake.shift - turns)),
+            );
+        }
+        hits.sort_unstable();
+        hits.dedup();
+        Candidates::Collected(hits.into_iter())
+    } else {
+        Candidates::Direct(
+            tree.locate_in_envelope_intersecting(env)
+                .map(|fake| (fake.id, fake.shift)),
+        )
+    }
+}
+
+pub trait Envelope<C: CoordFloat> {
+    fn to_env(&self) -> RTreeEnvelope<C>;
 }
 
-impl Envelope for Point<f64> {
-    fn to_env(&self) -> RTreeEnvelope {
+impl<C: CoordFloat> Envelope<C> for Point<C> {
+    fn to_env(&self) -> RTreeEnvelope<C> {
         RTreeEnvelope::from_point([self.x(), self.y()])
     }
 }
 
-impl Envelope for Line<f64> {
-    fn to_env(&self) -> RTreeEnvelope {
+impl<C: CoordFloat> Envelope<C> for Line<C> {
+    fn to_env(&self) -> RTreeEnvelope<C> {
         let bounds = self.bounding_rect();
         RTreeEnvelope::from_corners(
             [bounds.min().x, bounds.min().y],
@@ -116,8 +204,8 @@ impl Envelope for Line<f64> {
     }
 }
 
-impl Envelope for Polygon<f64> {
-    fn to_env(&self) -> RTreeEnvelope {
+impl<C: CoordFloat> Envelope<C> for Polygon<C> {
+    fn to_env(&self) -> RTreeEnvelope<C> {
         let bounds = self
             .bounding_rect()
             .expect("invalid bounding_rect for Polygon");
@@ -128,8 +216,8 @@ impl Envelope for Polygon<f64> {
     }
 }
 
-impl Envelope for LineString<f64> {
-    fn to_env(&self) -> RTreeEnvelope {
+impl<C: CoordFloat> Envelope<C> for LineString<C> {
+    fn to_env(&self) -> RTreeEnvelope<C> {
         let bounds = self
             .bounding_rect()
             .expect("invalid bounding_rect for LineString");
@@ -140,14 +228,14 @@ impl Envelope for LineString<f64> {
     }
 }
 
-impl Envelope for Rect<f64> {
-    fn to_env(&self) -> RTreeEnvelope {
+impl<C: CoordFloat> Envelope<C> for Rect<C> {
+    fn to_env(&self) -> RTreeEnvelope<C> {
         RTreeEnvelope::from_corners([self.min().x, self.min().y], [self.max().x, self.max().y])
     }
 }
 
-impl Envelope for Triangle<f64> {
-    fn to_env(&self) -> RTreeEnvelope {
+impl<C: CoordFloat> Envelope<C> for Triangle<C> {
+    fn to_env(&self) -> RTreeEnvelope<C> {
         let bounds = self.bounding_rect();
         RTreeEnvelope::from_corners(
             [bounds.min().x, bounds.min().y],
@@ -156,7 +244,67 @@ impl Envelope for Triangle<f64> {
     }
 }
 
-pub fn cheap_buffer(bbox: RTreeEnvelope, distance: f64) -> RTreeEnvelope {
+macro_rules! multi_envelope {
+    ($Multi:ident) => {
+        impl<C: CoordFloat> Envelope<C> for $Multi<C> {
+            fn to_env(&self) -> RTreeEnvelope<C> {
+                let bounds = self
+                    .bounding_rect()
+                    .expect(concat!("invalid bounding_rect for ", stringify!($Multi)));
+                RTreeEnvelope::from_corners(
+                    [bounds.min().x, bounds.min().y],
+                    [bounds.max().x, bounds.max().y],
+                )
+            }
+        }
+    };
+}
+
+multi_envelope!(MultiPoint);
+multi_envelope!(MultiLineString);
+multi_envelope!(MultiPolygon);
+
+fn buffer<C: CoordFloat>(
+    bbox: RTreeEnvelope<C>,
+    distance: f64,
+    config: &Config,
+) -> RTreeEnvelope<C> {
+    match config.distance_metric {
+        DistanceMetric::Euclidean => cheap_buffer(bbox, C::from_f64(distance)),
+        DistanceMetric::Geodesic => narrow(geodesic::buffer(widen(bbox), distance)),
+    }
+}
+
+// Geodesic edges can stray a little outside the bounding box of
+// their vertices, so we widen the envelopes we search with too.
+pub(crate) fn query_envelope<C: CoordFloat>(
+    env: RTreeEnvelope<C>,
+    config: &Config,
+) -> RTreeEnvelope<C> {
+    match config.distance_metric {
+        DistanceMetric::Euclidean => env,
+        DistanceMetric::Geodesic => narrow(geodesic::buffer(widen(env), 0.)),
+    }
+}
+
+// Searching `reach` further than `max_distance`, which the small
+// envelopes already allow for, means growing the envelope we search
+// with by the difference.
+pub(crate) fn grow<C: CoordFloat>(
+    env: RTreeEnvelope<C>,
+    reach: f64,
+    metric: DistanceMetric,
+) -> RTreeEnvelope<C> {
+    if reach <= 0. {
+        return env;
+    }
+    match metric {
+        DistanceMetric::Euclidean => cheap_buffer(env, C::from_f64(reach)),
+        DistanceMetric::Geodesic => narrow(geodesic::buffer(widen(env), reach)),
+    }
+}
+
+pub fn cheap_buffer<C: CoordFloat>(bbox: RTreeEnvelope<C>, distance: C) -> RTreeEnvelope<C> {
     let lower = bbox.lower();
     let upper = bbox.upper();
     RTreeEnvelope::from_corners(
@@ -164,3 +312,30 @@ pub fn cheap_buffer(bbox: RTreeEnvelope, distance: f64) -> RTreeEnvelope {
         [upper[0] + distance, upper[1] + distance],
     )
 }
+
+// The greatest distance between a point of `a` and a point of `b`,
+// and so an upper bound on the distance between anything inside
+// them.
+pub(crate) fn farthest<C: CoordFloat>(a: &RTreeEnvelope<C>, b: &RTreeEnvelope<C>) -> f64 {
+    let (a, b) = (widen(*a), widen(*b));
+    let span =
+        |axis: usize| (a.upper()[axis] - b.lower()[axis]).max(b.upper()[axis] - a.lower()[axis]);
+    span(0).hypot(span(1))
+}
+
+// The geodesic code works in `f64` whatever the coordinates are.
+pub(crate) fn widen<C: CoordFloat>(env: RTreeEnvelope<C>) -> RTreeEnvelope {
+    let (lower, upper) = (env.lower(), env.upper());
+    RTreeEnvelope::from_corners(
+        [lower[0].as_f64(), lower[1].as_f64()],
+        [upper[0].as_f64(), upper[1].as_f64()],
+    )
+}
+
+fn narrow<C: CoordFloat>(env: RTreeEnvelope) -> RTreeEnvelope<C> {
+    let (lower, upper) = (env.lower(), env.upper());
+    RTreeEnvelope::from_corners(
+        [C::from_f64(lower[0]), C::from_f64(lower[1])],
+        [C::from_f64(upper[0]), C::from_f64(upper[1])],
+    )
+}
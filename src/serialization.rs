@@ -0,0 +1,266 @@
+use std::convert::TryFrom;
+
+use rstar::RTree;
+
+use crate::rtrees::FakeRegion;
+use crate::{Config, CoordFloat, KeyedSpatialIndex, SpatialIndex, SplitGeoSeq};
+#[cfg(feature = "parallel")]
+use crate::{ParKeyedSpatialIndex, ParSpatialIndex};
+
+// serde's own Range impl is a struct with named fields; we write
+// ranges as (start, end) pairs so saved indexes don't depend on how
+// serde spells it.
+pub(crate) mod range {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::ops::Range;
+
+    pub fn serialize<S: Serializer>(
+        range: &Range<usize>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (range.start, range.end).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Range<usize>, D::Error> {
+        let (start, end) = Deserialize::deserialize(deserializer)?;
+        Ok(start..end)
+    }
+}
+
+// geo's serde support covers every geometry type except the Geometry
+// enum itself, so the geometry-carrying rows go through these mirrors
+// of it instead. They keep Geometry's variant names and order,
+// GeometryCollection included, so rows are written just as a
+// Geometry would be, packed formats too, and serializing doesn't
+// clone anything.
+pub(crate) mod geometry_cow {
+    use geo::{
+        Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Rect, Triangle,
+    };
+    use serde::de::{Error, IgnoredAny};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::borrow::Cow;
+
+    use crate::{CoordFloat, GeometryCow};
+
+    #[derive(Serialize)]
+    #[serde(rename = "Geometry")]
+    enum Borrowed<'a, C: CoordFloat> {
+        Point(&'a Point<C>),
+        Line(&'a Line<C>),
+        LineString(&'a LineString<C>),
+        Polygon(&'a Polygon<C>),
+        MultiPoint(&'a MultiPoint<C>),
+        MultiLineString(&'a MultiLineString<C>),
+        MultiPolygon(&'a MultiPolygon<C>),
+        #[allow(dead_code)]
+        GeometryCollection(()),
+        Rect(&'a Rect<C>),
+        Triangle(&'a Triangle<C>),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename = "Geometry")]
+    enum Owned<C: CoordFloat> {
+        Point(Point<C>),
+        Line(Line<C>),
+        LineString(LineString<C>),
+        Polygon(Polygon<C>),
+        MultiPoint(MultiPoint<C>),
+        MultiLineString(MultiLineString<C>),
+        MultiPolygon(MultiPolygon<C>),
+        GeometryCollection(IgnoredAny),
+        Rect(Rect<C>),
+        Triangle(Triangle<C>),
+    }
+
+    pub fn serialize<C, S>(geo: &GeometryCow<C>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        C: CoordFloat + Serialize,
+        S: Serializer,
+    {
+        match geo {
+            GeometryCow::Point(pt) => Borrowed::Point(pt),
+            GeometryCow::Line(ln) => Borrowed::Line(ln),
+            GeometryCow::Polygon(poly) => Borrowed::Polygon(poly),
+            GeometryCow::LineString(ls) => Borrowed::LineString(ls),
+            GeometryCow::Rect(r) => Borrowed::Rect(r),
+            GeometryCow::Triangle(tri) => Borrowed::Triangle(tri),
+            GeometryCow::MultiPoint(mp) => Borrowed::MultiPoint(mp),
+            GeometryCow::MultiLineString(mls) => Borrowed::MultiLineString(mls),
+            GeometryCow::MultiPolygon(mpoly) => Borrowed::MultiPolygon(mpoly),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, 'a, C, D>(deserializer: D) -> Result<GeometryCow<'a, C>, D::Error>
+    where
+        C: CoordFloat + Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(match Owned::deserialize(deserializer)? {
+            Owned::Point(pt) => GeometryCow::Point(pt),
+            Owned::Line(ln) => GeometryCow::Line(ln),
+            Owned::Polygon(poly) => GeometryCow::Polygon(Cow::Owned(poly)),
+            Owned::LineString(ls) => GeometryCow::LineString(Cow::Owned(ls)),
+            Owned::Rect(r) => GeometryCow::Rect(r),
+            Owned::Triangle(tri) => GeometryCow::Triangle(tri),
+            Owned::MultiPoint(mp) => GeometryCow::MultiPoint(Cow::Owned(mp)),
+            Owned::MultiLineString(mls) => GeometryCow::MultiLineString(Cow::Owned(mls)),
+            Owned::MultiPolygon(mpoly) => GeometryCow::MultiPolygon(Cow::Owned(mpoly)),
+            Owned::GeometryCollection(_) => {
+                return Err(D::Error::custom("rows never hold a GeometryCollection"))
+            }
+        })
+    }
+}
+
+// Deserializing a `SpatialIndex` goes through this mirror of its
+// fields so we can check the result before anyone joins against it:
+// the join code trusts the ids in the R-trees and the lengths of
+// everything else, and serialized data can say anything.
+#[derive(serde::Deserialize)]
+#[serde(rename = "SpatialIndex")]
+pub(crate) struct SpatialIndexFields<'a, C: CoordFloat> {
+    small: SplitGeoSeq<'a, C>,
+    point_tree: RTree<FakeRegion<C>>,
+    line_tree: RTree<FakeRegion<C>>,
+    poly_tree: RTree<FakeRegion<C>>,
+    ls_tree: RTree<FakeRegion<C>>,
+    rect_tree: RTree<FakeRegion<C>>,
+    tri_tree: RTree<FakeRegion<C>>,
+    multi_point_tree: RTree<FakeRegion<C>>,
+    multi_ls_tree: RTree<FakeRegion<C>>,
+    multi_poly_tree: RTree<FakeRegion<C>>,
+    distances: Vec<f64>,
+    next_index: usize,
+    config: Config,
+}
+
+impl<'a, C: CoordFloat> TryFrom<SpatialIndexFields<'a, C>> for SpatialIndex<'a, C> {
+    type Error = String;
+
+    fn try_from(fields: SpatialIndexFields<'a, C>) -> Result<Self, String> {
+        let index = SpatialIndex {
+            small: fields.small,
+            point_tree: fields.point_tree,
+            line_tree: fields.line_tree,
+            poly_tree: fields.poly_tree,
+            ls_tree: fields.ls_tree,
+            rect_tree: fields.rect_tree,
+            tri_tree: fields.tri_tree,
+            multi_point_tree: fields.multi_point_tree,
+            multi_ls_tree: fields.multi_ls_tree,
+            multi_poly_tree: fields.multi_poly_tree,
+            distances: fields.distances,
+            next_index: fields.next_index,
+            config: fields.config,
+        };
+        index.check()?;
+        Ok(index)
+    }
+}
+
+// A keyed index's keys are looked up by the small indexes its joins
+// turn up, so there has to be one for every index handed out.
+fn check_keys<C: CoordFloat>(keys: usize, index: &SpatialIndex<C>) -> Result<(), String> {
+    if keys == index.next_index {
+        Ok(())
+    } else {
+        Err(format!("{} keys for {} indexes", keys, index.next_index))
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "KeyedSpatialIndex")]
+pub(crate) struct KeyedSpatialIndexFields<K> {
+    index: SpatialIndex<'static>,
+    keys: Vec<K>,
+}
+
+impl<K> TryFrom<KeyedSpatialIndexFields<K>> for KeyedSpatialIndex<K> {
+    type Error = String;
+
+    fn try_from(fields: KeyedSpatialIndexFields<K>) -> Result<Self, String> {
+        check_keys(fields.keys.len(), &fields.index)?;
+        Ok(KeyedSpatialIndex {
+            index: fields.index,
+            keys: fields.keys,
+        })
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "ParKeyedSpatialIndex")]
+pub(crate) struct ParKeyedSpatialIndexFields<K> {
+    index: ParSpatialIndex<'static>,
+    keys: Vec<K>,
+}
+
+#[cfg(feature = "parallel")]
+impl<K> TryFrom<ParKeyedSpatialIndexFields<K>> for ParKeyedSpatialIndex<K> {
+    type Error = String;
+
+    fn try_from(fields: ParKeyedSpatialIndexFields<K>) -> Result<Self, String> {
+        check_keys(fields.keys.len(), &fields.index.0)?;
+        Ok(ParKeyedSpatialIndex {
+            index: fields.index,
+            keys: fields.keys,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::{Line, Point, Polygon};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Config, GeometryCow, KeyedSpatialIndex, SpatialIndex};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Row<'a>(#[serde(with = "super::geometry_cow")] GeometryCow<'a>);
+
+    #[test]
+    fn geometries() {
+        let poly = Polygon::new(vec![(0., 0.), (1., 1.), (1., 0.)].into(), vec![]);
+        let row = Row((&poly).into());
+        let json = serde_json::to_string(&row).unwrap();
+        assert!(json.starts_with(r#"{"Polygon":"#));
+        assert_eq!(serde_json::from_str::<Row>(&json).unwrap(), row);
+
+        let row = Row(Line::new((0., 0.), (3., 4.)).into());
+        let json = serde_json::to_string(&row).unwrap();
+        assert_eq!(serde_json::from_str::<Row>(&json).unwrap(), row);
+
+        assert!(serde_json::from_str::<Row>(r#"{"GeometryCollection":[]}"#).is_err());
+    }
+
+    #[test]
+    fn indexes_are_checked() {
+        let small = vec![Point::new(1., 1.), Point::new(5., 5.)];
+        let si = Config::new().max_distance(2.).serial(&small).unwrap();
+        let json = serde_json::to_value(&si).unwrap();
+        let back: SpatialIndex = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(back.small, si.small);
+
+        let mut bad = json.clone();
+        bad["next_index"] = 1.into();
+        assert!(serde_json::from_value::<SpatialIndex>(bad).is_err());
+
+        let mut bad = json;
+        bad["distances"] = vec![1.].into();
+        assert!(serde_json::from_value::<SpatialIndex>(bad).is_err());
+
+        let keyed = Config::new()
+            .serial_keyed(vec![("a", Point::new(1., 1.)), ("b", Point::new(5., 5.))])
+            .unwrap();
+        let mut json = serde_json::to_value(&keyed).unwrap();
+        let back: KeyedSpatialIndex<String> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(back.keys(), ["a", "b"]);
+        json["keys"] = vec!["a"].into();
+        assert!(serde_json::from_value::<KeyedSpatialIndex<String>>(json).is_err());
+    }
+}
@@ -1,8 +1,15 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 
-use geo::{Geometry, Line, LineString, Point, Polygon, Rect, Triangle};
+use geo::{
+    Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Rect,
+    Triangle,
+};
 use thiserror::Error;
 
+use crate::CoordFloat;
+
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
@@ -14,23 +21,195 @@ pub enum Error {
     #[error("max_distance must be finite and greater than or equal to zero: {0:?}")]
     BadMaxDistance(f64),
 
+    #[error("min_distance must be finite, at least zero and no more than max_distance: {0:?}")]
+    BadMinDistance(f64),
+
     #[error("LineString at index {0:?} must have at least two points")]
     LineStringTooSmall(usize),
 
     #[error("Polygon at index {0:?} must have an exterior with at least three points")]
     PolygonExteriorTooSmall(usize),
+
+    #[error("Multi-geometry at index {0:?} must have at least one member")]
+    EmptyMultiGeometry(usize),
+
+    #[error("{1} at index {0:?} is not supported")]
+    UnsupportedGeometry(usize, &'static str),
+
+    #[error("DE-9IM patterns must be nine characters from \"TF*012\": {0:?}")]
+    BadRelatePattern(String),
+
+    #[error("I/O error while saving or loading an index: {0}")]
+    Io(String),
+
+    #[error("not a saved index, or one from an incompatible version: {0}")]
+    BadIndexFile(String),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl Error {
+    /// The index of the offending geometry in its input sequence, if
+    /// this error is about a particular geometry.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            Error::BadCoordinateValue(index, _)
+            | Error::LineStringTooSmall(index)
+            | Error::PolygonExteriorTooSmall(index)
+            | Error::EmptyMultiGeometry(index)
+            | Error::UnsupportedGeometry(index, _) => Some(*index),
+            Error::BadMaxDistance(_)
+            | Error::BadMinDistance(_)
+            | Error::BadRelatePattern(_)
+            | Error::Io(_)
+            | Error::BadIndexFile(_) => None,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Interaction {
     Intersects,
     Within,
     Contains,
+    Touches,
+    Crosses,
+    Overlaps,
+    Covers,
+    CoveredBy,
+    Disjoint,
+    Relate(RelatePattern),
+    /// The shapes are no further apart than this, measured with
+    /// `Config::distance_metric`. The distance has to be finite and
+    /// at least zero, or the join fails with `BadMaxDistance`;
+    /// `self_join`, which can't fail, matches nothing instead.
+    WithinDistance(f64),
+}
+
+impl Interaction {
+    // Whether swapping small and big gives the same answer.
+    pub(crate) fn is_symmetric(&self) -> bool {
+        match self {
+            Interaction::Intersects
+            | Interaction::Touches
+            | Interaction::Crosses
+            | Interaction::Overlaps
+            | Interaction::Disjoint
+            | Interaction::WithinDistance(_) => true,
+            Interaction::Within
+            | Interaction::Contains
+            | Interaction::Covers
+            | Interaction::CoveredBy => false,
+            Interaction::Relate(pattern) => *pattern == pattern.transpose(),
+        }
+    }
+
+    pub fn validate(&self) -> Option<Error> {
+        match self {
+            Interaction::WithinDistance(distance) if !(distance.is_finite() && *distance >= 0.) => {
+                Some(Error::BadMaxDistance(*distance))
+            }
+            _ => None,
+        }
+    }
 }
 
+/// A [DE-9IM](https://en.wikipedia.org/wiki/DE-9IM) pattern like
+/// `"T*F**F***"` for use with `Interaction::Relate`.
+///
+/// Entries run row by row (interior, boundary, exterior of `small`)
+/// against the columns (interior, boundary, exterior of `big`). Each
+/// is one of `T` (non-empty), `F` (empty), `*` (anything), or `0`,
+/// `1`, `2` (an intersection of exactly that dimension).
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelatePattern(pub(crate) [u8; 9]);
+
+impl RelatePattern {
+    pub fn new(pattern: &str) -> Result<RelatePattern, Error> {
+        let bytes = pattern.as_bytes();
+        if bytes.len() != 9 {
+            return Err(Error::BadRelatePattern(pattern.to_string()));
+        }
+        let mut entries = [0u8; 9];
+        for (entry, b) in entries.iter_mut().zip(bytes.iter()) {
+            *entry = match b.to_ascii_uppercase() {
+                c @ b'T' | c @ b'F' | c @ b'*' | c @ b'0' | c @ b'1' | c @ b'2' => c,
+                _ => return Err(Error::BadRelatePattern(pattern.to_string())),
+            };
+        }
+        Ok(RelatePattern(entries))
+    }
+
+    // The pattern with small and big swapped.
+    fn transpose(&self) -> RelatePattern {
+        let mut entries = [0u8; 9];
+        for (i, entry) in entries.iter_mut().enumerate() {
+            *entry = self.0[(i % 3) * 3 + i / 3];
+        }
+        RelatePattern(entries)
+    }
+
+    // Patterns that allow an empty intersection between the shapes
+    // can match pairs the R-trees would never suggest.
+    pub(crate) fn needs_intersection(&self) -> bool {
+        // interior/interior, interior/boundary, boundary/interior and
+        // boundary/boundary
+        [0, 1, 3, 4]
+            .iter()
+            .any(|&i| self.0[i] != b'F' && self.0[i] != b'*')
+    }
+}
+
+impl std::convert::TryFrom<String> for RelatePattern {
+    type Error = Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        RelatePattern::new(&pattern)
+    }
+}
+
+impl From<RelatePattern> for String {
+    fn from(pattern: RelatePattern) -> String {
+        pattern.0.iter().map(|&b| b as char).collect()
+    }
+}
+
+impl std::str::FromStr for RelatePattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RelatePattern::new(s)
+    }
+}
+
+/// How proximity maps and nearest-neighbour joins measure distance.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum DistanceMetric {
+    /// Straight-line distance in the plane, in the same units as the
+    /// coordinates.
+    #[default]
+    Euclidean,
+    /// Distance in meters along the WGS84 ellipsoid, reading
+    /// coordinates as longitude and latitude in degrees. Without
+    /// [`Config::geographic`](struct.Config.html#method.geographic),
+    /// the R-trees don't wrap around ±180° longitude, so shapes on
+    /// either side of the antimeridian are never paired up, however
+    /// close they are.
+    Geodesic,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub struct Config {
     pub max_distance: f64,
+    pub min_distance: f64,
+    pub distance_metric: DistanceMetric,
+    pub geographic: bool,
 }
 
 impl Config {
@@ -46,17 +225,55 @@ impl Config {
         }
     }
 
+    /// Leaves pairs closer than `value` out of proximity maps, so
+    /// together with `max_distance` they only report the pairs in a
+    /// distance band. Nearest neighbours and `within_distance` ignore
+    /// it.
+    #[allow(clippy::needless_update)]
+    pub fn min_distance(self, value: f64) -> Config {
+        Config {
+            min_distance: value,
+            ..self
+        }
+    }
+
+    #[allow(clippy::needless_update)]
+    pub fn distance_metric(self, value: DistanceMetric) -> Config {
+        Config {
+            distance_metric: value,
+            ..self
+        }
+    }
+
+    /// Treat coordinates as longitude and latitude so geometries can
+    /// cross the antimeridian and things on either side of it count
+    /// as close together.
+    #[allow(clippy::needless_update)]
+    pub fn geographic(self, value: bool) -> Config {
+        Config {
+            geographic: value,
+            ..self
+        }
+    }
+
     pub fn validate(&self) -> Option<Error> {
         if !(self.max_distance.is_finite() && self.max_distance >= 0.) {
             return Some(Error::BadMaxDistance(self.max_distance));
         }
+        if !(self.min_distance.is_finite()
+            && self.min_distance >= 0.
+            && self.min_distance <= self.max_distance)
+        {
+            return Some(Error::BadMinDistance(self.min_distance));
+        }
 
         None
     }
 
-    pub fn serial<T, U>(self, small: T) -> Result<super::SpatialIndex, Error>
+    pub fn serial<'a, C, T, U>(self, small: T) -> Result<super::SpatialIndex<'a, C>, Error>
     where
-        T: TryInto<SplitGeoSeq, Error = U>,
+        C: CoordFloat,
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
         U: std::any::Any,
     {
         if let Some(error) = self.validate() {
@@ -66,9 +283,10 @@ impl Config {
     }
 
     #[cfg(feature = "parallel")]
-    pub fn parallel<T, U>(self, small: T) -> Result<super::ParSpatialIndex, Error>
+    pub fn parallel<'a, C, T, U>(self, small: T) -> Result<super::ParSpatialIndex<'a, C>, Error>
     where
-        T: TryInto<Par<SplitGeoSeq>, Error = U>,
+        C: CoordFloat,
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
         U: std::any::Any,
     {
         if let Some(error) = self.validate() {
@@ -76,28 +294,207 @@ impl Config {
         }
         super::ParSpatialIndex::new(small, self)
     }
+
+    /// Like [`serial`](#method.serial), but each small geometry gets
+    /// a `max_distance` of its own: `distances(i)` for the geometry
+    /// with index `i`, so `|i| radii[i]` works for a slice. Proximity
+    /// maps then find the pairs within that geometry's distance,
+    /// while `Config::max_distance` only applies to geometries added
+    /// later with `insert`.
+    pub fn serial_with_distances<'a, C, T, U, F>(
+        self,
+        small: T,
+        distances: F,
+    ) -> Result<super::SpatialIndex<'a, C>, Error>
+    where
+        C: CoordFloat,
+        T: TryInto<SplitGeoSeq<'a, C>, Error = U>,
+        U: std::any::Any,
+        F: Fn(usize) -> f64,
+    {
+        if let Some(error) = self.validate() {
+            return Err(error);
+        }
+        super::SpatialIndex::with_distances(small, self, distances)
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn parallel_with_distances<'a, C, T, U, F>(
+        self,
+        small: T,
+        distances: F,
+    ) -> Result<super::ParSpatialIndex<'a, C>, Error>
+    where
+        C: CoordFloat,
+        T: TryInto<Par<SplitGeoSeq<'a, C>>, Error = U>,
+        U: std::any::Any,
+        F: Fn(usize) -> f64,
+    {
+        if let Some(error) = self.validate() {
+            return Err(error);
+        }
+        super::ParSpatialIndex::with_distances(small, self, distances)
+    }
+
+    /// Like [`serial`](#method.serial), but takes `(key, geometry)`
+    /// pairs; joins on the index report those keys instead of
+    /// positions.
+    pub fn serial_keyed<K, G, I>(self, small: I) -> Result<super::KeyedSpatialIndex<K>, Error>
+    where
+        I: IntoIterator<Item = (K, G)>,
+        G: Into<Geometry<f64>>,
+    {
+        let (keys, geos) = unzip_keyed(small);
+        Ok(super::KeyedSpatialIndex {
+            index: self.serial(geos)?,
+            keys,
+        })
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn parallel_keyed<K, G, I>(self, small: I) -> Result<super::ParKeyedSpatialIndex<K>, Error>
+    where
+        I: IntoIterator<Item = (K, G)>,
+        G: Into<Geometry<f64>>,
+    {
+        let (keys, geos) = unzip_keyed(small);
+        Ok(super::ParKeyedSpatialIndex {
+            index: self.parallel(geos)?,
+            keys,
+        })
+    }
+}
+
+pub(crate) fn unzip_keyed<K, G, I>(seq: I) -> (Vec<K>, Vec<Geometry<f64>>)
+where
+    I: IntoIterator<Item = (K, G)>,
+    G: Into<Geometry<f64>>,
+{
+    seq.into_iter().map(|(key, geo)| (key, geo.into())).unzip()
 }
 
 pub struct Par<T>(pub T);
 
+// The heap-bound geometries are held as a `Cow` so that a sequence
+// built from a slice can borrow them from the caller instead of
+// cloning them; the `Copy` ones are cheap enough to copy anyway.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Default, PartialEq, Debug, Clone)]
-pub(crate) struct SplitGeo {
-    pub points: Vec<Point<f64>>,
-    pub lines: Vec<Line<f64>>,
-    pub polys: Vec<Polygon<f64>>,
-    pub line_strings: Vec<LineString<f64>>,
-    pub rects: Vec<Rect<f64>>,
-    pub tris: Vec<Triangle<f64>>,
+pub(crate) struct SplitGeo<'a, C: CoordFloat = f64> {
+    pub points: Vec<Point<C>>,
+    pub lines: Vec<Line<C>>,
+    pub polys: Vec<Cow<'a, Polygon<C>>>,
+    pub line_strings: Vec<Cow<'a, LineString<C>>>,
+    pub rects: Vec<Rect<C>>,
+    pub tris: Vec<Triangle<C>>,
+    pub multi_points: Vec<Cow<'a, MultiPoint<C>>>,
+    pub multi_line_strings: Vec<Cow<'a, MultiLineString<C>>>,
+    pub multi_polys: Vec<Cow<'a, MultiPolygon<C>>>,
+}
+
+/// How `SplitGeo` holds each kind of geometry, so code that works on
+/// every field can get at the geometry whichever way it's held.
+pub(crate) trait Stored<'a> {
+    type Coord: CoordFloat;
+    type Geo;
+
+    fn geo(&self) -> &Self::Geo;
+
+    // Only clones a borrowed geometry, and then only the first time.
+    fn geo_mut(&mut self) -> &mut Self::Geo;
+
+    // The geometry as it goes into a `*_with_geos` row: borrowed if
+    // we borrowed it.
+    fn to_row(&self) -> GeometryCow<'a, Self::Coord>;
+
+    // The geometry as a row borrowing from us, which is how small
+    // geometries come out of an index.
+    fn row_ref(&self) -> GeometryCow<'_, Self::Coord>;
+
+    fn into_geometry(self) -> Geometry<Self::Coord>;
+}
+
+macro_rules! stored_by_value {
+    ($Type:ident) => {
+        impl<'a, C: CoordFloat> Stored<'a> for $Type<C> {
+            type Coord = C;
+            type Geo = $Type<C>;
+
+            fn geo(&self) -> &Self::Geo {
+                self
+            }
+
+            fn geo_mut(&mut self) -> &mut Self::Geo {
+                self
+            }
+
+            fn to_row(&self) -> GeometryCow<'a, C> {
+                GeometryCow::$Type(*self)
+            }
+
+            fn row_ref(&self) -> GeometryCow<'_, C> {
+                GeometryCow::$Type(*self)
+            }
+
+            fn into_geometry(self) -> Geometry<C> {
+                Geometry::$Type(self)
+            }
+        }
+    };
+}
+
+macro_rules! stored_in_cow {
+    ($Type:ident) => {
+        impl<'a, C: CoordFloat> Stored<'a> for Cow<'a, $Type<C>> {
+            type Coord = C;
+            type Geo = $Type<C>;
+
+            fn geo(&self) -> &Self::Geo {
+                self
+            }
+
+            fn geo_mut(&mut self) -> &mut Self::Geo {
+                self.to_mut()
+            }
+
+            fn to_row(&self) -> GeometryCow<'a, C> {
+                GeometryCow::$Type(self.clone())
+            }
+
+            fn row_ref(&self) -> GeometryCow<'_, C> {
+                GeometryCow::$Type(Cow::Borrowed(self))
+            }
+
+            fn into_geometry(self) -> Geometry<C> {
+                Geometry::$Type(self.into_owned())
+            }
+        }
+    };
 }
 
-impl SplitGeoSeq {
-    pub fn merge(mut a: SplitGeoSeq, mut b: SplitGeoSeq) -> SplitGeoSeq {
+stored_by_value!(Point);
+stored_by_value!(Line);
+stored_by_value!(Rect);
+stored_by_value!(Triangle);
+stored_in_cow!(Polygon);
+stored_in_cow!(LineString);
+stored_in_cow!(MultiPoint);
+stored_in_cow!(MultiLineString);
+stored_in_cow!(MultiPolygon);
+
+impl<'a, C: CoordFloat> SplitGeoSeq<'a, C> {
+    pub fn merge(mut a: SplitGeoSeq<'a, C>, mut b: SplitGeoSeq<'a, C>) -> SplitGeoSeq<'a, C> {
         a.geos.points.append(&mut b.geos.points);
         a.geos.lines.append(&mut b.geos.lines);
         a.geos.polys.append(&mut b.geos.polys);
         a.geos.line_strings.append(&mut b.geos.line_strings);
         a.geos.rects.append(&mut b.geos.rects);
         a.geos.tris.append(&mut b.geos.tris);
+        a.geos.multi_points.append(&mut b.geos.multi_points);
+        a.geos
+            .multi_line_strings
+            .append(&mut b.geos.multi_line_strings);
+        a.geos.multi_polys.append(&mut b.geos.multi_polys);
 
         a.indexes.points = a.indexes.points.merge(b.indexes.points);
         a.indexes.lines = a.indexes.lines.merge(b.indexes.lines);
@@ -105,20 +502,82 @@ impl SplitGeoSeq {
         a.indexes.polys = a.indexes.polys.merge(b.indexes.polys);
         a.indexes.rects = a.indexes.rects.merge(b.indexes.rects);
         a.indexes.tris = a.indexes.tris.merge(b.indexes.tris);
+        a.indexes.multi_points = a.indexes.multi_points.merge(b.indexes.multi_points);
+        a.indexes.multi_line_strings = a
+            .indexes
+            .multi_line_strings
+            .merge(b.indexes.multi_line_strings);
+        a.indexes.multi_polys = a.indexes.multi_polys.merge(b.indexes.multi_polys);
 
         a
     }
-}
 
-lazy_static::lazy_static! {
-    static ref EMPTY_VEC: Vec<usize> = vec![];
+    /// A copy of the sequence that borrows the heap-bound geometries
+    /// from this one instead of cloning them.
+    pub fn borrowed(&self) -> SplitGeoSeq<'_, C> {
+        fn borrow<'b, G: Clone>(geos: &'b [Cow<'_, G>]) -> Vec<Cow<'b, G>> {
+            geos.iter().map(|geo| Cow::Borrowed(&**geo)).collect()
+        }
+        SplitGeoSeq {
+            geos: SplitGeo {
+                points: self.geos.points.clone(),
+                lines: self.geos.lines.clone(),
+                polys: borrow(&self.geos.polys),
+                line_strings: borrow(&self.geos.line_strings),
+                rects: self.geos.rects.clone(),
+                tris: self.geos.tris.clone(),
+                multi_points: borrow(&self.geos.multi_points),
+                multi_line_strings: borrow(&self.geos.multi_line_strings),
+                multi_polys: borrow(&self.geos.multi_polys),
+            },
+            indexes: self.indexes.clone(),
+        }
+    }
+
+    /// Clones whatever the sequence borrowed so it no longer depends
+    /// on the data it was built from.
+    pub fn into_owned(self) -> SplitGeoSeq<'static, C> {
+        fn own<G: Clone>(geos: Vec<Cow<'_, G>>) -> Vec<Cow<'static, G>> {
+            geos.into_iter()
+                .map(|geo| Cow::Owned(geo.into_owned()))
+                .collect()
+        }
+        SplitGeoSeq {
+            geos: SplitGeo {
+                points: self.geos.points,
+                lines: self.geos.lines,
+                polys: own(self.geos.polys),
+                line_strings: own(self.geos.line_strings),
+                rects: self.geos.rects,
+                tris: self.geos.tris,
+                multi_points: own(self.geos.multi_points),
+                multi_line_strings: own(self.geos.multi_line_strings),
+                multi_polys: own(self.geos.multi_polys),
+            },
+            indexes: self.indexes,
+        }
+    }
 }
 
 // Sigh...maybe we should just replace this with IDLBitRange
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(PartialEq, Debug, Clone)]
 pub(crate) enum Indexes {
     Explicit(Vec<usize>),
-    Range(std::ops::Range<usize>),
+    Range(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serialization::range"))]
+        std::ops::Range<usize>,
+    ),
+    // What a Range turns into after `swap_remove`: every position
+    // still holds `range.start + position` except the ones in `at`,
+    // and `of` maps those indexes back to their positions. Only the
+    // geometries a removal actually moved cost anything.
+    Moved {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serialization::range"))]
+        range: std::ops::Range<usize>,
+        at: BTreeMap<usize, usize>,
+        of: BTreeMap<usize, usize>,
+    },
 }
 
 impl Default for Indexes {
@@ -126,6 +585,7 @@ impl Default for Indexes {
         Indexes::Range(0..0)
     }
 }
+
 impl Indexes {
     pub fn push(&mut self, index: usize) {
         match self {
@@ -138,73 +598,136 @@ impl Indexes {
             Indexes::Range(r) => {
                 if r.end == index {
                     r.end = index + 1;
+                } else if r.start == r.end {
+                    *r = index..index + 1;
                 } else {
                     let mut v: Vec<usize> = r.collect();
                     v.push(index);
                     *self = Indexes::Explicit(v);
                 }
             }
+            Indexes::Moved { range, at, of } => {
+                if index != range.end {
+                    at.insert(range.len(), index);
+                    of.insert(index, range.len());
+                }
+                range.end += 1;
+            }
+        }
+    }
+
+    /// Like `push`, but a Range that `index` doesn't continue becomes
+    /// `Moved` rather than `Explicit`, so inserting into a built index
+    /// doesn't make finding its geometries linear.
+    pub fn push_sparse(&mut self, index: usize) {
+        if let Indexes::Range(r) = self {
+            if r.end != index && r.start != r.end {
+                *self = Indexes::Moved {
+                    range: r.clone(),
+                    at: BTreeMap::new(),
+                    of: BTreeMap::new(),
+                };
+            }
         }
+        self.push(index)
     }
 
-    fn range(&self) -> std::ops::Range<usize> {
+    pub fn len(&self) -> usize {
         match self {
-            Indexes::Range(r) => r.clone(),
-            _ => std::ops::Range {
-                start: 0 as usize,
-                end: 0 as usize,
-            },
+            Indexes::Explicit(v) => v.len(),
+            Indexes::Range(r) | Indexes::Moved { range: r, .. } => r.len(),
         }
     }
 
-    // The one user for this method is test code but it would be a
-    // pain to extract it to live with the rest of the test code.
+    // The one user for this method outside of tests is checking a
+    // deserialized index, which needs the serde feature.
     #[allow(dead_code)]
     pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
-        self.range().chain(match self {
-            Indexes::Range(_) => EMPTY_VEC.iter().copied(),
-            Indexes::Explicit(v) => v.iter().copied(),
-        })
+        (0..self.len()).map(move |position| self.get(position))
     }
 
     pub fn into_iter(self) -> impl Iterator<Item = usize> {
-        self.range().chain(match self {
-            Indexes::Range(_) => vec![].into_iter(),
-            Indexes::Explicit(v) => v.into_iter(),
-        })
+        (0..self.len()).map(move |position| self.get(position))
     }
 
     #[cfg(feature = "parallel")]
     pub fn into_par_iter(self) -> impl rayon::iter::IndexedParallelIterator<Item = usize> {
-        self.range().into_par_iter().chain(match self {
-            Indexes::Range(_) => vec![].into_par_iter(),
-            Indexes::Explicit(v) => v.into_par_iter(),
-        })
+        (0..self.len())
+            .into_par_iter()
+            .map(move |position| self.get(position))
     }
 
     pub fn get(&self, index: usize) -> usize {
         match self {
             Indexes::Range(r) => index + r.start,
             Indexes::Explicit(v) => v[index],
+            Indexes::Moved { range, at, .. } => *at.get(&index).unwrap_or(&(index + range.start)),
         }
     }
 
-    #[cfg(feature = "parallel")]
-    pub fn add_offset(&mut self, offset: usize) {
-        let new = match self {
-            Indexes::Range(r) => Indexes::Range(if r.start == r.end {
-                r.clone()
-            } else {
-                std::ops::Range {
-                    start: r.start + offset,
-                    end: r.end + offset,
-                }
+    /// Where `index` sits among our geometries, if it's one of ours.
+    /// Linear in the number of indexes for `Explicit`, which only
+    /// turns up when geometries of different types were interleaved
+    /// in the sequence we were built from.
+    pub fn position(&self, index: usize) -> Option<usize> {
+        match self {
+            Indexes::Range(r) if r.contains(&index) => Some(index - r.start),
+            Indexes::Range(_) => None,
+            Indexes::Explicit(v) => v.iter().position(|&i| i == index),
+            Indexes::Moved { range, at, of } => of.get(&index).copied().or_else(|| {
+                index
+                    .checked_sub(range.start)
+                    .filter(|position| *position < range.len() && !at.contains_key(position))
             }),
-            Indexes::Explicit(v) => {
-                Indexes::Explicit(v.iter().map(|value| value + offset).collect())
+        }
+    }
+
+    /// Like `Vec::swap_remove`. Afterwards the indexes may be out of
+    /// order, which only matters to `merge`, and nothing merges the
+    /// indexes of a built `SpatialIndex`.
+    pub fn swap_remove(&mut self, position: usize) -> usize {
+        match self {
+            Indexes::Range(r) if position + 1 == r.len() => {
+                r.end -= 1;
+                r.end
             }
-        };
-        *self = new;
+            Indexes::Range(r) => {
+                *self = Indexes::Moved {
+                    range: r.clone(),
+                    at: BTreeMap::new(),
+                    of: BTreeMap::new(),
+                };
+                self.swap_remove(position)
+            }
+            Indexes::Explicit(v) => v.swap_remove(position),
+            Indexes::Moved { range, at, of } => {
+                let last = range.len() - 1;
+                let get = |position| *at.get(&position).unwrap_or(&(position + range.start));
+                let (removed, moved) = (get(position), get(last));
+                for position in &[last, position] {
+                    if let Some(index) = at.remove(position) {
+                        of.remove(&index);
+                    }
+                }
+                if position != last {
+                    at.insert(position, moved);
+                    of.insert(moved, position);
+                }
+                range.end -= 1;
+                if at.is_empty() {
+                    *self = Indexes::Range(range.clone());
+                }
+                removed
+            }
+        }
+    }
+
+    /// One past the largest index we hold.
+    pub fn end(&self) -> usize {
+        match self {
+            Indexes::Range(r) => r.end,
+            _ => self.iter().max().map_or(0, |i| i + 1),
+        }
     }
 
     pub fn canonicalize(&mut self) {
@@ -275,12 +798,18 @@ impl Indexes {
             }
 
             Indexes::Explicit(if a.end <= b[0] {
-                a.chain(b.into_iter()).collect()
+                a.chain(b).collect()
             } else {
                 b.into_iter().chain(a).collect()
             })
         }
 
+        if let Indexes::Moved { .. } = self {
+            self = Indexes::Explicit(self.iter().collect());
+        }
+        if let Indexes::Moved { .. } = other {
+            other = Indexes::Explicit(other.iter().collect());
+        }
         self.canonicalize();
         other.canonicalize();
         let mut res = match (self, other) {
@@ -288,12 +817,14 @@ impl Indexes {
             (Indexes::Range(a), Indexes::Explicit(b)) => join_range_vec(a, b),
             (Indexes::Explicit(a), Indexes::Range(b)) => join_range_vec(b, a),
             (Indexes::Explicit(a), Indexes::Explicit(b)) => join_vec(a, b),
+            _ => unreachable!(),
         };
         res.canonicalize();
         res
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Default, PartialEq, Debug, Clone)]
 pub(crate) struct SplitGeoIndexes {
     pub points: Indexes,
@@ -302,14 +833,39 @@ pub(crate) struct SplitGeoIndexes {
     pub line_strings: Indexes,
     pub rects: Indexes,
     pub tris: Indexes,
+    pub multi_points: Indexes,
+    pub multi_line_strings: Indexes,
+    pub multi_polys: Indexes,
 }
 
+impl SplitGeoIndexes {
+    pub fn end(&self) -> usize {
+        [
+            &self.points,
+            &self.lines,
+            &self.polys,
+            &self.line_strings,
+            &self.rects,
+            &self.tris,
+            &self.multi_points,
+            &self.multi_line_strings,
+            &self.multi_polys,
+        ]
+        .iter()
+        .map(|indexes| indexes.end())
+        .max()
+        .unwrap_or(0)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Default, PartialEq, Debug, Clone)]
-pub struct SplitGeoSeq {
-    pub(crate) geos: SplitGeo,
+pub struct SplitGeoSeq<'a, C: CoordFloat = f64> {
+    pub(crate) geos: SplitGeo<'a, C>,
     pub(crate) indexes: SplitGeoIndexes,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Debug)]
 pub struct ProxMapRow {
     pub big_index: usize,
@@ -337,65 +893,267 @@ impl Ord for ProxMapRow {
     }
 }
 
+/// A geometry in the rows of the `*_with_geos` joins. Small
+/// geometries borrow from the index, and big ones from your data
+/// when `big` borrowed it, so a row costs no more than a couple of
+/// pointers; `into_owned` or `Geometry::from` gets you an ordinary
+/// `Geometry`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeometryCow<'a, C: CoordFloat = f64> {
+    Point(Point<C>),
+    Line(Line<C>),
+    Polygon(Cow<'a, Polygon<C>>),
+    LineString(Cow<'a, LineString<C>>),
+    Rect(Rect<C>),
+    Triangle(Triangle<C>),
+    MultiPoint(Cow<'a, MultiPoint<C>>),
+    MultiLineString(Cow<'a, MultiLineString<C>>),
+    MultiPolygon(Cow<'a, MultiPolygon<C>>),
+}
+
+impl<C: CoordFloat> GeometryCow<'_, C> {
+    pub fn into_owned(self) -> Geometry<C> {
+        match self {
+            GeometryCow::Point(pt) => Geometry::Point(pt),
+            GeometryCow::Line(ln) => Geometry::Line(ln),
+            GeometryCow::Polygon(poly) => Geometry::Polygon(poly.into_owned()),
+            GeometryCow::LineString(ls) => Geometry::LineString(ls.into_owned()),
+            GeometryCow::Rect(r) => Geometry::Rect(r),
+            GeometryCow::Triangle(tri) => Geometry::Triangle(tri),
+            GeometryCow::MultiPoint(mp) => Geometry::MultiPoint(mp.into_owned()),
+            GeometryCow::MultiLineString(mls) => Geometry::MultiLineString(mls.into_owned()),
+            GeometryCow::MultiPolygon(mpoly) => Geometry::MultiPolygon(mpoly.into_owned()),
+        }
+    }
+}
+
+impl<C: CoordFloat> From<GeometryCow<'_, C>> for Geometry<C> {
+    fn from(geo: GeometryCow<'_, C>) -> Geometry<C> {
+        geo.into_owned()
+    }
+}
+
+macro_rules! geometry_cow_from {
+    ($Type:ident, by_value) => {
+        impl<C: CoordFloat> From<$Type<C>> for GeometryCow<'_, C> {
+            fn from(geo: $Type<C>) -> Self {
+                GeometryCow::$Type(geo)
+            }
+        }
+    };
+    ($Type:ident, in_cow) => {
+        impl<C: CoordFloat> From<$Type<C>> for GeometryCow<'_, C> {
+            fn from(geo: $Type<C>) -> Self {
+                GeometryCow::$Type(Cow::Owned(geo))
+            }
+        }
+
+        impl<'a, C: CoordFloat> From<&'a $Type<C>> for GeometryCow<'a, C> {
+            fn from(geo: &'a $Type<C>) -> Self {
+                GeometryCow::$Type(Cow::Borrowed(geo))
+            }
+        }
+    };
+}
+
+geometry_cow_from!(Point, by_value);
+geometry_cow_from!(Line, by_value);
+geometry_cow_from!(Rect, by_value);
+geometry_cow_from!(Triangle, by_value);
+geometry_cow_from!(Polygon, in_cow);
+geometry_cow_from!(LineString, in_cow);
+geometry_cow_from!(MultiPoint, in_cow);
+geometry_cow_from!(MultiLineString, in_cow);
+geometry_cow_from!(MultiPolygon, in_cow);
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "C: serde::Serialize",
+        deserialize = "C: serde::Deserialize<'de>"
+    ))
+)]
 #[derive(Clone, Debug)]
-pub struct ProxMapGeoRow {
+pub struct ProxMapGeoRow<'a, C: CoordFloat = f64> {
     pub big_index: usize,
     pub small_index: usize,
-    pub big: Geometry<f64>,
-    pub small: Geometry<f64>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::geometry_cow"))]
+    pub big: GeometryCow<'a, C>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::geometry_cow"))]
+    pub small: GeometryCow<'a, C>,
     pub distance: f64,
 }
 
-impl Eq for ProxMapGeoRow {}
+impl<C: CoordFloat> Eq for ProxMapGeoRow<'_, C> {}
 
-impl PartialEq for ProxMapGeoRow {
+impl<C: CoordFloat> PartialEq for ProxMapGeoRow<'_, C> {
     fn eq(&self, other: &Self) -> bool {
         (self.big_index, self.small_index) == (other.big_index, other.small_index)
     }
 }
 
-impl PartialOrd for ProxMapGeoRow {
+impl<C: CoordFloat> PartialOrd for ProxMapGeoRow<'_, C> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for ProxMapGeoRow {
+impl<C: CoordFloat> Ord for ProxMapGeoRow<'_, C> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         (self.big_index, self.small_index).cmp(&(other.big_index, other.small_index))
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, PartialEq, Eq, Debug, PartialOrd, Ord)]
 pub struct SJoinRow {
     pub big_index: usize,
     pub small_index: usize,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "C: serde::Serialize",
+        deserialize = "C: serde::Deserialize<'de>"
+    ))
+)]
+#[derive(Clone, Debug)]
+pub struct SJoinGeoRow<'a, C: CoordFloat = f64> {
+    pub big_index: usize,
+    pub small_index: usize,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::geometry_cow"))]
+    pub big: GeometryCow<'a, C>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::geometry_cow"))]
+    pub small: GeometryCow<'a, C>,
+}
+
+impl<C: CoordFloat> Eq for SJoinGeoRow<'_, C> {}
+
+impl<C: CoordFloat> PartialEq for SJoinGeoRow<'_, C> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.big_index, self.small_index) == (other.big_index, other.small_index)
+    }
+}
+
+impl<C: CoordFloat> PartialOrd for SJoinGeoRow<'_, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: CoordFloat> Ord for SJoinGeoRow<'_, C> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.big_index, self.small_index).cmp(&(other.big_index, other.small_index))
+    }
+}
+
+/// A row of [`spatial_join_with_overlap`](struct.SpatialIndex.html#method.spatial_join_with_overlap):
+/// a pair of intersecting geometries along with what they share.
+/// Areas and lengths are in the units of the coordinates, whatever
+/// `Config::distance_metric` says.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "C: serde::Serialize",
+        deserialize = "C: serde::Deserialize<'de>"
+    ))
+)]
 #[derive(Clone, Debug)]
-pub struct SJoinGeoRow {
+pub struct SJoinOverlapRow<C: CoordFloat = f64> {
     pub big_index: usize,
     pub small_index: usize,
-    pub big: Geometry<f64>,
-    pub small: Geometry<f64>,
+    /// The area of the overlap when both geometries are areal
+    /// (polygons, rects, triangles and their multi versions); zero
+    /// otherwise.
+    pub area: f64,
+    /// When one geometry is lineal and the other isn't a point, the
+    /// lineal one clipped to the other, boundary included; when both
+    /// are lineal, the stretches of `small` that run along `big`.
+    /// Empty otherwise.
+    pub lines: MultiLineString<C>,
+    /// The total length of `lines`.
+    pub length: f64,
 }
 
-impl Eq for SJoinGeoRow {}
+impl<C: CoordFloat> Eq for SJoinOverlapRow<C> {}
 
-impl PartialEq for SJoinGeoRow {
+impl<C: CoordFloat> PartialEq for SJoinOverlapRow<C> {
     fn eq(&self, other: &Self) -> bool {
         (self.big_index, self.small_index) == (other.big_index, other.small_index)
     }
 }
 
-impl PartialOrd for SJoinGeoRow {
+impl<C: CoordFloat> PartialOrd for SJoinOverlapRow<C> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for SJoinGeoRow {
+impl<C: CoordFloat> Ord for SJoinOverlapRow<C> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         (self.big_index, self.small_index).cmp(&(other.big_index, other.small_index))
     }
 }
+
+/// A row of a left join. Every big geometry gets at least one, with
+/// `small_index` set to `None` if it matched nothing.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, PartialOrd, Ord)]
+pub struct SJoinLeftRow {
+    pub big_index: usize,
+    pub small_index: Option<usize>,
+}
+
+/// A row of a left proximity map. Every big geometry gets at least
+/// one; one with nothing in range gets a single row with `None` for
+/// both `small_index` and `distance`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug)]
+pub struct ProxMapLeftRow {
+    pub big_index: usize,
+    pub small_index: Option<usize>,
+    pub distance: Option<f64>,
+}
+
+impl Eq for ProxMapLeftRow {}
+
+impl PartialEq for ProxMapLeftRow {
+    fn eq(&self, other: &Self) -> bool {
+        (self.big_index, self.small_index) == (other.big_index, other.small_index)
+    }
+}
+
+impl PartialOrd for ProxMapLeftRow {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ProxMapLeftRow {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.big_index, self.small_index).cmp(&(other.big_index, other.small_index))
+    }
+}
+
+/// A [`SJoinRow`](struct.SJoinRow.html) from a keyed index, with the
+/// keys of the two geometries in place of their positions.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, PartialOrd, Ord, Hash)]
+pub struct SJoinKeyRow<B, S> {
+    pub big_key: B,
+    pub small_key: S,
+}
+
+/// A [`ProxMapRow`](struct.ProxMapRow.html) from a keyed index, with
+/// the keys of the two geometries in place of their positions.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ProxMapKeyRow<B, S> {
+    pub big_key: B,
+    pub small_key: S,
+    pub distance: f64,
+}
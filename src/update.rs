@@ -0,0 +1,182 @@
+use geo::Geometry;
+use rstar::RTree;
+
+use crate::index::sgs_try_into;
+use crate::rtrees::{self, own_distance, Envelope, FakeRegion};
+use crate::structs::{Indexes, Stored};
+use crate::{Config, CoordFloat, Error, SpatialIndex};
+
+#[cfg(feature = "parallel")]
+use crate::ParSpatialIndex;
+
+// Each small geometry lives at some position in its per-type Vec,
+// which is the id its R-tree entries carry, and `Indexes` maps that
+// position to the index join results report. Removing swaps the last
+// geometry of that type into the hole, so only that one geometry's
+// tree entries need fixing up.
+impl<C: CoordFloat> SpatialIndex<'_, C> {
+    /// Adds `geometry` to the small geometries and returns the index
+    /// join results will use for it. New indexes start one past the
+    /// largest index the index was built with and are never reused,
+    /// so every other geometry keeps its index.
+    pub fn insert<G: Into<Geometry<C>>>(&mut self, geometry: G) -> Result<usize, Error> {
+        self.insert_with_distance(geometry, self.config.max_distance)
+    }
+
+    /// Like [`insert`](#method.insert), but proximity maps look
+    /// `distance` away from the new geometry instead of
+    /// `Config::max_distance`.
+    pub fn insert_with_distance<G: Into<Geometry<C>>>(
+        &mut self,
+        geometry: G,
+        distance: f64,
+    ) -> Result<usize, Error> {
+        if !(distance.is_finite() && distance >= 0.) {
+            return Err(Error::BadMaxDistance(distance));
+        }
+        let one = sgs_try_into(vec![geometry.into()])?.prepare(&self.config);
+        let index = self.next_index;
+        // The first geometry with a distance of its own gives every
+        // other one the config's.
+        if self.distances.is_empty() && distance != self.config.max_distance {
+            self.distances = vec![self.config.max_distance; index];
+        }
+        if !self.distances.is_empty() {
+            self.distances.push(distance);
+        }
+
+        macro_rules! insert {
+            ($field:ident, $tree:ident) => {
+                for geo in one.geos.$field {
+                    insert_one(
+                        &mut self.small.geos.$field,
+                        &mut self.small.indexes.$field,
+                        &mut self.$tree,
+                        geo,
+                        index,
+                        distance,
+                        &self.config,
+                    );
+                }
+            };
+        }
+        insert!(points, point_tree);
+        insert!(lines, line_tree);
+        insert!(polys, poly_tree);
+        insert!(line_strings, ls_tree);
+        insert!(rects, rect_tree);
+        insert!(tris, tri_tree);
+        insert!(multi_points, multi_point_tree);
+        insert!(multi_line_strings, multi_ls_tree);
+        insert!(multi_polys, multi_poly_tree);
+
+        self.next_index += 1;
+        Ok(index)
+    }
+
+    /// Removes the small geometry with index `index` and returns it,
+    /// or `None` if there isn't one. Every other geometry keeps its
+    /// index. Finding the geometry can take time linear in the number
+    /// of small geometries when geometries of different types were
+    /// interleaved in the sequence the index was built from.
+    ///
+    /// What comes back is the geometry as the index stored it, not
+    /// the one you passed in. With `Config::geographic` set, a
+    /// geometry that crossed the antimeridian was stored with its
+    /// western vertices shifted 360° east, and is returned that way.
+    pub fn remove(&mut self, index: usize) -> Option<Geometry<C>> {
+        macro_rules! remove {
+            ($field:ident, $tree:ident) => {
+                if let Some(position) = self.small.indexes.$field.position(index) {
+                    return Some(
+                        remove_one(
+                            &mut self.small.geos.$field,
+                            &mut self.small.indexes.$field,
+                            &mut self.$tree,
+                            position,
+                            &self.distances,
+                            &self.config,
+                        )
+                        .into_geometry(),
+                    );
+                }
+            };
+        }
+        remove!(points, point_tree);
+        remove!(lines, line_tree);
+        remove!(polys, poly_tree);
+        remove!(line_strings, ls_tree);
+        remove!(rects, rect_tree);
+        remove!(tris, tri_tree);
+        remove!(multi_points, multi_point_tree);
+        remove!(multi_line_strings, multi_ls_tree);
+        remove!(multi_polys, multi_poly_tree);
+        None
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<C: CoordFloat> ParSpatialIndex<'_, C> {
+    pub fn insert<G: Into<Geometry<C>>>(&mut self, geometry: G) -> Result<usize, Error> {
+        self.0.insert(geometry)
+    }
+
+    pub fn insert_with_distance<G: Into<Geometry<C>>>(
+        &mut self,
+        geometry: G,
+        distance: f64,
+    ) -> Result<usize, Error> {
+        self.0.insert_with_distance(geometry, distance)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Geometry<C>> {
+        self.0.remove(index)
+    }
+}
+
+fn insert_one<'a, S>(
+    geos: &mut Vec<S>,
+    indexes: &mut Indexes,
+    tree: &mut RTree<FakeRegion<S::Coord>>,
+    geo: S,
+    index: usize,
+    distance: f64,
+    config: &Config,
+) where
+    S: Stored<'a>,
+    S::Geo: Envelope<S::Coord>,
+{
+    rtrees::insert(tree, geos.len(), geo.geo(), distance, config);
+    geos.push(geo);
+    indexes.push_sparse(index);
+}
+
+fn remove_one<'a, S>(
+    geos: &mut Vec<S>,
+    indexes: &mut Indexes,
+    tree: &mut RTree<FakeRegion<S::Coord>>,
+    position: usize,
+    distances: &[f64],
+    config: &Config,
+) -> S
+where
+    S: Stored<'a>,
+    S::Geo: Envelope<S::Coord>,
+{
+    let last = geos.len() - 1;
+    let distance = |position| own_distance(config, distances, indexes.get(position));
+    rtrees::remove(
+        tree,
+        position,
+        geos[position].geo(),
+        distance(position),
+        config,
+    );
+    if position != last {
+        let moved = distance(last);
+        rtrees::remove(tree, last, geos[last].geo(), moved, config);
+        rtrees::insert(tree, position, geos[last].geo(), moved, config);
+    }
+    indexes.swap_remove(position);
+    geos.swap_remove(position)
+}
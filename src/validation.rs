@@ -1,29 +1,49 @@
-use geo::{Geometry, Line, LineString, Point, Polygon, Rect, Triangle};
+use geo::{
+    Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon, Rect,
+    Triangle,
+};
 
-use crate::Error;
+use geo::algorithm::map_coords::MapCoords;
+
+#[cfg(feature = "serde")]
+use rstar::RTree;
+
+#[cfg(feature = "serde")]
+use crate::rtrees::FakeRegion;
+#[cfg(feature = "serde")]
+use crate::structs::{Indexes, Stored};
+#[cfg(feature = "serde")]
+use crate::SpatialIndex;
+use crate::{CoordFloat, Error};
 
 pub(crate) trait IsSafe {
     fn is_safe(&self, position: usize) -> Result<(), Error>;
 }
 
-impl IsSafe for Point<f64> {
+// `Error` isn't generic, so a bad geometry gets reported with `f64`
+// coordinates whatever it had.
+fn bad<C: CoordFloat>(position: usize, geo: Geometry<C>) -> Error {
+    Error::BadCoordinateValue(position, geo.map_coords(|&(x, y)| (x.as_f64(), y.as_f64())))
+}
+
+impl<C: CoordFloat> IsSafe for Point<C> {
     fn is_safe(&self, position: usize) -> Result<(), Error> {
         if self.x().is_finite() && self.y().is_finite() {
             Ok(())
         } else {
-            Err(Error::BadCoordinateValue(position, Geometry::Point(*self)))
+            Err(bad(position, Geometry::Point(*self)))
         }
     }
 }
 
-impl IsSafe for Line<f64> {
+impl<C: CoordFloat> IsSafe for Line<C> {
     fn is_safe(&self, position: usize) -> Result<(), Error> {
         let r = self
             .start_point()
             .is_safe(position)
             .and(self.end_point().is_safe(position));
         if r.is_err() {
-            Err(Error::BadCoordinateValue(position, Geometry::Line(*self)))
+            Err(bad(position, Geometry::Line(*self)))
         } else {
             Ok(())
         }
@@ -36,74 +56,247 @@ impl IsSafe for Line<f64> {
 // ever change those length checks, we have to review all uses of
 // all() in Relates impls.
 
-impl IsSafe for LineString<f64> {
+impl<C: CoordFloat> IsSafe for LineString<C> {
     fn is_safe(&self, position: usize) -> Result<(), Error> {
         if self.0.len() < 2 {
             return Err(Error::LineStringTooSmall(position));
         }
         for pt in self.points_iter() {
             if pt.is_safe(position).is_err() {
-                return Err(Error::BadCoordinateValue(
-                    position,
-                    Geometry::LineString(self.clone()),
-                ));
+                return Err(bad(position, Geometry::LineString(self.clone())));
             }
         }
         Ok(())
     }
 }
 
-impl IsSafe for Rect<f64> {
+impl<C: CoordFloat> IsSafe for Rect<C> {
     fn is_safe(&self, position: usize) -> Result<(), Error> {
-        let min: Point<f64> = self.min().into();
-        let max: Point<f64> = self.max().into();
+        let min: Point<C> = self.min().into();
+        let max: Point<C> = self.max().into();
         let r = min.is_safe(position).and(max.is_safe(position));
         if r.is_err() {
-            Err(Error::BadCoordinateValue(position, Geometry::Rect(*self)))
+            Err(bad(position, Geometry::Rect(*self)))
         } else {
             Ok(())
         }
     }
 }
 
-impl IsSafe for Triangle<f64> {
+impl<C: CoordFloat> IsSafe for Triangle<C> {
     fn is_safe(&self, position: usize) -> Result<(), Error> {
         let [a, b, c] = self.to_array();
-        let a: Point<f64> = a.into();
-        let b: Point<f64> = b.into();
-        let c: Point<f64> = c.into();
+        let a: Point<C> = a.into();
+        let b: Point<C> = b.into();
+        let c: Point<C> = c.into();
         let r = a
             .is_safe(position)
             .and(b.is_safe(position))
             .and(c.is_safe(position));
         if r.is_err() {
-            Err(Error::BadCoordinateValue(
-                position,
-                Geometry::Triangle(*self),
-            ))
+            Err(bad(position, Geometry::Triangle(*self)))
         } else {
             Ok(())
         }
     }
 }
 
-impl IsSafe for Polygon<f64> {
+impl<C: CoordFloat> IsSafe for Polygon<C> {
     fn is_safe(&self, position: usize) -> Result<(), Error> {
         if self.exterior().num_coords() < 3 {
             return Err(Error::PolygonExteriorTooSmall(position));
         }
         for line_string in std::iter::once(self.exterior()).chain(self.interiors().iter()) {
             if line_string.is_safe(position).is_err() {
-                return Err(Error::BadCoordinateValue(
-                    position,
-                    Geometry::Polygon(self.clone()),
-                ));
+                return Err(bad(position, Geometry::Polygon(self.clone())));
             }
         }
         Ok(())
     }
 }
 
+// Multi-geometries are safe when they have at least one member and
+// every member is safe. The emptiness check matters for the same
+// reason as the length checks above: the multi-geometry Relates
+// impls use all() over members. A member with a bad coordinate is
+// reported with the whole multi-geometry so the caller can find it;
+// the size errors only carry the index so we pass them through.
+macro_rules! multi_is_safe {
+    ($Multi:ident) => {
+        impl<C: CoordFloat> IsSafe for $Multi<C> {
+            fn is_safe(&self, position: usize) -> Result<(), Error> {
+                if self.0.is_empty() {
+                    return Err(Error::EmptyMultiGeometry(position));
+                }
+                for member in self.0.iter() {
+                    match member.is_safe(position) {
+                        Err(Error::BadCoordinateValue(_, _)) => {
+                            return Err(bad(position, Geometry::$Multi(self.clone())))
+                        }
+                        Err(e) => return Err(e),
+                        Ok(()) => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+multi_is_safe!(MultiPoint);
+multi_is_safe!(MultiLineString);
+multi_is_safe!(MultiPolygon);
+
+// An index we didn't build ourselves (one read back from a file, say)
+// could hold anything, and the join code indexes straight into the
+// per-type Vecs with the ids in the R-trees, so we check everything
+// it relies on before handing the index out.
+#[cfg(feature = "serde")]
+impl<C: CoordFloat> SpatialIndex<'_, C> {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if let Some(error) = self.config.validate() {
+            return Err(error.to_string());
+        }
+        if !self.distances.is_empty() && self.distances.len() != self.next_index {
+            return Err(format!(
+                "{} distances for {} indexes",
+                self.distances.len(),
+                self.next_index
+            ));
+        }
+        if let Some(d) = self
+            .distances
+            .iter()
+            .find(|d| !(d.is_finite() && **d >= 0.))
+        {
+            return Err(format!("bad distance {}", d));
+        }
+
+        let mut all_indexes = vec![];
+        check_one(
+            &self.small.geos.points,
+            &self.small.indexes.points,
+            &self.point_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.lines,
+            &self.small.indexes.lines,
+            &self.line_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.polys,
+            &self.small.indexes.polys,
+            &self.poly_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.line_strings,
+            &self.small.indexes.line_strings,
+            &self.ls_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.rects,
+            &self.small.indexes.rects,
+            &self.rect_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.tris,
+            &self.small.indexes.tris,
+            &self.tri_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.multi_points,
+            &self.small.indexes.multi_points,
+            &self.multi_point_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.multi_line_strings,
+            &self.small.indexes.multi_line_strings,
+            &self.multi_ls_tree,
+            &mut all_indexes,
+        )?;
+        check_one(
+            &self.small.geos.multi_polys,
+            &self.small.indexes.multi_polys,
+            &self.multi_poly_tree,
+            &mut all_indexes,
+        )?;
+
+        all_indexes.sort_unstable();
+        if let Some(pair) = all_indexes.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("index {} used twice", pair[0]));
+        }
+        match all_indexes.last() {
+            Some(&last) if last >= self.next_index => Err(format!(
+                "index {} but the next index is {}",
+                last, self.next_index
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn check_one<'a, S>(
+    geos: &[S],
+    indexes: &Indexes,
+    tree: &RTree<FakeRegion<S::Coord>>,
+    all_indexes: &mut Vec<usize>,
+) -> Result<(), String>
+where
+    S: Stored<'a>,
+    S::Geo: IsSafe,
+{
+    // A damaged Range could claim to hold billions of indexes, so we
+    // count before collecting any.
+    let count = indexes.iter().take(geos.len() + 1).count();
+    if count != geos.len() {
+        return Err(format!(
+            "wrong number of indexes for {} geometries",
+            geos.len()
+        ));
+    }
+    if let Indexes::Moved { range, at, of } = indexes {
+        let consistent = at.len() == of.len()
+            && at.iter().all(|(position, index)| {
+                *position < range.len() && of.get(index) == Some(position)
+            });
+        if !consistent {
+            return Err("moved indexes don't match up".to_string());
+        }
+    }
+    all_indexes.extend(indexes.iter());
+    for (geo, index) in geos.iter().zip(indexes.iter()) {
+        geo.geo().is_safe(index).map_err(|e| e.to_string())?;
+    }
+
+    // Every geometry needs at least one entry in the tree or joins
+    // would never find it; with `Config::geographic` some have two.
+    let mut in_tree = vec![false; geos.len()];
+    for region in tree.iter() {
+        match in_tree.get_mut(region.id) {
+            Some(seen) => *seen = true,
+            None => {
+                return Err(format!(
+                    "tree entry for geometry {} of {}",
+                    region.id,
+                    geos.len()
+                ))
+            }
+        }
+    }
+    match in_tree.iter().position(|seen| !seen) {
+        Some(position) => Err(format!("geometry {} missing from its tree", position)),
+        None => Ok(()),
+    }
+}
+
 // //<T as TryInto<SplitGeoSeq>>::Error
 // impl From<std::convert::Infallible> for Error {
 //     fn from(t: std::convert::Infallible) -> Self {